@@ -0,0 +1,120 @@
+//! Pluggable policies for retrying transactions that fail to commit.
+//!
+//! By default, a transaction that conflicts spins, then yields, and eventually prevents every other
+//! thread from committing until it commits (starvation). A [`ContentionManager`] set on a
+//! [`ThreadKey`](crate::thread_key::ThreadKey) replaces that policy for every transaction run by
+//! the thread.
+//!
+//! # Examples
+//!
+//! Exponential backoff, serializing the transaction after a number of failed attempts:
+//!
+//! ```
+//! use swym::{
+//!     contention::{Backoff, Conflict, ContentionManager},
+//!     tcell::TCell,
+//!     thread_key,
+//! };
+//!
+//! struct Exponential {
+//!     max_attempts: u32,
+//! }
+//!
+//! impl ContentionManager for Exponential {
+//!     fn on_conflict(&mut self, conflict: &Conflict) -> Backoff {
+//!         if conflict.attempts() >= self.max_attempts {
+//!             Backoff::Irrevocable
+//!         } else if conflict.attempts() <= 6 {
+//!             Backoff::Spin(1 << conflict.attempts())
+//!         } else {
+//!             Backoff::Yield
+//!         }
+//!     }
+//! }
+//!
+//! let thread_key = thread_key::get();
+//! thread_key.set_contention_manager(Exponential { max_attempts: 10 });
+//!
+//! let x = TCell::new(0);
+//! thread_key.rw(|tx| Ok(x.set(tx, 1)?));
+//! # drop(thread_key.take_contention_manager());
+//! ```
+
+use core::fmt::{self, Debug, Formatter};
+use std::time::Duration;
+
+/// Decides how a thread waits before retrying a transaction that failed.
+///
+/// Callbacks are run while the thread is inside of a transaction, so they must not start
+/// transactions of their own.
+pub trait ContentionManager {
+    /// Called each time a transaction fails, before it is retried. Returns how the thread should
+    /// wait before the next attempt.
+    fn on_conflict(&mut self, conflict: &Conflict) -> Backoff;
+
+    /// Called after a transaction completes successfully.
+    #[inline]
+    fn on_commit(&mut self) {}
+
+    /// Called after the thread was blocked from committing by another thread that was starving, or
+    /// running an irrevocable transaction.
+    #[inline]
+    fn on_starvation(&mut self) {}
+}
+
+impl Debug for dyn ContentionManager {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter.pad("ContentionManager { .. }")
+    }
+}
+
+/// How a thread waits before retrying a transaction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Backoff {
+    /// Busy waits for the specified number of iterations.
+    Spin(u32),
+
+    /// Yields the remainder of the threads timeslice to the OS.
+    Yield,
+
+    /// Sleeps the thread for the specified duration.
+    ///
+    /// The thread is unpinned while it sleeps, so that other threads can keep collecting garbage.
+    /// Nested transactions can not unpin the thread, and yield instead.
+    Sleep(Duration),
+
+    /// Prevents every other thread from starting a commit until the transaction commits.
+    ///
+    /// Unlike [`rw_irrevocable`](crate::thread_key::ThreadKey::rw_irrevocable), the transaction
+    /// may still be retried if it conflicts with a commit that was already in progress.
+    ///
+    /// Nested transactions yield instead, rather than blocking commits in the middle of the
+    /// enclosing transaction. The enclosing transaction becomes irrevocable if the contention
+    /// manager asks for it again when it retries.
+    Irrevocable,
+}
+
+/// Information about a transaction that failed, passed to [`ContentionManager::on_conflict`].
+#[derive(Copy, Clone, Debug)]
+pub struct Conflict {
+    pub(crate) attempts: u32,
+    pub(crate) elapsed:  usize,
+}
+
+impl Conflict {
+    /// Returns the number of times the transaction has failed, including this failure.
+    #[inline]
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Returns the number of transactions committed by other threads since the first failed
+    /// attempt of this transaction.
+    ///
+    /// Older transactions having a higher value makes this suitable for timestamp based priority
+    /// schemes.
+    #[inline]
+    pub fn elapsed_commits(&self) -> usize {
+        self.elapsed
+    }
+}
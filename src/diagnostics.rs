@@ -0,0 +1,189 @@
+//! Opt-in details about why transactions conflict.
+//!
+//! Diagnostics are disabled by default. Once [`enable`]d, every conflict records which `TCell`
+//! caused it and why, as the [`last_conflict`] of the thread that observed it. Conflicts returned
+//! to a transaction as an [`Error`] also carry their own [`details`](Error::details). Recording
+//! only happens on the slow path of a conflict, so enabling diagnostics does not slow down
+//! transactions that succeed.
+//!
+//! # Examples
+//!
+//! ```
+//! use swym::{diagnostics, tcell::TCell, thread_key};
+//!
+//! static HOT: TCell<usize> = TCell::new(0);
+//!
+//! diagnostics::enable();
+//! diagnostics::set_name(&HOT, "hot counter");
+//!
+//! thread_key::get().rw(|tx| {
+//!     let next = HOT.get(tx, Default::default())? + 1;
+//!     Ok(HOT.set(tx, next)?)
+//! });
+//!
+//! if let Some(conflict) = diagnostics::last_conflict() {
+//!     println!("{:?} conflicted on {:?}", conflict.kind(), conflict.name());
+//! }
+//! # diagnostics::disable();
+//! ```
+
+use crate::{
+    internal::{epoch::EpochLock, tcell_erased::TCellErased},
+    profile,
+    tcell::TCell,
+    tx::Error,
+};
+use core::{
+    cell::Cell,
+    sync::atomic::{AtomicBool, Ordering::Relaxed},
+};
+use fxhash::FxHashMap;
+use parking_lot::Mutex;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static::lazy_static! {
+    /// Names keyed by the address of the `TCellErased` they were assigned to.
+    static ref NAMES: Mutex<FxHashMap<usize, &'static str>> = Mutex::new(FxHashMap::default());
+}
+
+thread_local! {
+    static LAST_CONFLICT: Cell<Option<ConflictDetails>> = Cell::new(None);
+}
+
+/// Starts recording the details of conflicts.
+#[inline]
+pub fn enable() {
+    ENABLED.store(true, Relaxed)
+}
+
+/// Stops recording the details of conflicts. Previously recorded details are kept.
+#[inline]
+pub fn disable() {
+    ENABLED.store(false, Relaxed)
+}
+
+/// Returns true if the details of conflicts are being recorded.
+#[inline]
+pub fn is_enabled() -> bool {
+    ENABLED.load(Relaxed)
+}
+
+/// Assigns a name to `tcell`, which is reported by conflicts involving it, and groups it with other
+/// `TCell`s of the same name in the [`profile`](crate::profile).
+///
+/// Names are associated with the address of the `TCell`, and are not removed when it is dropped.
+/// Call [`clear_name`] before dropping a named `TCell` that is not `'static`.
+pub fn set_name<T>(tcell: &TCell<T>, name: &'static str) {
+    drop(NAMES.lock().insert(address(&tcell.erased), name))
+}
+
+/// Removes the name assigned to `tcell`, if any.
+pub fn clear_name<T>(tcell: &TCell<T>) {
+    drop(NAMES.lock().remove(&address(&tcell.erased)))
+}
+
+/// Returns the details of the most recent conflict observed by the current thread while
+/// diagnostics were enabled.
+#[inline]
+pub fn last_conflict() -> Option<ConflictDetails> {
+    LAST_CONFLICT.with(Cell::get)
+}
+
+/// Why a transaction conflicted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ConflictKind {
+    /// A `TCell` was modified after the transaction started, and before the transaction accessed
+    /// it.
+    ReadValidation,
+
+    /// A `TCell` was locked by another thread that was committing.
+    LockHeld,
+
+    /// A `TCell` accessed by the transaction was modified before the transaction could commit.
+    CommitValidation,
+}
+
+/// The details of a conflict.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ConflictDetails {
+    kind:    ConflictKind,
+    address: usize,
+    name:    Option<&'static str>,
+}
+
+impl ConflictDetails {
+    /// Returns why the transaction conflicted.
+    #[inline]
+    pub fn kind(&self) -> ConflictKind {
+        self.kind
+    }
+
+    /// Returns an address identifying the `TCell` that caused the conflict.
+    ///
+    /// The address is only meaningful for comparisons while the `TCell` is alive.
+    #[inline]
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    /// Returns the name assigned to the `TCell` that caused the conflict, if any.
+    #[inline]
+    pub fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+}
+
+#[inline]
+fn address(tcell: &TCellErased) -> usize {
+    tcell as *const _ as usize
+}
+
+/// Returns the name assigned to the `TCellErased` at `address`, if any.
+pub(crate) fn name_of(address: usize) -> Option<&'static str> {
+    NAMES.lock().get(&address).cloned()
+}
+
+#[inline(never)]
+#[cold]
+fn record(kind: ConflictKind, tcell: &TCellErased) -> ConflictDetails {
+    let address = address(tcell);
+    let name = name_of(address);
+    let details = ConflictDetails {
+        kind,
+        address,
+        name,
+    };
+    LAST_CONFLICT.with(move |last| last.set(Some(details)));
+    details
+}
+
+/// Records a conflict caused by accessing `tcell` during a transaction, and returns the error.
+#[inline]
+pub(crate) fn access_conflict(tcell: &TCellErased) -> Error {
+    profile::conflict(tcell);
+    let kind = if tcell.current_epoch.is_locked(Relaxed) {
+        ConflictKind::LockHeld
+    } else {
+        ConflictKind::ReadValidation
+    };
+    if unlikely!(is_enabled()) {
+        Error::with_details(record(kind, tcell))
+    } else {
+        Error::conflict(kind)
+    }
+}
+
+/// Records a conflict caused by failing to lock, or validate `epoch_lock` at commit time.
+#[inline]
+pub(crate) fn commit_conflict(epoch_lock: &EpochLock) {
+    if unlikely!(is_enabled()) {
+        let tcell = TCellErased::from_epoch_lock(epoch_lock);
+        let kind = if epoch_lock.is_locked(Relaxed) {
+            ConflictKind::LockHeld
+        } else {
+            ConflictKind::CommitValidation
+        };
+        drop(record(kind, tcell))
+    }
+}
@@ -0,0 +1,103 @@
+//! Read write transactions that wait on [`AWAIT_RETRY`](crate::tx::Status::AWAIT_RETRY) by
+//! returning `Pending` to an async executor, instead of parking the thread.
+//!
+//! # Examples
+//!
+//! ```
+//! use swym::{future, tcell::TCell, tx::Status};
+//!
+//! let ready = TCell::new(false);
+//!
+//! // Resolves once a transaction sets `ready` to true.
+//! let wait = future::rw(|tx| {
+//!     if ready.get(tx, Default::default())? {
+//!         Ok(())
+//!     } else {
+//!         Err(Status::AWAIT_RETRY)
+//!     }
+//! });
+//! # drop(wait);
+//! ```
+
+use crate::{internal::parking, rw::RwTx, thread_key, tx::Status};
+use core::{
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Creates a future that runs `f` as a read write transaction each time it is polled.
+///
+/// If `f` returns [`AWAIT_RETRY`](crate::tx::Status::AWAIT_RETRY), the future returns `Pending`,
+/// and the task is woken once another transaction commits a write to a `TCell` that `f` read.
+/// Otherwise, the future resolves to the output of the committed transaction.
+///
+/// # Panics
+///
+/// Polling the future panics if it is polled from within another transaction.
+#[inline]
+pub fn rw<'tcell, F, O>(f: F) -> Rw<'tcell, F>
+where
+    F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+{
+    Rw {
+        f,
+        registration: None,
+        phantom: PhantomData,
+    }
+}
+
+/// Future returned by [`rw`].
+pub struct Rw<'tcell, F> {
+    f: F,
+
+    /// The registration of the task, if it is waiting for a commit.
+    registration: Option<parking::Registration>,
+
+    phantom: PhantomData<fn(&mut RwTx<'tcell>)>,
+}
+
+// `f` is never pinned.
+impl<'tcell, F> Unpin for Rw<'tcell, F> {}
+
+impl<'tcell, F> Debug for Rw<'tcell, F> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("Rw")
+            .field("registration", &self.registration)
+            .finish()
+    }
+}
+
+impl<'tcell, F> Drop for Rw<'tcell, F> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(registration) = self.registration.take() {
+            parking::deregister_task(registration)
+        }
+    }
+}
+
+impl<'tcell, F, O> Future for Rw<'tcell, F>
+where
+    F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+{
+    type Output = O;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<O> {
+        let this = self.get_mut();
+        if let Some(registration) = this.registration.take() {
+            parking::deregister_task(registration)
+        }
+        match thread_key::get().rw_or_register(&mut this.f, cx.waker()) {
+            Ok(o) => Poll::Ready(o),
+            Err(registration) => {
+                this.registration = Some(registration);
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -8,14 +8,17 @@ pub mod alloc;
 pub mod phoenix_tls;
 
 pub mod bloom;
+mod buckets;
 mod commit;
 mod gc;
-mod parking;
+mod hooks;
 mod starvation;
 
 pub mod epoch;
+pub mod parking;
 pub mod read_log;
 pub mod tcell_erased;
 pub mod thread;
 pub mod usize_aligned;
+pub mod versions;
 pub mod write_log;
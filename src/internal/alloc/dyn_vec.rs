@@ -148,6 +148,42 @@ macro_rules! dyn_vec_decl {
                 self.data.clear();
             }
 
+            /// Drops every element starting at `word_len`. `word_len` must be the start of an
+            /// element, or the current `word_len`.
+            #[inline]
+            $vis unsafe fn truncate(&mut self, word_len: usize) {
+                self.drain_from(word_len).for_each(|_| {})
+            }
+
+            /// Removes every element starting at `word_len`, and returns them in an iterator.
+            /// `word_len` must be the start of an element, or the current `word_len`.
+            #[inline]
+            $vis unsafe fn drain_from(&mut self, word_len: usize) -> $crate::internal::alloc::dyn_vec::Drain<'_, dyn $trait + 'a> {
+                debug_assert!(word_len <= self.data.len(), "attempt to grow a dynvec by truncating");
+                let slice: &mut [_] = &mut self.data[word_len..];
+                let raw: ::core::ptr::NonNull<_> = slice.into();
+                self.data.truncate(word_len);
+
+                $crate::internal::alloc::dyn_vec::Drain::new((*raw.as_ptr()).iter_mut())
+            }
+
+            /// Moves every element of `other` starting at `word_len` to the end of `self`.
+            /// `word_len` must be the start of an element, or the current `word_len` of `other`.
+            #[inline]
+            $vis unsafe fn append_from(&mut self, other: &mut Self, word_len: usize) {
+                debug_assert!(word_len <= other.data.len(), "attempt to grow a dynvec by truncating");
+                self.data.extend_from_slice(&other.data[word_len..]);
+                other.data.truncate(word_len);
+            }
+
+            /// Forgets every element starting at `word_len`. `word_len` must be the start of an
+            /// element, or the current `word_len`.
+            #[inline]
+            $vis unsafe fn truncate_no_drop(&mut self, word_len: usize) {
+                debug_assert!(word_len <= self.data.len(), "attempt to grow a dynvec by truncating");
+                self.data.truncate(word_len);
+            }
+
             #[inline]
             $vis fn iter(&self) -> $crate::internal::alloc::dyn_vec::Iter<'_, dyn $trait> {
                 unsafe {
@@ -569,4 +605,29 @@ mod test {
 
         assert_eq!(DROP_COUNT.with(|x| x.get()), 85);
     }
+
+    #[test]
+    fn append_from() {
+        let mut v = AnyDynVec::with_capacity(0);
+        let mut other = AnyDynVec::with_capacity(0);
+        v.push(1usize);
+        other.push(2usize);
+        let word_len = other.word_len();
+        other.push(vec![3usize]);
+        other.push(4usize);
+
+        unsafe { v.append_from(&mut other, word_len) };
+
+        let mut iter = other.iter();
+        assert_eq!(iter.next().unwrap().as_any().downcast_ref(), Some(&2usize));
+        assert!(iter.next().is_none());
+        let mut iter = v.iter();
+        assert_eq!(iter.next().unwrap().as_any().downcast_ref(), Some(&1usize));
+        assert_eq!(
+            iter.next().unwrap().as_any().downcast_ref(),
+            Some(&vec![3usize])
+        );
+        assert_eq!(iter.next().unwrap().as_any().downcast_ref(), Some(&4usize));
+        assert!(iter.next().is_none());
+    }
 }
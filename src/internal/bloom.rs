@@ -45,7 +45,7 @@ impl<'tcell, K> Bloom<'tcell, K> {
     }
 
     #[inline]
-    fn has_overflowed(&self) -> bool {
+    pub fn has_overflowed(&self) -> bool {
         self.filter.get() == OVERFLOWED
     }
 
@@ -124,6 +124,18 @@ impl<'tcell, K> Bloom<'tcell, K> {
         self.overflow().get(&(key as _)).cloned()
     }
 
+    #[inline]
+    pub fn overflow_insert(&mut self, key: &'tcell K, value: usize) -> Option<usize> {
+        debug_assert!(self.has_overflowed());
+        self.overflow().insert(key as _, value)
+    }
+
+    #[inline]
+    pub fn overflow_retain(&mut self, f: impl FnMut(&*const K, &mut usize) -> bool) {
+        debug_assert!(self.has_overflowed());
+        self.overflow().retain(f)
+    }
+
     #[inline]
     pub fn overflow_entry(&mut self, key: &K) -> Entry<'_, *const K, usize> {
         debug_assert!(self.has_overflowed());
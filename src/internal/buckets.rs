@@ -0,0 +1,47 @@
+//! Fixed size tables of locks, indexed by hashing an address.
+//!
+//! Like the hash table of `parking_lot_core`, this spreads state keyed by address over many locks,
+//! so that threads working on unrelated addresses rarely contend.
+
+use crossbeam_utils::CachePadded;
+use parking_lot::{Mutex, MutexGuard};
+
+/// The number of buckets in a table. Must be a power of two.
+const BUCKET_COUNT: usize = 64;
+
+pub struct Buckets<T> {
+    buckets: Box<[CachePadded<Mutex<T>>]>,
+}
+
+impl<T: Default> Buckets<T> {
+    pub fn new() -> Self {
+        Buckets {
+            buckets: (0..BUCKET_COUNT).map(|_| Default::default()).collect(),
+        }
+    }
+}
+
+impl<T> Buckets<T> {
+    /// Returns the index of the bucket `address` belongs to.
+    #[inline]
+    pub fn index(&self, address: usize) -> usize {
+        // Fibonacci hashing, as in `parking_lot_core`.
+        #[cfg(target_pointer_width = "64")]
+        const MULTIPLIER: usize = 0x9E37_79B9_7F4A_7C15;
+        #[cfg(target_pointer_width = "32")]
+        const MULTIPLIER: usize = 0x9E37_79B9;
+        address.wrapping_mul(MULTIPLIER) >> (0usize.count_zeros() - BUCKET_COUNT.trailing_zeros())
+    }
+
+    /// Locks the bucket at `index`.
+    #[inline]
+    pub fn lock_index(&self, index: usize) -> MutexGuard<'_, T> {
+        self.buckets[index].lock()
+    }
+
+    /// Locks the bucket `address` belongs to.
+    #[inline]
+    pub fn lock(&self, address: usize) -> MutexGuard<'_, T> {
+        self.lock_index(self.index(address))
+    }
+}
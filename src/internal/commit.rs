@@ -1,10 +1,13 @@
 use crate::{
+    diagnostics,
     internal::{
         epoch::{EpochLock, ParkStatus, QuiesceEpoch, EPOCH_CLOCK},
-        thread::{Logs, PinRw},
+        tcell_erased::TCellErased,
+        thread::{Logs, PinMutRef, PinRw},
+        versions::{self, Pending},
         write_log::{WriteEntry, WriteLog},
     },
-    stats,
+    profile, stats,
 };
 use core::{
     mem,
@@ -15,6 +18,27 @@ use swym_htm::{BoundedHtxErr, HardwareTx};
 
 const MAX_HTX_RETRIES: u8 = 3;
 
+/// A successful commit.
+#[derive(Copy, Clone, Debug)]
+pub struct Committed {
+    /// The epoch the transaction committed at.
+    pub epoch: QuiesceEpoch,
+
+    /// Whether the writes were published by a hardware transaction.
+    pub htm: bool,
+}
+
+/// Copies the values about to be overwritten, if any snapshot transactions might read them. Every
+/// `TCell` in the write log must be locked.
+#[inline]
+unsafe fn keep_versions(write_log: &WriteLog<'_>) -> Option<Pending> {
+    if unlikely!(versions::is_active()) {
+        Some(Pending::capture(write_log))
+    } else {
+        None
+    }
+}
+
 impl<'tcell> Logs<'tcell> {
     #[inline]
     pub unsafe fn remove_writes_from_reads(&mut self) {
@@ -68,7 +92,7 @@ impl<'tcell> dyn WriteEntry + 'tcell {
 
 impl<'tcell> WriteLog<'tcell> {
     #[inline]
-    unsafe fn publish(&self, sync_epoch: QuiesceEpoch) {
+    pub unsafe fn publish(&self, sync_epoch: QuiesceEpoch) {
         self.epoch_locks()
             .for_each(|epoch_lock| epoch_lock.unlock_publish(sync_epoch))
     }
@@ -84,7 +108,7 @@ impl<'tcell> WriteLog<'tcell> {
     }
 
     #[inline]
-    unsafe fn perform_writes(&self) {
+    pub unsafe fn perform_writes(&self) {
         atomic::fence(Release);
         for entry in self.write_entries() {
             entry.perform_write();
@@ -94,18 +118,22 @@ impl<'tcell> WriteLog<'tcell> {
 
 impl<'tx, 'tcell> PinRw<'tx, 'tcell> {
     /// The commit algorithm, called after user code has finished running without returning an
-    /// error. Returns true if the transaction committed successfully.
+    /// error. Returns `None` if the transaction failed to commit.
+    ///
+    /// Transactions without writes commit at the epoch they were pinned at.
     #[inline]
-    pub fn commit(self) -> bool {
+    pub fn commit(self) -> Option<Committed> {
         if likely!(!self.logs().write_log.is_empty()) {
             self.commit_slow()
         } else {
-            unsafe { self.commit_empty_write_log() }
+            let epoch = unsafe { self.commit_empty_write_log() };
+            Some(Committed { epoch, htm: false })
         }
     }
 
     #[inline]
-    unsafe fn commit_empty_write_log(self) -> bool {
+    unsafe fn commit_empty_write_log(self) -> QuiesceEpoch {
+        let pin_epoch = self.pin_epoch();
         let (_, logs, progress) = self.into_inner();
         progress.progressed();
         // RwTx validates reads as they occur. As a result, if there are no writes, then we have
@@ -121,12 +149,16 @@ impl<'tx, 'tcell> PinRw<'tx, 'tcell> {
             "Garbage queued, without any writes!"
         );
         logs.read_log.clear();
-        true
+        pin_epoch
     }
 
     #[inline]
     fn start_htx(&self, retry_count: &mut u8) -> Result<HardwareTx, BoundedHtxErr> {
-        if swym_htm::htm_supported() && self.logs().write_log.word_len() >= 9 {
+        // Hardware transactions cannot keep the overwritten values for snapshots.
+        if swym_htm::htm_supported()
+            && self.logs().write_log.word_len() >= 9
+            && !versions::is_active()
+        {
             HardwareTx::bounded(retry_count, MAX_HTX_RETRIES)
         } else {
             Err(BoundedHtxErr::SoftwareFallback)
@@ -134,28 +166,33 @@ impl<'tx, 'tcell> PinRw<'tx, 'tcell> {
     }
 
     #[inline]
-    fn commit_slow(self) -> bool {
+    fn commit_slow(self) -> Option<Committed> {
         let mut retry_count = 0;
         self.progress().wait_for_starvers();
         match self.start_htx(&mut retry_count) {
             Ok(htx) => {
-                let success = self.commit_hard(htx);
+                let success = {
+                    let _timer = stats::htm_commit_time();
+                    self.commit_hard(htx)
+                };
                 stats::htm_conflicts(retry_count as _);
-                success
+                success.map(|epoch| Committed { epoch, htm: true })
             }
             Err(BoundedHtxErr::SoftwareFallback) => {
                 stats::htm_conflicts(retry_count as _);
+                let _timer = stats::software_commit_time();
                 self.commit_soft()
+                    .map(|epoch| Committed { epoch, htm: false })
             }
             Err(BoundedHtxErr::AbortOrConflict) => {
                 stats::htm_abort(retry_count as _);
-                false
+                None
             }
         }
     }
 
     #[inline(never)]
-    fn commit_hard(self, htx: HardwareTx) -> bool {
+    fn commit_hard(self, htx: HardwareTx) -> Option<QuiesceEpoch> {
         unsafe {
             let (synch, logs, progress) = self.into_inner();
             let current = synch.current_epoch();
@@ -167,22 +204,21 @@ impl<'tx, 'tcell> PinRw<'tx, 'tcell> {
             let sync_epoch = EPOCH_CLOCK.fetch_and_tick();
             logs.write_log.publish(sync_epoch.next());
 
+            if unlikely!(park_status == ParkStatus::HasParked) {
+                crate::internal::parking::unpark(&logs.write_log);
+            }
             logs.read_log.clear();
             logs.write_log.clear_no_drop();
-
             progress.progressed();
-            if unlikely!(park_status == ParkStatus::HasParked) {
-                crate::internal::parking::unpark();
-            }
             logs.garbage.seal_with_epoch(synch, sync_epoch);
 
-            true
+            Some(sync_epoch.next())
         }
     }
 
     /// This performs a lot of lock cmpxchgs, so inlining doesn't really doesn't give us much.
     #[inline(never)]
-    fn commit_soft(mut self) -> bool {
+    fn commit_soft(mut self) -> Option<QuiesceEpoch> {
         // Locking the write log, would cause validation of any reads to the same TCell to fail.
         // So we remove all TCells in the read log that are also in the write log, and assume all
         // TCells in the write log were also in the read log.
@@ -213,7 +249,7 @@ impl<'tx, 'tcell> PinRw<'tx, 'tcell> {
     }
 
     #[inline]
-    unsafe fn write_log_lock_success(self, park_status: ParkStatus) -> bool {
+    unsafe fn write_log_lock_success(self, park_status: ParkStatus) -> Option<QuiesceEpoch> {
         // after locking the write set, ensure nothing in the read set has been modified.
         if likely!(self.logs().read_log.validate_reads(self.pin_epoch())) {
             // The transaction can no longer fail, so proceed to modify and publish the TCells in
@@ -226,18 +262,22 @@ impl<'tx, 'tcell> PinRw<'tx, 'tcell> {
 
     #[cold]
     #[inline(never)]
-    fn write_log_lock_failure(self, unlock_until: *const EpochLock) -> bool {
+    fn write_log_lock_failure(self, unlock_until: *const EpochLock) -> Option<QuiesceEpoch> {
+        let epoch_lock = unsafe { &*unlock_until };
+        diagnostics::commit_conflict(epoch_lock);
+        profile::lock_failure(epoch_lock);
         self.logs()
             .write_log
             .epoch_locks()
             .take_while(move |&e| !ptr::eq(e, unlock_until))
             .for_each(|epoch_lock| unsafe { epoch_lock.unlock_undo() });
-        false
+        None
     }
 
     #[inline]
-    unsafe fn validation_success(self, park_status: ParkStatus) -> bool {
+    unsafe fn validation_success(self, park_status: ParkStatus) -> Option<QuiesceEpoch> {
         let (synch, logs, progress) = self.into_inner();
+        let versions = keep_versions(&logs.write_log);
 
         // The writes must be performed before the EPOCH_CLOCK is tick'ed.
         // Reads can get away with performing less work with this ordering.
@@ -248,29 +288,93 @@ impl<'tx, 'tcell> PinRw<'tx, 'tcell> {
             synch.current_epoch() <= sync_epoch,
             "`EpochClock::fetch_and_tick` returned an earlier time than expected"
         );
+        if let Some(versions) = versions {
+            versions.publish(sync_epoch.next(), &mut logs.garbage)
+        }
 
         // unlocks everything in the write lock and sets the TCell epochs to sync_epoch.next()
         logs.write_log.publish(sync_epoch.next());
 
+        if unlikely!(park_status == ParkStatus::HasParked) {
+            crate::internal::parking::unpark(&logs.write_log);
+        }
         logs.read_log.clear();
         logs.write_log.clear_no_drop();
         progress.progressed();
-        if unlikely!(park_status == ParkStatus::HasParked) {
-            crate::internal::parking::unpark();
-        }
         logs.garbage.seal_with_epoch(synch, sync_epoch);
 
-        true
+        Some(sync_epoch.next())
     }
 
     #[inline(never)]
     #[cold]
-    unsafe fn validation_failure(self) -> bool {
+    unsafe fn validation_failure(self) -> Option<QuiesceEpoch> {
+        if diagnostics::is_enabled() || profile::is_enabled() {
+            let pin_epoch = self.pin_epoch();
+            let invalid = self
+                .logs()
+                .read_log
+                .epoch_locks()
+                .find(|epoch_lock| !pin_epoch.read_write_valid_lockable(epoch_lock));
+            if let Some(epoch_lock) = invalid {
+                diagnostics::commit_conflict(epoch_lock);
+                profile::conflict(TCellErased::from_epoch_lock(epoch_lock))
+            }
+        }
         // on fail unlock the write set
         self.logs()
             .write_log
             .epoch_locks()
             .for_each(|epoch_lock| epoch_lock.unlock_undo());
-        false
+        None
+    }
+}
+
+impl<'tx, 'tcell> PinMutRef<'tx, 'tcell> {
+    /// The commit algorithm for irrevocable transactions. Every TCell in the read log and write log
+    /// must be locked by the current thread, and the current thread must hold the starvation lock.
+    ///
+    /// Returns the epoch the transaction committed at.
+    #[inline(never)]
+    pub unsafe fn commit_irrevocable(self, park_status: ParkStatus) -> QuiesceEpoch {
+        let pin_epoch = self.pin_epoch();
+        let (synch, logs, progress) = self.into_inner();
+        logs.remove_writes_from_reads();
+
+        let sync_epoch = if likely!(!logs.write_log.is_empty()) {
+            let versions = keep_versions(&logs.write_log);
+            logs.write_log.perform_writes();
+            let sync_epoch = EPOCH_CLOCK.fetch_and_tick();
+            if let Some(versions) = versions {
+                versions.publish(sync_epoch.next(), &mut logs.garbage)
+            }
+            logs.write_log.publish(sync_epoch.next());
+            Some(sync_epoch)
+        } else {
+            None
+        };
+        logs.read_log
+            .epoch_locks()
+            .for_each(|epoch_lock| epoch_lock.unlock_undo());
+
+        if unlikely!(park_status == ParkStatus::HasParked) {
+            crate::internal::parking::unpark(&logs.write_log);
+        }
+        logs.read_log.clear();
+        logs.write_log.clear_no_drop();
+        progress.progressed();
+        match sync_epoch {
+            Some(sync_epoch) => {
+                logs.garbage.seal_with_epoch(synch, sync_epoch);
+                sync_epoch.next()
+            }
+            None => {
+                debug_assert!(
+                    logs.garbage.is_speculative_bag_empty(),
+                    "Garbage queued, without any writes!"
+                );
+                pin_epoch
+            }
+        }
     }
 }
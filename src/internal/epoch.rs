@@ -204,6 +204,49 @@ impl ParkStatus {
     }
 }
 
+/// The epochs during which a value was the current value of a `TCell`. The range starts at the
+/// epoch the value was published with, and ends at the epoch the value that replaced it was
+/// published with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EpochRange {
+    start: Storage,
+    end:   Storage,
+}
+
+impl EpochRange {
+    /// Creates a range starting at the epoch that `epoch_lock` was published with, and that has not
+    /// ended. The calling thread must hold the lock.
+    #[inline]
+    pub unsafe fn start_locked(epoch_lock: &EpochLock) -> Self {
+        let start = epoch_lock.load_raw(Relaxed).get();
+        debug_assert!(
+            lock_bit_set(start),
+            "`EpochRange::start_locked` called on an unlocked `EpochLock`"
+        );
+        EpochRange {
+            start: as_unlocked(start),
+            end:   INACTIVE_EPOCH,
+        }
+    }
+
+    /// Ends the range at `end`, the epoch that the replacement value was published with.
+    #[inline]
+    pub fn end(&mut self, end: QuiesceEpoch) {
+        debug_assert!(
+            end.read_write_valid_(self.start),
+            "`EpochRange` ended before it started"
+        );
+        self.end = end.get().get()
+    }
+
+    /// Returns true if a thread pinned at `epoch` reads the value that was current during this
+    /// range.
+    #[inline]
+    pub fn contains(self, epoch: QuiesceEpoch) -> bool {
+        epoch.read_write_valid_(self.start) && !epoch.read_write_valid_(self.end)
+    }
+}
+
 /// An atomic QuiesceEpoch, where the MSB is used as a lock. It should never contain the inactive
 /// epoch.
 ///
@@ -283,6 +326,16 @@ impl EpochLock {
         }
     }
 
+    /// Attempts to lock the EpochLock regardless of the epoch it contains, returning None if the
+    /// lock is already held.
+    ///
+    /// This is allowed to fail spuriously.
+    #[inline]
+    #[must_use]
+    pub fn try_lock_any(&self) -> Option<ParkStatus> {
+        self.try_lock(QuiesceEpoch::end_of_time())
+    }
+
     /// Attempts to acquire the lock, aborting the transaction on failure.
     #[inline]
     pub fn try_lock_htm(&self, htx: &HardwareTx, max_expected: QuiesceEpoch) -> ParkStatus {
@@ -1,4 +1,7 @@
-use crate::internal::usize_aligned::ForcedUsizeAligned;
+use crate::internal::{
+    hooks::{Hook, HookFn},
+    usize_aligned::ForcedUsizeAligned,
+};
 use core::{
     mem::{self, ManuallyDrop},
     ptr,
@@ -27,6 +30,11 @@ impl<T: 'static + Send> Queued<T> {
 pub trait FnOnceish {
     /// Unsafe to call more than once
     unsafe fn call(&mut self);
+
+    /// Discards garbage queued by a transaction that did not commit.
+    ///
+    /// Unsafe to call more than once, or after `call`.
+    unsafe fn abort(&mut self);
 }
 
 impl<T: 'static + Send> FnOnceish for Queued<T> {
@@ -42,4 +50,22 @@ impl<T: 'static + Send> FnOnceish for Queued<T> {
             ptr::drop_in_place::<T>(&mut self.to_drop as *mut _ as *mut T)
         }
     }
+
+    #[inline]
+    unsafe fn abort(&mut self) {
+        // the value is still owned by the `TCell` it was going to be removed from
+    }
+}
+
+// Closures queued by `ThreadGarbage::dispose_fn` run once the GC has collected their bag.
+impl<F: FnOnce() + Send + 'static> FnOnceish for HookFn<F> {
+    #[inline]
+    unsafe fn call(&mut self) {
+        Hook::call(self)
+    }
+
+    #[inline]
+    unsafe fn abort(&mut self) {
+        self.drop_uncalled()
+    }
 }
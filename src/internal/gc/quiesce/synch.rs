@@ -63,6 +63,7 @@ impl Synch {
     #[inline(never)]
     #[cold]
     pub(super) fn local_quiesce(&self, quiesce_epoch: QuiesceEpoch) {
+        let _timer = stats::gc_quiesce_time();
         // TODO: should backoff be a parameter?
         let backoff = Backoff::new();
         let mut should_park_count = 0;
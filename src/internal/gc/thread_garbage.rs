@@ -5,6 +5,7 @@ use crate::internal::{
         queued::{FnOnceish, Queued},
         quiesce::OwnedSynch,
     },
+    hooks::HookFn,
 };
 use core::mem::{self, ManuallyDrop};
 
@@ -118,6 +119,10 @@ pub struct ThreadGarbage {
 
     /// Store of unused bags from which new bags can be obtained.
     unused_bags: UnusedBags,
+
+    /// Whether the speculative bag might hold closures that have to be dropped if the transaction
+    /// does not commit.
+    speculative_fns: bool,
 }
 
 impl ThreadGarbage {
@@ -138,6 +143,7 @@ impl ThreadGarbage {
             speculative_bag,
             sealed_bags,
             unused_bags,
+            speculative_fns: false,
         }
     }
 
@@ -147,13 +153,49 @@ impl ThreadGarbage {
         self.speculative_bag.queued.is_empty()
     }
 
-    /// Leaks all the trash in the speculative bag.
+    /// Leaks all the trash in the speculative bag, and drops any closures queued with
+    /// `dispose_fn` without calling them.
     ///
     /// When running a transaction, trash is queued up speculatively. If the transaction fails, none
     /// of the garbage that was queued up should be collected.
     #[inline]
     pub fn abort_speculative_garbage(&mut self) {
-        self.speculative_bag.queued.clear_no_drop()
+        if unlikely!(self.speculative_fns) {
+            self.speculative_fns = false;
+            unsafe { self.abort_fns_since(0) }
+        } else {
+            self.speculative_bag.queued.clear_no_drop()
+        }
+    }
+
+    /// Returns the current length of the speculative bag in words, for use with
+    /// `abort_speculative_garbage_since`.
+    #[inline]
+    pub fn speculative_word_len(&self) -> usize {
+        self.speculative_bag.queued.word_len()
+    }
+
+    /// Leaks the trash that was queued after the speculative bag had a length of `word_len`.
+    ///
+    /// Used to discard the garbage of a nested transaction, while keeping the garbage of its
+    /// parents.
+    #[inline]
+    pub fn abort_speculative_garbage_since(&mut self, word_len: usize) {
+        unsafe {
+            if unlikely!(self.speculative_fns) {
+                self.abort_fns_since(word_len)
+            } else {
+                self.speculative_bag.queued.truncate_no_drop(word_len)
+            }
+        }
+    }
+
+    #[inline(never)]
+    #[cold]
+    unsafe fn abort_fns_since(&mut self, word_len: usize) {
+        for mut queued in self.speculative_bag.queued.drain_from(word_len) {
+            queued.abort()
+        }
     }
 
     /// Used to help move allocations out of the fast path.
@@ -180,6 +222,16 @@ impl ThreadGarbage {
             .push_unchecked(Queued::new(value))
     }
 
+    /// Queues up `f` to be called once no transaction can observe the state from before the current
+    /// transaction, should it succeed. Otherwise `f` is dropped without being called.
+    #[inline]
+    pub fn dispose_fn<F: FnOnce() + Send + 'static>(&mut self, f: F) {
+        if mem::needs_drop::<F>() {
+            self.speculative_fns = true
+        }
+        self.speculative_bag.queued.push(HookFn::new(f))
+    }
+
     /// Ends the speculative garbage queuing, and commits the current_bag's garbage to be collected
     /// at some point. May modify synch's current_epoch.
     ///
@@ -198,6 +250,7 @@ impl ThreadGarbage {
     /// If the current bag was not empty, we have real work to do.
     #[inline(never)]
     unsafe fn seal_with_epoch_slow(&mut self, synch: &OwnedSynch, quiesce_epoch: QuiesceEpoch) {
+        self.speculative_fns = false;
         let new_bag = self.unused_bags.open_bag_unchecked();
         let prev_bag = mem::replace(&mut self.speculative_bag, new_bag);
         let sealed_bag = prev_bag.seal(quiesce_epoch);
@@ -0,0 +1,189 @@
+//! Closures registered by a transaction to run once it either commits or aborts.
+
+use crate::internal::usize_aligned::ForcedUsizeAligned;
+use core::{
+    mem::{self, ManuallyDrop},
+    ptr,
+};
+
+/// An in place FnOnce, that can also be dropped without being called.
+pub trait Hook {
+    /// Unsafe to call more than once, or after `drop_uncalled`.
+    unsafe fn call(&mut self);
+
+    /// Unsafe to call more than once, or after `call`.
+    unsafe fn drop_uncalled(&mut self);
+}
+
+/// A closure stored in a `DynVecHook`, or queued in the garbage bag. Dropping a `HookFn` leaks the
+/// closure.
+pub struct HookFn<F> {
+    /// HookFns are stored in a DynVec which does not support > `usize` alignment.
+    f: ForcedUsizeAligned<ManuallyDrop<F>>,
+}
+
+impl<F: FnOnce()> HookFn<F> {
+    #[inline]
+    pub fn new(f: F) -> Self {
+        HookFn {
+            f: ForcedUsizeAligned::new(ManuallyDrop::new(f)),
+        }
+    }
+
+    #[inline]
+    unsafe fn take(&mut self) -> F {
+        if mem::align_of::<F>() > mem::align_of::<usize>() {
+            ptr::read_unaligned::<F>(&mut self.f as *mut _ as *mut F)
+        } else {
+            ptr::read::<F>(&mut self.f as *mut _ as *mut F)
+        }
+    }
+}
+
+impl<F: FnOnce()> Hook for HookFn<F> {
+    #[inline]
+    unsafe fn call(&mut self) {
+        self.take()()
+    }
+
+    #[inline]
+    unsafe fn drop_uncalled(&mut self) {
+        drop(self.take())
+    }
+}
+
+dyn_vec_decl! {struct DynVecHook: Hook;}
+
+impl<'tcell> DynVecHook<'tcell> {
+    /// Calls, and removes, every hook starting at `word_len`.
+    #[inline]
+    unsafe fn call_since(&mut self, word_len: usize) {
+        for mut hook in self.drain_from(word_len) {
+            hook.call()
+        }
+    }
+
+    /// Drops, without calling, every hook starting at `word_len`.
+    #[inline]
+    unsafe fn drop_since(&mut self, word_len: usize) {
+        for mut hook in self.drain_from(word_len) {
+            hook.drop_uncalled()
+        }
+    }
+}
+
+/// The lengths of the hook lists at the start of a nested transaction.
+#[derive(Copy, Clone, Debug)]
+pub struct HooksCheckpoint {
+    on_commit: usize,
+    on_abort:  usize,
+}
+
+pub struct Hooks<'tcell> {
+    on_commit: DynVecHook<'tcell>,
+    on_abort:  DynVecHook<'tcell>,
+    /// Hooks waiting for the thread to be unpinned: the abort hooks of discarded attempts, and
+    /// nested transactions, followed by the commit hooks of a committed transaction.
+    unpinned:  DynVecHook<'tcell>,
+}
+
+impl<'tcell> Hooks<'tcell> {
+    #[inline]
+    pub fn new() -> Self {
+        Hooks {
+            on_commit: DynVecHook::new(),
+            on_abort:  DynVecHook::new(),
+            unpinned:  DynVecHook::new(),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.on_commit.is_empty() && self.on_abort.is_empty() && self.unpinned.is_empty()
+    }
+
+    /// Returns true if there are hooks waiting for the thread to be unpinned.
+    #[inline]
+    pub fn has_unpinned(&self) -> bool {
+        !self.unpinned.is_empty()
+    }
+
+    #[inline]
+    pub fn on_commit<F: FnOnce() + Send + 'tcell>(&mut self, f: F) {
+        self.on_commit.push(HookFn::new(f))
+    }
+
+    #[inline]
+    pub fn on_abort<F: FnOnce() + Send + 'tcell>(&mut self, f: F) {
+        self.on_abort.push(HookFn::new(f))
+    }
+
+    #[inline]
+    pub fn checkpoint(&self) -> HooksCheckpoint {
+        HooksCheckpoint {
+            on_commit: self.on_commit.word_len(),
+            on_abort:  self.on_abort.word_len(),
+        }
+    }
+
+    /// Drops the commit hooks registered since the checkpoint, and queues the abort hooks to be run
+    /// once the thread is unpinned.
+    #[inline]
+    pub fn rollback(&mut self, checkpoint: HooksCheckpoint) {
+        unsafe {
+            self.on_commit.drop_since(checkpoint.on_commit);
+            self.unpinned
+                .append_from(&mut self.on_abort, checkpoint.on_abort);
+        }
+    }
+
+    /// Drops every commit hook, and queues every abort hook to be run once the thread is unpinned.
+    #[inline]
+    pub fn aborted(&mut self) {
+        if unlikely!(!self.on_commit.is_empty() || !self.on_abort.is_empty()) {
+            self.rollback(HooksCheckpoint {
+                on_commit: 0,
+                on_abort:  0,
+            })
+        }
+    }
+
+    /// Drops every abort hook, and queues the commit hooks to be run once the thread is unpinned.
+    #[inline]
+    pub fn committed(&mut self) {
+        unsafe {
+            self.on_abort.drop_since(0);
+            self.unpinned.append_from(&mut self.on_commit, 0);
+        }
+    }
+
+    /// Returns the hooks waiting for the thread to be unpinned, so that they can be run after the
+    /// logs are released.
+    #[inline]
+    pub fn take_unpinned(&mut self) -> UnpinnedHooks<'tcell> {
+        UnpinnedHooks {
+            hooks: mem::replace(&mut self.unpinned, DynVecHook::new()),
+        }
+    }
+
+    /// Returns the storage of `hooks` to be reused by later transactions.
+    #[inline]
+    pub fn recycle(&mut self, hooks: UnpinnedHooks<'tcell>) {
+        debug_assert!(hooks.hooks.is_empty());
+        if self.unpinned.is_empty() && self.unpinned.word_capacity() < hooks.hooks.word_capacity() {
+            self.unpinned = hooks.hooks
+        }
+    }
+}
+
+/// The hooks of a finished, or discarded, transaction.
+pub struct UnpinnedHooks<'tcell> {
+    hooks: DynVecHook<'tcell>,
+}
+
+impl<'tcell> UnpinnedHooks<'tcell> {
+    #[inline]
+    pub fn run(&mut self) {
+        unsafe { self.hooks.call_since(0) }
+    }
+}
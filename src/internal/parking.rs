@@ -1,11 +1,21 @@
 use crate::{
     internal::{
+        buckets::Buckets,
         epoch::{EpochClock, ParkStatus, QuiesceEpoch, EPOCH_CLOCK},
         thread::{Logs, ParkPinMutRef, PinMutRef, PinRw},
+        write_log::WriteLog,
     },
-    stats,
+    observer::TxObserver,
+    profile, stats,
+    thread_key::AwaitErr,
 };
+use core::{
+    sync::atomic::{AtomicBool, Ordering::Relaxed},
+    task::Waker,
+};
+use parking_lot::Mutex;
 use parking_lot_core::{FilterOp, ParkResult, ParkToken, DEFAULT_UNPARK_TOKEN};
+use std::{sync::Arc, time::Instant};
 use swym_htm::{BoundedHtxErr, HardwareTx};
 
 const MAX_HTX_RETRIES: u8 = 10;
@@ -17,50 +27,227 @@ fn key() -> usize {
     &EPOCH_CLOCK as *const EpochClock as usize
 }
 
+/// Shared state behind a `CancelHandle`.
+#[derive(Debug, Default)]
+pub struct Cancel {
+    cancelled: AtomicBool,
+
+    /// Park tokens of the threads currently parked with this `Cancel`.
+    parked: Mutex<Vec<usize>>,
+}
+
+impl Cancel {
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Relaxed)
+    }
+
+    #[inline(never)]
+    #[cold]
+    pub fn cancel(&self) {
+        // Parking validation happens while holding the queue lock, so either the parking thread
+        // sees the flag, or it is already in the queue by the time the filter runs.
+        self.cancelled.store(true, Relaxed);
+        let parked = &self.parked;
+        let filter = |ParkToken(token)| {
+            if parked.lock().contains(&token) {
+                FilterOp::Unpark
+            } else {
+                FilterOp::Skip
+            }
+        };
+        let callback = |_| DEFAULT_UNPARK_TOKEN;
+        let unpark_result = unsafe { parking_lot_core::unpark_filter(key(), filter, callback) };
+        stats::unparked_size(unpark_result.unparked_threads);
+    }
+
+    #[inline]
+    fn remove_parked(&self, token: usize) {
+        let mut parked = self.parked.lock();
+        if let Some(index) = parked.iter().position(|&parked| parked == token) {
+            drop(parked.swap_remove(index));
+        }
+    }
+}
+
+/// A task waiting for a commit to modify the read set of its transaction.
+#[derive(Debug)]
+struct Task {
+    /// Taken by the first commit to wake the task.
+    waker: Mutex<Option<Waker>>,
+}
+
+/// A task registered under one of the `EpochLock`s of its read set.
+struct Waiter {
+    /// The address of the `EpochLock`. This is never dereferenced, since the `TCell`s are not
+    /// guaranteed to outlive the task (e.g. if its future is leaked).
+    epoch_lock: usize,
+
+    task: Arc<Task>,
+}
+
+lazy_static::lazy_static! {
+    /// Waiters are hashed into buckets by the address of their `EpochLock`. Tasks are registered
+    /// while holding the lock of every bucket they are added to, and committers only wake tasks
+    /// while holding the lock of the bucket, serving the same purpose as the queue lock for parked
+    /// threads.
+    static ref WAITERS: Buckets<Vec<Waiter>> = Buckets::new();
+}
+
+/// The registration of a task that is waiting for a commit. Must be passed to `deregister_task`
+/// once the task no longer needs to be woken.
+#[derive(Debug)]
+pub struct Registration {
+    task: Arc<Task>,
+
+    /// The sorted, deduplicated addresses of the `EpochLock`s the task is registered under.
+    epoch_locks: Vec<usize>,
+}
+
 fn parkable(pin: PinMutRef<'_, '_>) -> bool {
     let logs = pin.logs();
     // parking a thread without any logs, will sleep the thread forever!
     !logs.read_log.is_empty() || !logs.write_log.is_empty()
 }
 
+/// Parks the thread until the read set of the transaction has been modified.
+///
+/// Returns an error if the deadline passes, or the transaction is cancelled before then.
 #[inline(never)]
 #[cold]
-pub fn park(mut pin: PinRw<'_, '_>) {
+pub fn park(
+    mut pin: PinRw<'_, '_>,
+    deadline: Option<Instant>,
+    cancel: Option<&Cancel>,
+) -> Result<(), AwaitErr> {
     debug_assert!(
         parkable(pin.reborrow()),
         "`AWAIT_RETRY` on a transaction that has an empty read set causes the thread to sleep \
          forever in release"
     );
 
+    let _timer = stats::parked_time();
+    let observer = pin.observer();
+    observer.notify(observer.event(|| pin.logs().sizes()), TxObserver::on_park);
     let parked_pin = pin.parked();
 
     let key = key();
-    let park_token = ParkToken(parked_pin.park_token());
+    let token = parked_pin.park_token();
+    let park_token = ParkToken(token);
     let logs = &*parked_pin;
     let pin_epoch = parked_pin.pin_epoch;
-    let validate = move || try_clear_unpark_bits(logs, pin_epoch);
-    let before_sleep = || {};
+    let validate = move || match cancel {
+        None => try_clear_unpark_bits(logs, pin_epoch),
+        Some(cancel) => {
+            let valid = !cancel.is_cancelled() && try_clear_unpark_bits(logs, pin_epoch);
+            if valid {
+                cancel.parked.lock().push(token)
+            }
+            valid
+        }
+    };
+    let before_sleep = || {
+        profile::parked(
+            logs.read_log
+                .epoch_locks()
+                .chain(logs.write_log.epoch_locks()),
+        )
+    };
     let timed_out = |_, _| {};
 
-    match unsafe {
-        parking_lot_core::park(key, validate, before_sleep, timed_out, park_token, None)
+    let result = match unsafe {
+        parking_lot_core::park(key, validate, before_sleep, timed_out, park_token, deadline)
     } {
         ParkResult::Unparked(token) => {
             debug_assert_eq!(token, DEFAULT_UNPARK_TOKEN);
             let parked_size = logs.read_log.len() + logs.write_log.epoch_locks().count();
             stats::parked_size(parked_size);
+            Ok(())
         }
         ParkResult::Invalid => {
             let parked_size = logs.read_log.len() + logs.write_log.epoch_locks().count();
             stats::park_failure_size(parked_size);
+            Ok(())
         }
         ParkResult::TimedOut => {
-            if cfg!(debug_assertions) {
-                panic!("unexpected timeout on parked thread")
+            debug_assert!(deadline.is_some(), "unexpected timeout on parked thread");
+            Err(AwaitErr::TimedOut)
+        }
+    };
+    observer.notify(observer.event(|| logs.sizes()), TxObserver::on_unpark);
+    drop(parked_pin);
+    match cancel {
+        Some(cancel) => {
+            cancel.remove_parked(token);
+            if cancel.is_cancelled() {
+                Err(AwaitErr::Cancelled)
+            } else {
+                result
             }
         }
+        None => result,
+    }
+}
+
+/// Registers `waker` to be woken once a commit modifies the read set of the transaction.
+///
+/// Returns None without registering if the read set has already been modified, in which case the
+/// transaction should be retried immediately.
+#[inline(never)]
+#[cold]
+pub fn register_task(mut pin: PinRw<'_, '_>, waker: &Waker) -> Option<Registration> {
+    debug_assert!(
+        parkable(pin.reborrow()),
+        "`AWAIT_RETRY` on a transaction that has an empty read set causes the task to wait \
+         forever in release"
+    );
+
+    let parked_pin = pin.parked();
+    let logs = &*parked_pin;
+    let mut epoch_locks: Vec<usize> = logs
+        .read_log
+        .epoch_locks()
+        .chain(logs.write_log.epoch_locks())
+        .map(|e| e as *const _ as usize)
+        .collect();
+    epoch_locks.sort_unstable();
+    epoch_locks.dedup();
+    let mut buckets: Vec<usize> = epoch_locks.iter().map(|&e| WAITERS.index(e)).collect();
+    buckets.sort_unstable();
+    buckets.dedup();
+
+    // Buckets are locked in order, and committers hold at most one bucket lock at a time.
+    let mut guards: Vec<_> = buckets.iter().map(|&b| WAITERS.lock_index(b)).collect();
+    if try_clear_unpark_bits(logs, parked_pin.pin_epoch) {
+        stats::parked_size(epoch_locks.len());
+        let task = Arc::new(Task {
+            waker: Mutex::new(Some(waker.clone())),
+        });
+        for &epoch_lock in &epoch_locks {
+            let index = buckets.binary_search(&WAITERS.index(epoch_lock)).unwrap();
+            guards[index].push(Waiter {
+                epoch_lock,
+                task: task.clone(),
+            })
+        }
+        Some(Registration { task, epoch_locks })
+    } else {
+        stats::park_failure_size(epoch_locks.len());
+        None
+    }
+}
+
+/// Removes the registration of the task, if it has not already been woken.
+#[inline(never)]
+#[cold]
+pub fn deregister_task(registration: Registration) {
+    // Waiters of a woken task may still be in the buckets of the `EpochLock`s that were not
+    // written.
+    for &epoch_lock in &registration.epoch_locks {
+        WAITERS.lock(epoch_lock).retain(|waiter| {
+            waiter.epoch_lock != epoch_lock || !Arc::ptr_eq(&waiter.task, &registration.task)
+        })
     }
-    drop(parked_pin);
 }
 
 fn try_clear_unpark_bits<'tcell>(logs: &Logs<'tcell>, pin_epoch: QuiesceEpoch) -> bool {
@@ -132,9 +319,16 @@ fn park_failure_cleanup<'tcell>(logs: &Logs<'tcell>, park_statuses: Vec<ParkStat
         .for_each(|epoch_lock| epoch_lock.set_unpark_bit())
 }
 
+/// Wakes every thread and task waiting on a `TCell` in the write set that was just published.
 #[inline(never)]
 #[cold]
-pub fn unpark() {
+pub fn unpark(write_log: &WriteLog<'_>) {
+    unpark_threads();
+    wake_tasks(write_log);
+}
+
+#[inline]
+fn unpark_threads() {
     let key = key();
     let callback = |_| DEFAULT_UNPARK_TOKEN;
     let mut not_unparked_count = 0;
@@ -160,3 +354,22 @@ unsafe fn should_unpark(ParkToken(token): ParkToken, not_unparked_count: &mut us
         FilterOp::Skip
     }
 }
+
+#[inline]
+fn wake_tasks(write_log: &WriteLog<'_>) {
+    let mut woken = Vec::new();
+    for epoch_lock in write_log.epoch_locks() {
+        let epoch_lock = epoch_lock as *const _ as usize;
+        WAITERS.lock(epoch_lock).retain(|waiter| {
+            if waiter.epoch_lock == epoch_lock {
+                woken.extend(waiter.task.waker.lock().take());
+                false
+            } else {
+                true
+            }
+        })
+    }
+    stats::unparked_size(woken.len());
+    // Wakers may run arbitrary code, so they are woken without holding any lock.
+    woken.into_iter().for_each(Waker::wake)
+}
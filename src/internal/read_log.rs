@@ -18,13 +18,19 @@ const READ_CAPACITY: usize = 0;
 #[derive(Debug)]
 pub struct ReadLog<'tcell> {
     data: FVec<Option<&'tcell TCellErased>>,
+
+    /// Whether any reads were performed without being logged (`Ordering::Read`). Values from such
+    /// reads may have been retired since they were read, so the pinned epoch must not be moved
+    /// forward.
+    unlogged: bool,
 }
 
 impl<'tcell> ReadLog<'tcell> {
     #[inline]
     pub fn new() -> Self {
         ReadLog {
-            data: FVec::with_capacity(READ_CAPACITY),
+            data:     FVec::with_capacity(READ_CAPACITY),
+            unlogged: false,
         }
     }
 
@@ -38,6 +44,16 @@ impl<'tcell> ReadLog<'tcell> {
         self.data.next_push_allocates()
     }
 
+    #[inline]
+    pub fn record_unlogged(&mut self) {
+        self.unlogged = true
+    }
+
+    #[inline]
+    pub fn has_unlogged(&self) -> bool {
+        self.unlogged
+    }
+
     #[inline]
     pub fn record(&mut self, erased: &'tcell TCellErased) {
         self.data.push(Some(erased))
@@ -73,9 +89,16 @@ impl<'tcell> ReadLog<'tcell> {
     #[inline]
     pub fn clear(&mut self) {
         stats::read_size(self.len());
+        self.unlogged = false;
         self.data.clear()
     }
 
+    /// Forgets all reads after the first `len` reads.
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        self.data.truncate(len)
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         self.data.len()
@@ -18,14 +18,16 @@
 //! https://github.com/Amanieu/parking_lot
 
 use crate::{
+    contention::{Backoff, Conflict, ContentionManager},
     internal::epoch::{QuiesceEpoch, EPOCH_CLOCK, TICK_SIZE},
     stats,
 };
 use core::{
     cell::Cell,
-    mem,
+    hint, mem,
     ptr::NonNull,
-    sync::atomic::{self, AtomicU8, AtomicUsize, Ordering::Relaxed},
+    sync::atomic::{AtomicU8, AtomicUsize, Ordering::Relaxed},
+    time::Duration,
 };
 use parking_lot_core::{self, FilterOp, ParkResult, ParkToken, UnparkResult, UnparkToken};
 use std::thread;
@@ -138,10 +140,14 @@ impl Starvation {
         self.starve_unlock_slow(should_upgrade, upgrade);
     }
 
+    /// Returns true if there were starving threads.
     #[inline]
-    fn wait_for_starvers(&self, token: Token) {
+    fn wait_for_starvers(&self, token: Token) -> bool {
         if unlikely!(self.state.load(Relaxed) != 0) {
-            self.wait_for_starvers_slow(token)
+            self.wait_for_starvers_slow(token);
+            true
+        } else {
+            false
         }
     }
 
@@ -336,6 +342,11 @@ enum ProgressImpl {
         first_failed_epoch: Option<QuiesceEpoch>,
         backoff:            u32,
     },
+    /// Backoff is decided by a `ContentionManager`.
+    Managed {
+        first_failed_epoch: QuiesceEpoch,
+        attempts:           u32,
+    },
     Starving,
 }
 
@@ -365,6 +376,8 @@ impl ProgressImpl {
                 first_failed_epoch: None,
                 ..
             } => false,
+            // The contention manager decides when to starve.
+            ProgressImpl::Managed { .. } => false,
             ProgressImpl::Starving => {
                 debug_assert!(false);
                 false
@@ -377,6 +390,10 @@ pub struct Progress {
     /// The `Cell` here is actually accessed from multiple threads, but only while the "owning"
     /// thread is parked, and parking lots bucket locks are held.
     inner: Cell<ProgressImpl>,
+
+    /// Whether the thread has waited on a starving thread since `take_blocked` was last called.
+    /// Only accessed by the owning thread.
+    blocked: Cell<bool>,
 }
 
 #[cfg(debug_assertions)]
@@ -399,16 +416,30 @@ impl Progress {
     #[inline]
     pub const fn new() -> Self {
         Progress {
-            inner: Cell::new(ProgressImpl::new()),
+            inner:   Cell::new(ProgressImpl::new()),
+            blocked: Cell::new(false),
         }
     }
 
     /// Called when a thread has failed either the optimistic phase of concurrency, or the
     /// pessimistic phase of concurrency.
+    ///
+    /// Nested transactions yield instead of taking the starvation lock in the middle of the
+    /// enclosing transaction. The lock is taken once the outermost transaction retries.
     #[cold]
-    pub fn failed_to_progress(&self, epoch: QuiesceEpoch) {
+    pub fn failed_to_progress(&self, epoch: QuiesceEpoch, nested: bool) {
         // TODO: can this be golfed, and/or write to less memory?
-        match self.inner.get() {
+        let inner = match self.inner.get() {
+            // The contention manager was removed part way through a transaction.
+            ProgressImpl::Managed {
+                first_failed_epoch, ..
+            } => ProgressImpl::NotStarving {
+                first_failed_epoch: Some(first_failed_epoch),
+                backoff:            0,
+            },
+            inner => inner,
+        };
+        match inner {
             ProgressImpl::NotStarving {
                 first_failed_epoch,
                 backoff,
@@ -429,7 +460,7 @@ impl Progress {
                             return;
                         } else {
                             for _ in 0..1 << backoff {
-                                atomic::spin_loop_hint();
+                                hint::spin_loop();
                             }
                         }
                     } else {
@@ -439,12 +470,15 @@ impl Progress {
                         first_failed_epoch: Some(first_failed_epoch),
                         backoff:            backoff + 1,
                     });
+                } else if nested {
+                    thread::yield_now();
                 } else {
                     thread::yield_now();
                     STARVATION.starve_lock(Token::new(self));
                     self.inner.set(ProgressImpl::Starving)
                 }
             }
+            ProgressImpl::Managed { .. } => unreachable!(),
             ProgressImpl::Starving => {
                 // There might be a few straggler threads that were in the middle of a commit when
                 // this thread signaled it was starving. Rare, but in that scenario we can commit
@@ -454,16 +488,98 @@ impl Progress {
         };
     }
 
+    /// Like `failed_to_progress`, but `manager` decides how to backoff.
+    ///
+    /// A requested sleep is returned instead of taken, so that the caller can unpin the thread
+    /// first. Nested transactions can not unpin the thread, so they yield instead of sleeping, or
+    /// becoming irrevocable.
+    #[cold]
+    pub fn failed_to_progress_managed(
+        &self,
+        epoch: QuiesceEpoch,
+        manager: &mut dyn ContentionManager,
+        nested: bool,
+    ) -> Option<Duration> {
+        let (first_failed_epoch, attempts) = match self.inner.get() {
+            ProgressImpl::NotStarving {
+                first_failed_epoch, ..
+            } => (first_failed_epoch.unwrap_or(epoch), 1),
+            ProgressImpl::Managed {
+                first_failed_epoch,
+                attempts,
+            } => (first_failed_epoch, attempts.saturating_add(1)),
+            ProgressImpl::Starving => {
+                // See `failed_to_progress`.
+                thread::yield_now();
+                return None;
+            }
+        };
+        self.inner.set(ProgressImpl::Managed {
+            first_failed_epoch,
+            attempts,
+        });
+        let conflict = Conflict {
+            attempts,
+            elapsed: (epoch.get().get() - first_failed_epoch.get().get()) / TICK_SIZE,
+        };
+        match manager.on_conflict(&conflict) {
+            Backoff::Spin(count) => {
+                for _ in 0..count {
+                    hint::spin_loop();
+                }
+            }
+            Backoff::Yield => thread::yield_now(),
+            Backoff::Sleep(_) | Backoff::Irrevocable if nested => thread::yield_now(),
+            Backoff::Sleep(duration) => return Some(duration),
+            Backoff::Irrevocable => {
+                // See `starve`.
+                self.inner.set(ProgressImpl::NotStarving {
+                    first_failed_epoch: Some(first_failed_epoch),
+                    backoff:            YIELD_LIMIT + 1,
+                });
+                STARVATION.starve_lock(Token::new(self));
+                self.inner.set(ProgressImpl::Starving)
+            }
+        }
+        None
+    }
+
+    /// Blocks until this thread holds the starvation lock, preventing other threads from starting
+    /// to commit until `progressed` is called. The thread must not be pinned.
+    #[inline(never)]
+    #[cold]
+    pub fn starve(&self) {
+        let now = EPOCH_CLOCK.now().unwrap_or_else(|| abort!());
+        // While parked, the thread must look like it is starving, so that the starvation lock can
+        // be handed off to it.
+        self.inner.set(ProgressImpl::NotStarving {
+            first_failed_epoch: Some(now),
+            backoff:            YIELD_LIMIT + 1,
+        });
+        STARVATION.starve_lock(Token::new(self));
+        self.inner.set(ProgressImpl::Starving)
+    }
+
     /// Called when a thread has finished the optimistic phase of concurrency, and is about to enter
     /// a pessimistic phase where the threads progress will be published.
     #[inline]
     pub fn wait_for_starvers(&self) {
         match self.inner.get() {
-            ProgressImpl::NotStarving { .. } => STARVATION.wait_for_starvers(Token::new(self)),
+            ProgressImpl::NotStarving { .. } | ProgressImpl::Managed { .. } => {
+                if STARVATION.wait_for_starvers(Token::new(self)) {
+                    self.blocked.set(true)
+                }
+            }
             ProgressImpl::Starving => {}
         };
     }
 
+    /// Returns whether the thread has waited on a starving thread since the last call.
+    #[inline]
+    pub fn take_blocked(&self) -> bool {
+        self.blocked.replace(false)
+    }
+
     /// Called after progress has been made.
     #[inline]
     pub fn progressed(&self) {
@@ -481,7 +597,7 @@ impl Progress {
     #[cold]
     fn progressed_slow(&self) {
         match self.inner.get() {
-            ProgressImpl::NotStarving { .. } => {}
+            ProgressImpl::NotStarving { .. } | ProgressImpl::Managed { .. } => {}
             ProgressImpl::Starving => unsafe {
                 STARVATION.starve_unlock(
                     |this| this.as_ref().inner.get().should_upgrade(),
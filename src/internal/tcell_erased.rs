@@ -21,4 +21,11 @@ impl TCellErased {
             current_epoch: EpochLock::first(),
         }
     }
+
+    /// Returns the `TCellErased` containing `epoch_lock`.
+    #[inline]
+    pub fn from_epoch_lock(epoch_lock: &EpochLock) -> &TCellErased {
+        // TCellErased is repr(transparent) over EpochLock.
+        unsafe { &*(epoch_lock as *const EpochLock as *const TCellErased) }
+    }
 }
@@ -1,26 +1,39 @@
 use crate::{
+    contention::ContentionManager,
+    diagnostics::ConflictKind,
     internal::{
+        commit::Committed,
         epoch::{QuiesceEpoch, EPOCH_CLOCK},
         gc::{GlobalSynchList, OwnedSynch, ThreadGarbage},
+        hooks::{Hooks, HooksCheckpoint},
+        parking::{self, Cancel, Registration},
         phoenix_tls::PhoenixTarget,
         read_log::ReadLog,
         starvation::{self, Progress},
-        write_log::WriteLog,
+        tcell_erased::TCellErased,
+        write_log::{WriteCheckpoint, WriteLog},
     },
+    irrevocable::IrrevocableTx,
+    observer::{ThreadObserver, TxKind, TxObserver},
     read::ReadTx,
     rw::RwTx,
-    stats,
-    tx::{Error, InternalStatus, Status},
+    snapshot::SnapshotTx,
+    stats, tarc,
+    thread_key::{AwaitErr, TxInfo},
+    tx::{Abort, AbortKind, Error, InternalStatus, Status},
 };
 use core::{
-    cell::UnsafeCell,
+    cell::{Cell, RefCell, UnsafeCell},
+    convert::Infallible,
     fmt::{self, Debug, Formatter},
     marker::PhantomData,
     mem,
     ops::{Deref, DerefMut},
-    ptr,
+    ptr::{self, NonNull},
     sync::atomic::Ordering::{Relaxed, Release},
+    task::Waker,
 };
+use std::time::{Duration, Instant};
 
 /// Thread local data.
 ///
@@ -40,6 +53,122 @@ pub struct Thread {
 
     /// Backoff handling for thread starvation.
     progress: Progress,
+
+    /// Replaces the backoff policy of `progress`, if set.
+    contention: RefCell<Option<Box<dyn ContentionManager>>>,
+
+    /// Receives the events of transactions run on this thread.
+    observer: ThreadObserver,
+
+    /// References held by `TArc`s and `TWeak`s that were dropped while the thread was pinned.
+    releases: RefCell<Vec<tarc::Release>>,
+
+    /// Whether `releases` are being released further up the stack.
+    releasing: Cell<bool>,
+
+    /// The kind of transaction whose closure is currently running on this thread. Transactions
+    /// started through `ThreadKey` while it is running are nested inside of it.
+    nesting: Cell<Nesting>,
+
+    /// Set when a nested transaction could not complete without the root transaction being
+    /// restarted. The root transaction is then retried, or waits, whatever its closure returns.
+    restart: Cell<Restart>,
+
+    /// The epoch reads are validated against, while a nested transaction runs ahead of a root
+    /// transaction that is no longer valid. The thread stays pinned to the original epoch, so that
+    /// everything the root transaction has read is kept alive.
+    snapshot: Cell<Option<QuiesceEpoch>>,
+}
+
+/// The kind of transaction whose closure is running on a thread.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Nesting {
+    /// No transaction closure is running. The thread may still be pinned (e.g. during commit, or
+    /// while the logs drop a value), where starting a nested transaction is not allowed.
+    None,
+    /// A read only transaction without a read log.
+    Read,
+    /// A read only transaction that records its reads.
+    LoggedRead,
+    /// A read only transaction that records its reads, and may be upgraded.
+    Upgradable,
+    /// A read write transaction.
+    Rw,
+}
+
+impl Nesting {
+    #[inline]
+    fn can_write(self) -> bool {
+        self == Nesting::Upgradable || self == Nesting::Rw
+    }
+}
+
+/// Sets the `Nesting` of a thread, until dropped.
+pub struct NestingGuard<'a> {
+    nesting: &'a Cell<Nesting>,
+    prev:    Nesting,
+}
+
+impl<'a> Drop for NestingGuard<'a> {
+    #[inline]
+    fn drop(&mut self) {
+        self.nesting.set(self.prev)
+    }
+}
+
+impl<'a> NestingGuard<'a> {
+    #[inline]
+    fn new(thread: &'a Thread, kind: Nesting) -> Self {
+        NestingGuard {
+            nesting: &thread.nesting,
+            prev:    thread.nesting.replace(kind),
+        }
+    }
+
+    /// Prevents destructors run by the logs from starting nested transactions, while the logs are
+    /// borrowed.
+    #[inline]
+    fn suspend(thread: &'a Thread) -> Self {
+        NestingGuard::new(thread, Nesting::None)
+    }
+}
+
+/// Why the root transaction has to be restarted. Later requests only ever escalate the restart.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Restart {
+    None,
+    AwaitRetry,
+    Conflict,
+}
+
+/// Error returned when a transaction can not be nested inside of the transaction running on a
+/// thread.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NestErr {
+    /// No transaction closure is running (e.g. the thread is committing), or the running
+    /// transaction can not contain the nested transaction.
+    Unsupported,
+    /// The nested transaction returned `AWAIT_RETRY`, which the root transaction waits on once
+    /// its closure returns.
+    AwaitRetry,
+}
+
+/// Parks the thread until the read set of the transaction is modified, without a deadline, or a
+/// way to cancel it.
+#[inline]
+fn park_forever<E>(pin_rw: PinRw<'_, '_>) -> Result<(), E> {
+    let result = parking::park(pin_rw, None, None);
+    debug_assert!(
+        result.is_ok(),
+        "parking without a deadline or cancel failed"
+    );
+    Ok(())
+}
+
+thread_local! {
+    /// The thread that is collecting the last of its garbage before being dropped. References
+    /// released by its garbage are queued up on it, instead of on a temporary thread.
+    static EXITING: Cell<Option<NonNull<Thread>>> = Cell::new(None);
 }
 
 impl Default for Thread {
@@ -53,9 +182,16 @@ impl Thread {
     #[inline]
     pub fn new() -> Self {
         Thread {
-            logs:     UnsafeCell::new(Logs::new()),
-            synch:    OwnedSynch::new(),
-            progress: Progress::new(),
+            logs:       UnsafeCell::new(Logs::new()),
+            synch:      OwnedSynch::new(),
+            progress:   Progress::new(),
+            contention: RefCell::new(None),
+            observer:   ThreadObserver::new(),
+            releases:   RefCell::new(Vec::new()),
+            releasing:  Cell::new(false),
+            nesting:    Cell::new(Nesting::None),
+            restart:    Cell::new(Restart::None),
+            snapshot:   Cell::new(None),
         }
     }
 
@@ -69,9 +205,267 @@ impl Thread {
     ///
     /// This makes mutable access to `Logs` safe, and is the only way to perform transactions.
     #[inline]
-    pub fn try_pin(&self) -> Option<Pin<'_>> {
+    pub fn try_pin<'tcell>(&self) -> Option<Pin<'_, 'tcell>> {
         Pin::try_new(self)
     }
+
+    /// Acquires the starvation lock, and then pins the current thread. Returns None, without
+    /// acquiring the lock, if already pinned.
+    #[inline]
+    pub fn try_pin_irrevocable<'tcell>(&self) -> Option<Pin<'_, 'tcell>> {
+        if likely!(!self.is_pinned()) {
+            // The lock has to be acquired while unpinned. The thread holding it may be waiting on
+            // this thread to unpin in order to collect garbage.
+            self.progress.starve();
+            Pin::try_new(self)
+        } else {
+            None
+        }
+    }
+
+    /// Replaces the contention manager of the current thread, returning the previous one.
+    #[inline]
+    pub fn set_contention_manager(
+        &self,
+        manager: Option<Box<dyn ContentionManager>>,
+    ) -> Option<Box<dyn ContentionManager>> {
+        let prev = self
+            .contention
+            .try_borrow_mut()
+            .map(|mut contention| mem::replace(&mut *contention, manager))
+            .expect("contention managers cannot be replaced from inside of their own callbacks");
+        drop(self.progress.take_blocked());
+        prev
+    }
+
+    /// Replaces the observer of the current thread, returning the previous one.
+    #[inline]
+    pub fn set_observer(
+        &self,
+        observer: Option<Box<dyn TxObserver>>,
+    ) -> Option<Box<dyn TxObserver>> {
+        self.observer.replace(observer)
+    }
+
+    /// Waits before retrying a transaction that failed, using the contention manager if one is
+    /// set. Returns how long the thread should sleep, once it has been unpinned.
+    #[inline]
+    fn failed_to_progress(&self, epoch: QuiesceEpoch, nested: bool) -> Option<Duration> {
+        match &mut *self.contention.borrow_mut() {
+            None => {
+                self.notify_blocked(None);
+                self.progress.failed_to_progress(epoch, nested);
+                None
+            }
+            Some(manager) => {
+                self.notify_blocked(Some(&mut **manager));
+                self.progress
+                    .failed_to_progress_managed(epoch, &mut **manager, nested)
+            }
+        }
+    }
+
+    /// Waits before retrying a nested transaction that failed. The thread stays pinned, so the
+    /// backoff never sleeps, or takes the starvation lock.
+    #[inline]
+    fn failed_to_progress_nested(&self, epoch: QuiesceEpoch) {
+        let sleep = self.failed_to_progress(epoch, true);
+        debug_assert!(sleep.is_none(), "nested transactions can not sleep");
+    }
+
+    /// Notifies the contention manager, if one is set, that a transaction completed.
+    #[inline]
+    fn notify_commit(&self) {
+        match &mut *self.contention.borrow_mut() {
+            None => self.notify_blocked(None),
+            Some(manager) => {
+                self.notify_blocked(Some(&mut **manager));
+                manager.on_commit()
+            }
+        }
+    }
+
+    /// Notifies the contention manager and observers if the thread was blocked by a starving
+    /// thread since the last notification.
+    #[inline]
+    fn notify_blocked(&self, manager: Option<&mut dyn ContentionManager>) {
+        if (manager.is_some() || self.observer.is_active()) && self.progress.take_blocked() {
+            if let Some(manager) = manager {
+                manager.on_starvation()
+            }
+            self.observer
+                .notify(self.observer.event(|| (0, 0)), TxObserver::on_starvation)
+        }
+    }
+
+    /// Runs the commit hooks of the transaction that just committed on this thread, and then the
+    /// deferred releases. The thread must not be pinned.
+    #[inline]
+    fn run_commit_hooks(&self) {
+        let logs = self.logs.get();
+        if unlikely!(unsafe { !(*logs).hooks.is_empty() }) {
+            unsafe { (*logs).hooks.committed() };
+            self.run_unpinned_hooks_slow();
+        }
+        self.release_deferred()
+    }
+
+    /// Runs the hooks that were waiting for the thread to be unpinned. The thread must not be
+    /// pinned.
+    #[inline]
+    fn run_unpinned_hooks(&self) {
+        if unlikely!(unsafe { (*self.logs.get()).hooks.has_unpinned() }) {
+            self.run_unpinned_hooks_slow()
+        }
+    }
+
+    #[inline(never)]
+    #[cold]
+    fn run_unpinned_hooks_slow(&self) {
+        // The hooks are moved out of the logs before being run, so that they are free to start new
+        // transactions.
+        let logs = self.logs.get();
+        let mut hooks = unsafe { (*logs).hooks.take_unpinned() };
+        hooks.run();
+        unsafe { (*logs).hooks.recycle(hooks) }
+    }
+
+    /// Runs `f` with the thread that is being dropped on the current OS thread, if any.
+    #[inline]
+    pub fn with_exiting<O>(f: impl FnOnce(Option<&Thread>) -> O) -> O {
+        f(EXITING
+            .with(Cell::get)
+            .map(|thread| unsafe { &*thread.as_ptr() }))
+    }
+
+    /// Releases a reference held by a dropped `TArc` or `TWeak`. If the thread is pinned, this is
+    /// deferred until it is unpinned.
+    pub fn release(&self, release: tarc::Release) {
+        self.releases.borrow_mut().push(release);
+        if !self.is_pinned() {
+            self.release_deferred()
+        }
+    }
+
+    /// Releases the references that were dropped while the thread was pinned. The thread must not
+    /// be pinned.
+    #[inline]
+    fn release_deferred(&self) {
+        if unlikely!(!self.releasing.get() && !self.releases.borrow().is_empty()) {
+            self.release_deferred_slow()
+        }
+    }
+
+    #[inline(never)]
+    #[cold]
+    fn release_deferred_slow(&self) {
+        // The references stay queued until whatever has pinned the thread unpins it.
+        if self.is_pinned() {
+            return;
+        }
+        // Dropping the values of released references can drop more `TArc`s. Releasing them in a
+        // loop, instead of recursively, keeps the stack from overflowing on long chains.
+        let _releasing = Releasing::new(&self.releasing);
+        loop {
+            let releases = mem::take(&mut *self.releases.borrow_mut());
+            if releases.is_empty() {
+                break;
+            }
+            let pin = match self.try_pin() {
+                Some(pin) => pin,
+                None => unreachable!("the thread was pinned while releasing references"),
+            };
+            pin.run_rw(|tx| {
+                for release in &releases {
+                    unsafe { release.run(tx)? }
+                }
+                Ok(())
+            })
+        }
+    }
+
+    /// Requests that the root transaction be restarted once its closure returns.
+    #[inline]
+    fn restart_root(&self, restart: Restart) {
+        self.restart.set(self.restart.get().max(restart))
+    }
+
+    /// Replaces the result of the closure of a root transaction with the restart requested by a
+    /// nested transaction, if any.
+    #[inline]
+    fn take_restart<O, E: From<Status>>(&self, result: Result<O, E>) -> Result<O, E> {
+        match self.restart.replace(Restart::None) {
+            Restart::None => result,
+            Restart::AwaitRetry => Err(Status::AWAIT_RETRY.into()),
+            Restart::Conflict => {
+                Err(Status::from(Error::conflict(ConflictKind::ReadValidation)).into())
+            }
+        }
+    }
+
+    #[inline]
+    fn pin_mut_ref<'tcell>(&self) -> PinMutRef<'_, 'tcell> {
+        debug_assert!(
+            self.is_pinned(),
+            "nested a transaction on an unpinned thread"
+        );
+        PinMutRef {
+            pin_ref: PinRef {
+                thread:  self,
+                phantom: PhantomData,
+            },
+        }
+    }
+
+    /// Runs a read only transaction nested inside of the transaction whose closure is currently
+    /// running on this thread. Upgradable transactions can only be nested inside of transactions
+    /// that can write.
+    #[inline(never)]
+    pub fn try_nested_read<'tcell, F, O>(
+        &'tcell self,
+        upgradable: bool,
+        mut f: F,
+    ) -> Result<(O, TxInfo), NestErr>
+    where
+        F: FnMut(&mut ReadTx<'tcell>) -> Result<O, Status>,
+    {
+        let nesting = self.nesting.get();
+        let kind = match nesting {
+            Nesting::None => return Err(NestErr::Unsupported),
+            _ if upgradable => {
+                if !nesting.can_write() {
+                    return Err(NestErr::Unsupported);
+                }
+                Nesting::Upgradable
+            }
+            Nesting::Read => return self.pin_mut_ref().run_nested_unlogged(f),
+            _ => Nesting::LoggedRead,
+        };
+        match self
+            .pin_mut_ref()
+            .run_nested::<_, _, Infallible>(kind, move |tx| Ok(f(ReadTx::from_rw(tx))?))?
+        {
+            (Ok(o), info) => Ok((o, info)),
+            (Err(never), _) => match never {},
+        }
+    }
+
+    /// Runs a read write transaction nested inside of the transaction whose closure is currently
+    /// running on this thread, which must be able to write.
+    #[inline(never)]
+    pub fn try_nested_rw<'tcell, F, O, E>(
+        &'tcell self,
+        f: F,
+    ) -> Result<(Result<O, E>, TxInfo), NestErr>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Abort<E>>,
+    {
+        if self.nesting.get().can_write() {
+            self.pin_mut_ref().run_nested(Nesting::Rw, f)
+        } else {
+            Err(NestErr::Unsupported)
+        }
+    }
 }
 
 impl PhoenixTarget for Thread {
@@ -83,12 +477,28 @@ impl PhoenixTarget for Thread {
     }
 
     fn unsubscribe(&mut self) {
-        unsafe {
-            // All thread garbage must be collected before the Thread is dropped.
-            (*self.logs.get())
-                .garbage
-                .synch_and_collect_all(&self.synch);
+        let this: &Thread = self;
+        let prev = EXITING.with(|exiting| exiting.replace(Some(NonNull::from(this))));
+        // All thread garbage must be collected before the Thread is dropped. Collecting it may drop
+        // more references, which are released before collecting again.
+        loop {
+            this.release_deferred();
+            this.releasing.set(true);
+            unsafe {
+                (*this.logs.get())
+                    .garbage
+                    .synch_and_collect_all(&this.synch)
+            };
+            // collecting garbage leaves the thread pinned to a collect epoch
+            if this.is_pinned() {
+                this.synch.unpin(Release);
+            }
+            this.releasing.set(false);
+            if this.releases.borrow().is_empty() {
+                break;
+            }
         }
+        EXITING.with(|exiting| exiting.set(prev));
 
         // fullfilling the promise we made in `Self::new`. we must unregister before
         // deallocation, or there will be UB
@@ -107,6 +517,7 @@ pub struct Logs<'tcell> {
     pub read_log:  ReadLog<'tcell>,
     pub write_log: WriteLog<'tcell>,
     pub garbage:   ThreadGarbage,
+    pub hooks:     Hooks<'tcell>,
 }
 
 impl<'tcell> Logs<'tcell> {
@@ -116,15 +527,66 @@ impl<'tcell> Logs<'tcell> {
             read_log:  ReadLog::new(),
             write_log: WriteLog::new(),
             garbage:   ThreadGarbage::new(),
+            hooks:     Hooks::new(),
         }
     }
 
+    /// Returns the number of `TCell`s in the read log, and the number of writes in the write log.
+    #[inline]
+    pub fn sizes(&self) -> (usize, usize) {
+        (self.read_log.len(), self.write_log.epoch_locks().count())
+    }
+
+    /// Returns the number of `TCell`s in the read log, and the size of the write log in words.
+    #[inline]
+    pub fn lens(&self) -> (usize, usize) {
+        (self.read_log.len(), self.write_log.word_len())
+    }
+
     #[inline]
     fn validate_start_state(&self) {
         debug_assert!(self.read_log.is_empty());
         debug_assert!(self.write_log.is_empty());
         debug_assert!(self.garbage.is_speculative_bag_empty());
     }
+
+    #[inline]
+    fn checkpoint(&mut self) -> Checkpoint {
+        Checkpoint {
+            read_len:    self.read_log.len(),
+            write:       self.write_log.checkpoint(),
+            garbage_len: self.garbage.speculative_word_len(),
+            hooks:       self.hooks.checkpoint(),
+        }
+    }
+
+    #[inline]
+    fn merge(&mut self, checkpoint: Checkpoint) {
+        self.write_log.merge(checkpoint.write)
+    }
+
+    #[inline]
+    fn rollback(&mut self, checkpoint: Checkpoint) {
+        self.read_log.truncate(checkpoint.read_len);
+        self.rollback_writes(checkpoint);
+    }
+
+    #[inline]
+    fn rollback_writes(&mut self, checkpoint: Checkpoint) {
+        self.garbage
+            .abort_speculative_garbage_since(checkpoint.garbage_len);
+        self.write_log.rollback(checkpoint.write);
+        self.hooks.rollback(checkpoint.hooks);
+    }
+}
+
+/// The state of the logs at the start of a nested transaction.
+#[derive(Copy, Clone, Debug)]
+struct Checkpoint {
+    read_len:    usize,
+    write:       WriteCheckpoint,
+    garbage_len: usize,
+    hooks:       HooksCheckpoint,
 }
 
 #[cfg(debug_assertions)]
@@ -176,6 +638,9 @@ impl<'tx, 'tcell> PinRef<'tx, 'tcell> {
     /// Gets the currently pinned epoch.
     #[inline]
     pub fn pin_epoch(&self) -> QuiesceEpoch {
+        if let Some(snapshot) = self.thread.snapshot.get() {
+            return snapshot;
+        }
         let pin_epoch = self.synch().current_epoch();
         debug_assert!(
             pin_epoch.is_active(),
@@ -183,6 +648,117 @@ impl<'tx, 'tcell> PinRef<'tx, 'tcell> {
         );
         pin_epoch
     }
+
+    /// Returns the observer of the pinned thread.
+    #[inline]
+    pub fn observer(&self) -> &'tx ThreadObserver {
+        &self.thread.observer
+    }
+
+    /// Returns the address of the pinned thread.
+    #[inline]
+    pub fn thread_ptr(&self) -> *const Thread {
+        self.thread
+    }
+
+    /// Keeps transactions started by destructors from nesting inside of the running transaction
+    /// while its logs are being modified.
+    #[inline]
+    pub fn suspend_nesting(&self) -> NestingGuard<'tx> {
+        NestingGuard::suspend(self.thread)
+    }
+
+    /// Recreates a `PinRef` from the address returned by `thread_ptr`.
+    #[inline]
+    pub unsafe fn from_thread_ptr(thread: *const Thread) -> Self {
+        PinRef {
+            thread:  &*thread,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Records a read performed by a read only transaction that keeps a read log.
+    ///
+    /// The read log is only ever accessed by the thread that owns it, and read only transactions
+    /// hold no other references into the logs while their closure runs.
+    #[inline]
+    pub unsafe fn record_read(&self, erased: &'tcell TCellErased) {
+        let logs: &mut Logs<'tcell> = &mut *(self.thread.logs.get() as *const _ as *mut _);
+        logs.read_log.record(erased)
+    }
+
+    /// Runs `f` as a read write transaction nested inside of the currently running read only
+    /// transaction, which must keep a read log. The writes are committed by the root transaction.
+    ///
+    /// The read only transaction must be mutably borrowed for the duration of the call, so that
+    /// nothing else has access to the logs.
+    #[inline]
+    pub unsafe fn run_upgrade<F, O>(self, mut f: F) -> Result<O, Status>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        let mut pin_ref = PinMutRef { pin_ref: self };
+        match pin_ref.nested::<_, _, Infallible>(move |tx| Ok(f(tx)?))? {
+            Ok(o) => Ok(o),
+            Err(never) => match never {},
+        }
+    }
+}
+
+/// Pins the thread again once dropped, even if the code running while unpinned panics, so that the
+/// `Pin` can be dropped.
+struct Repin<'a>(&'a OwnedSynch);
+
+impl Drop for Repin<'_> {
+    fn drop(&mut self) {
+        let now = EPOCH_CLOCK.now();
+        if let Some(now) = now {
+            self.0.pin(now, Release);
+        } else {
+            abort!()
+        }
+    }
+}
+
+/// Marks a thread as releasing references until dropped, even if dropping a released value panics.
+struct Releasing<'a>(&'a Cell<bool>);
+
+impl<'a> Drop for Releasing<'a> {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.set(false)
+    }
+}
+
+impl<'a> Releasing<'a> {
+    #[inline]
+    fn new(releasing: &'a Cell<bool>) -> Self {
+        releasing.set(true);
+        Releasing(releasing)
+    }
+}
+
+/// Restores the snapshot of a thread when a nested transaction completes, even if it panics.
+struct RestoreSnapshot<'a> {
+    thread: &'a Thread,
+    prev:   Option<QuiesceEpoch>,
+}
+
+impl<'a> Drop for RestoreSnapshot<'a> {
+    #[inline]
+    fn drop(&mut self) {
+        self.thread.snapshot.set(self.prev)
+    }
+}
+
+impl<'a> RestoreSnapshot<'a> {
+    #[inline]
+    fn new(thread: &'a Thread) -> Self {
+        RestoreSnapshot {
+            thread,
+            prev: thread.snapshot.get(),
+        }
+    }
 }
 
 pub struct PinMutRef<'tx, 'tcell> {
@@ -219,8 +795,219 @@ impl<'tx, 'tcell> PinMutRef<'tx, 'tcell> {
         unsafe { &mut *(self.pin_ref.thread.logs.get() as *const _ as *mut _) }
     }
 
+    /// Attempts to move the pinned epoch forward to the current time without restarting the
+    /// transaction. This is only possible if nothing that has been read or written has been
+    /// modified since the thread was pinned.
+    #[inline]
+    fn try_extend(&mut self) -> bool {
+        let pin_epoch = self.pin_epoch();
+        // The current time must be sampled before validating, otherwise a commit between the
+        // validation and the sample would go unnoticed.
+        let now = EPOCH_CLOCK.now().unwrap_or_else(|| abort!());
+        let logs = self.logs();
+        if !logs.read_log.has_unlogged()
+            && logs.read_log.validate_reads(pin_epoch)
+            && logs.write_log.validate_writes(pin_epoch)
+        {
+            self.synch().repin(now, Release);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Runs a read write transaction inside of the currently running read write transaction.
+    ///
+    /// On conflict, only the writes of the nested transaction are discarded, and the nested
+    /// transaction is retried. If the enclosing transaction is no longer valid, the conflict is
+    /// returned instead, so that the root transaction is restarted. User aborts and `AWAIT_RETRY`
+    /// discard the writes of the nested transaction, but keep its reads.
+    #[inline]
+    pub fn nested<F, O, E>(&mut self, mut f: F) -> Result<Result<O, E>, Status>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Abort<E>>,
+    {
+        loop {
+            let mut nested = NestedRw::new(self.reborrow());
+            match f(RwTx::new(&mut nested)) {
+                Ok(o) => {
+                    nested.merge();
+                    return Ok(Ok(o));
+                }
+                Err(Abort {
+                    kind: AbortKind::User(e),
+                }) => {
+                    nested.discard_writes();
+                    return Ok(Err(e));
+                }
+                Err(Abort {
+                    kind:
+                        AbortKind::Status(Status {
+                            kind: InternalStatus::Error(_),
+                        }),
+                }) => {}
+                Err(Abort {
+                    kind: AbortKind::Status(status),
+                }) => {
+                    // The root transaction waits on the reads of the nested transaction as well as
+                    // its own.
+                    nested.discard_writes();
+                    return Err(status);
+                }
+            }
+            drop(nested);
+            self.thread.failed_to_progress_nested(self.pin_epoch());
+            if !self.try_extend() {
+                return Err(Error::conflict(ConflictKind::ReadValidation).into());
+            }
+        }
+    }
+
+    /// Runs a transaction started through `ThreadKey` nested inside of the running transaction.
+    ///
+    /// Unlike `nested`, the caller has no way to pass on a conflict of the enclosing transaction.
+    /// The root transaction is marked for restart instead, and `f` is rerun against a newer
+    /// snapshot, until it completes. `AWAIT_RETRY` discards the writes of `f`, and is waited on by
+    /// the root transaction.
+    #[inline]
+    fn run_nested<F, O, E>(
+        mut self,
+        kind: Nesting,
+        mut f: F,
+    ) -> Result<(Result<O, E>, TxInfo), NestErr>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Abort<E>>,
+    {
+        let thread = self.thread;
+        let _snapshot = RestoreSnapshot::new(thread);
+        let start = self.logs().lens();
+        let mut conflicts = 0;
+        let result = loop {
+            let mut nested = NestedRw::new(self.reborrow());
+            let r = {
+                let _nesting = NestingGuard::new(thread, kind);
+                f(RwTx::new(&mut nested))
+            };
+            match r {
+                Ok(o) => {
+                    nested.merge();
+                    break Ok(o);
+                }
+                Err(Abort {
+                    kind: AbortKind::User(e),
+                }) => {
+                    nested.discard_writes();
+                    break Err(e);
+                }
+                Err(Abort {
+                    kind:
+                        AbortKind::Status(Status {
+                            kind: InternalStatus::Error(_),
+                        }),
+                }) => {}
+                Err(Abort {
+                    kind: AbortKind::Status(_),
+                }) => {
+                    // The root transaction waits on the reads of the nested transaction as well as
+                    // its own.
+                    nested.discard_writes();
+                    thread.restart_root(Restart::AwaitRetry);
+                    return Err(NestErr::AwaitRetry);
+                }
+            }
+            drop(nested);
+            conflicts += 1;
+            self.retry_nested(true);
+        };
+        let (read_len, write_len) = self.logs().lens();
+        let info = TxInfo {
+            eager_conflicts:    conflicts,
+            commit_conflicts:   0,
+            htm_committed:      false,
+            read_log_len:       read_len.saturating_sub(start.0),
+            write_log_word_len: write_len.saturating_sub(start.1),
+            parked:             false,
+            commit_epoch:       self.pin_epoch(),
+        };
+        Ok((result, info))
+    }
+
+    /// Runs a read only transaction without a read log nested inside of the running read only
+    /// transaction, which has no read log either.
+    #[inline]
+    fn run_nested_unlogged<F, O>(mut self, mut f: F) -> Result<(O, TxInfo), NestErr>
+    where
+        F: FnMut(&mut ReadTx<'tcell>) -> Result<O, Status>,
+    {
+        let thread = self.thread;
+        let _snapshot = RestoreSnapshot::new(thread);
+        let mut conflicts = 0;
+        let result = loop {
+            let r = {
+                let _nesting = NestingGuard::new(thread, Nesting::Read);
+                f(ReadTx::new(&self))
+            };
+            match r {
+                Ok(o) => break o,
+                Err(Status {
+                    kind: InternalStatus::Error(_),
+                }) => {}
+                Err(_) => {
+                    thread.restart_root(Restart::AwaitRetry);
+                    return Err(NestErr::AwaitRetry);
+                }
+            }
+            conflicts += 1;
+            self.retry_nested(false);
+        };
+        let info = TxInfo {
+            eager_conflicts:    conflicts,
+            commit_conflicts:   0,
+            htm_committed:      false,
+            read_log_len:       0,
+            write_log_word_len: 0,
+            parked:             false,
+            commit_epoch:       self.pin_epoch(),
+        };
+        Ok((result, info))
+    }
+
+    /// Prepares to rerun a nested transaction that conflicted. If the enclosing transaction can not
+    /// be extended, it is marked for restart, and the nested transaction reads from a newer
+    /// snapshot.
+    #[inline(never)]
+    #[cold]
+    fn retry_nested(&mut self, extendable: bool) {
+        self.thread.failed_to_progress_nested(self.pin_epoch());
+        if extendable && self.thread.restart.get() != Restart::Conflict && self.try_extend() {
+            return;
+        }
+        self.thread.restart_root(Restart::Conflict);
+        let now = EPOCH_CLOCK.now().unwrap_or_else(|| abort!());
+        self.thread.snapshot.set(Some(now));
+    }
+
+    /// Runs `first`. If it requests a retry, its writes are discarded and `second` is run in its
+    /// place. The reads of `first` are kept, so a retry of `second` waits on both read sets.
+    #[inline]
+    pub fn or_else<F1, F2, O>(&mut self, first: F1, second: F2) -> Result<O, Status>
+    where
+        F1: FnOnce(&mut RwTx<'tcell>) -> Result<O, Status>,
+        F2: FnOnce(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        let mut nested = NestedRw::new(self.reborrow());
+        match first(RwTx::new(&mut nested)) {
+            Err(Status::AWAIT_RETRY) => nested.discard_writes(),
+            result => {
+                nested.merge();
+                return result;
+            }
+        }
+        second(RwTx::new(self))
+    }
+
     #[inline]
-    unsafe fn into_inner(self) -> (&'tx OwnedSynch, &'tx mut Logs<'tcell>, &'tx Progress) {
+    pub unsafe fn into_inner(self) -> (&'tx OwnedSynch, &'tx mut Logs<'tcell>, &'tx Progress) {
         let synch = &self.pin_ref.thread.synch;
         let logs = &mut *(self.pin_ref.thread.logs.get() as *const _ as *mut _);
         let progress = &self.pin_ref.thread.progress;
@@ -228,35 +1015,42 @@ impl<'tx, 'tcell> PinMutRef<'tx, 'tcell> {
     }
 }
 
-pub struct Pin<'tcell> {
-    pin_ref: PinRef<'tcell, 'tcell>,
+pub struct Pin<'tx, 'tcell> {
+    pin_ref: PinRef<'tx, 'tcell>,
 }
 
-impl<'tcell> Drop for Pin<'tcell> {
+impl<'tx, 'tcell> Drop for Pin<'tx, 'tcell> {
     #[inline]
     fn drop(&mut self) {
         self.synch().unpin(Release);
         // Panics are more or less considered a successful transaction with no write log.
         self.progress().progressed();
+        self.observer().end(|| self.logs().sizes());
+        // Runs the abort hooks of a transaction that was aborted by the user, or by a panic.
+        self.thread.run_unpinned_hooks();
+        // Releases the references dropped by read only, and aborted transactions.
+        self.thread.release_deferred();
     }
 }
 
-impl<'tcell> Deref for Pin<'tcell> {
-    type Target = PinRef<'tcell, 'tcell>;
+impl<'tx, 'tcell> Deref for Pin<'tx, 'tcell> {
+    type Target = PinRef<'tx, 'tcell>;
 
     #[inline]
-    fn deref(&self) -> &PinRef<'tcell, 'tcell> {
+    fn deref(&self) -> &PinRef<'tx, 'tcell> {
         &self.pin_ref
     }
 }
 
-impl<'tcell> Pin<'tcell> {
+impl<'tx, 'tcell> Pin<'tx, 'tcell> {
     #[inline]
-    fn try_new(thread: &'tcell Thread) -> Option<Pin<'tcell>> {
+    fn try_new(thread: &'tx Thread) -> Option<Self> {
         if likely!(!thread.is_pinned()) {
             let now = EPOCH_CLOCK.now();
             if let Some(now) = now {
                 thread.synch.pin(now, Release);
+                // A panic in the previous transaction may have skipped a requested restart.
+                thread.restart.set(Restart::None);
                 Some(Pin {
                     pin_ref: PinRef {
                         thread,
@@ -273,6 +1067,9 @@ impl<'tcell> Pin<'tcell> {
 
     #[inline]
     fn repin(&mut self) {
+        if unlikely!(self.logs().hooks.has_unpinned()) {
+            return self.run_unpinned_hooks_and_repin();
+        }
         let now = EPOCH_CLOCK.now();
         if let Some(now) = now {
             self.synch().repin(now, Release);
@@ -281,10 +1078,32 @@ impl<'tcell> Pin<'tcell> {
         }
     }
 
+    /// Runs the abort hooks of the discarded attempt outside of the transaction, before starting
+    /// the next attempt.
+    #[inline(never)]
+    #[cold]
+    fn run_unpinned_hooks_and_repin(&mut self) {
+        self.synch().unpin(Release);
+        let _repin = Repin(self.synch());
+        self.thread.run_unpinned_hooks_slow();
+    }
+
     #[inline]
     fn snooze_repin(&mut self) {
-        self.progress().failed_to_progress(self.pin_epoch());
-        self.repin()
+        let epoch = self.pin_epoch();
+        if unlikely!(self.logs().hooks.has_unpinned()) {
+            // The hooks are run before backing off, which may take the starvation lock.
+            self.run_unpinned_hooks_and_repin();
+        }
+        match self.thread.failed_to_progress(epoch, false) {
+            None => self.repin(),
+            Some(duration) => {
+                // Sleeping while pinned would keep every other thread from collecting garbage.
+                self.synch().unpin(Release);
+                let _repin = Repin(self.synch());
+                std::thread::sleep(duration)
+            }
+        }
     }
 
     #[inline]
@@ -293,54 +1112,282 @@ impl<'tcell> Pin<'tcell> {
         mem::forget(self);
     }
 
-    /// Runs a read only transaction.
+    /// Runs a read only transaction, returning the details of how it ran.
+    ///
+    /// Unless `upgradable` is set, reads are not logged until the transaction first returns
+    /// `AWAIT_RETRY`. After that, every attempt records its reads, so that the thread can be parked
+    /// until one of them is modified, and so that the writes of upgrades can be committed.
+    #[inline]
+    pub fn run_read_with<F, O>(mut self, upgradable: bool, mut f: F) -> (O, TxInfo)
+    where
+        F: FnMut(&mut ReadTx<'tcell>) -> Result<O, Status>,
+    {
+        let thread = self.pin_ref.thread;
+        let mut logged = upgradable;
+        let observer = &thread.observer;
+        observer.start(TxKind::Read);
+        let mut eager_conflicts = 0;
+        let mut commit_conflicts = 0;
+        let mut parked = false;
+        let (result, committed, (read_log_len, write_log_word_len)) = loop {
+            observer.begin();
+            let r = if likely!(!logged) {
+                let _timer = stats::read_closure_time();
+                let _nesting = NestingGuard::new(thread, Nesting::Read);
+                thread.take_restart(f(ReadTx::new(&self)))
+            } else {
+                self.logs().validate_start_state();
+                debug_assert!(self.logs().hooks.is_empty());
+                // dropping pin_rw discards the logs
+                let pin_rw = unsafe { PinRw::new(&mut self) };
+                let r = {
+                    let _timer = stats::read_closure_time();
+                    let kind = if upgradable {
+                        Nesting::Upgradable
+                    } else {
+                        Nesting::LoggedRead
+                    };
+                    let _nesting = NestingGuard::new(thread, kind);
+                    thread.take_restart(f(ReadTx::new_logged(&pin_rw)))
+                };
+                match r {
+                    // Publishes the writes of any upgrades.
+                    Ok(o) => {
+                        let event = observer.event(|| pin_rw.logs().sizes());
+                        let log_lens = pin_rw.logs().lens();
+                        if let Some(committed) = pin_rw.commit() {
+                            observer.commit(event, committed.epoch);
+                            self.logs().validate_start_state();
+                            break (o, committed, log_lens);
+                        }
+                        observer.notify(event, TxObserver::on_conflict);
+                        commit_conflicts += 1;
+                    }
+                    Err(Status::AWAIT_RETRY) => {
+                        let result = parking::park(pin_rw, None, None);
+                        debug_assert!(
+                            result.is_ok(),
+                            "parking without a deadline or cancel failed"
+                        );
+                        parked = true;
+                        self.repin();
+                        continue;
+                    }
+                    Err(_) => {
+                        observer.notify(
+                            observer.event(|| pin_rw.logs().sizes()),
+                            TxObserver::on_conflict,
+                        );
+                        eager_conflicts += 1;
+                    }
+                }
+                Err(Error::conflict(ConflictKind::CommitValidation).into())
+            };
+            match r {
+                Ok(o) => {
+                    let epoch = self.pin_epoch();
+                    observer.commit(observer.event(|| (0, 0)), epoch);
+                    break (o, Committed { epoch, htm: false }, (0, 0));
+                }
+                Err(Status {
+                    kind: InternalStatus::Error(_),
+                }) => {
+                    // logged attempts have already been observed, and counted
+                    if !logged {
+                        observer.notify(observer.event(|| (0, 0)), TxObserver::on_conflict);
+                        eager_conflicts += 1;
+                    }
+                }
+                Err(Status::AWAIT_RETRY) => {
+                    // The read set is needed to know which commits should wake the thread.
+                    logged = true;
+                    self.repin();
+                    continue;
+                }
+            }
+            self.snooze_repin();
+        };
+        drop(self);
+        thread.notify_commit();
+        stats::read_transaction_conflicts(eager_conflicts + commit_conflicts);
+        if logged {
+            thread.run_commit_hooks();
+        }
+        let info = TxInfo {
+            eager_conflicts,
+            commit_conflicts,
+            htm_committed: committed.htm,
+            read_log_len,
+            write_log_word_len,
+            parked,
+            commit_epoch: committed.epoch,
+        };
+        (result, info)
+    }
+
+    /// Runs a snapshot transaction. Overwritten values must have been kept since before the thread
+    /// was pinned.
     #[inline]
-    pub fn run_read<F, O>(mut self, mut f: F) -> O
+    pub fn run_snapshot<F, O>(mut self, mut f: F) -> O
     where
-        F: FnMut(&ReadTx<'tcell>) -> Result<O, Error>,
+        F: FnMut(&SnapshotTx<'tcell>) -> Result<O, Error>,
     {
+        let thread = self.pin_ref.thread;
+        let observer = &thread.observer;
+        observer.start(TxKind::Snapshot);
         let mut conflicts = 0;
         let result = loop {
-            let r = f(ReadTx::new(&mut self));
+            observer.begin();
+            let r = {
+                let _timer = stats::read_closure_time();
+                f(SnapshotTx::new(&self))
+            };
             match r {
-                Ok(o) => break o,
-                Err(Error::CONFLICT) => {}
+                Ok(o) => {
+                    observer.commit(observer.event(|| (0, 0)), self.pin_epoch());
+                    break o;
+                }
+                Err(_) => observer.notify(observer.event(|| (0, 0)), TxObserver::on_conflict),
             }
             conflicts += 1;
             self.snooze_repin();
         };
+        drop(self);
+        thread.notify_commit();
         stats::read_transaction_conflicts(conflicts);
         result
     }
 
     /// Runs a read-write transaction.
     #[inline]
-    pub fn run_rw<F, O>(mut self, mut f: F) -> O
+    pub fn run_rw<F, O>(self, mut f: F) -> O
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        match self.run_rw_abortable::<_, _, Infallible>(move |tx| Ok(f(tx)?)) {
+            Ok(o) => o,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Runs a read-write transaction, returning the details of how it ran.
+    #[inline]
+    pub fn run_rw_with_info<F, O>(self, mut f: F) -> (O, TxInfo)
     where
         F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
     {
+        match self.run_rw_with::<_, _, _, Infallible>(move |tx| Ok(f(tx)?), park_forever) {
+            Ok(result) => result,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Runs a read-write transaction, which may be aborted by returning a user defined error.
+    #[inline]
+    pub fn run_rw_abortable<F, O, E>(self, f: F) -> Result<O, E>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Abort<E>>,
+    {
+        self.run_rw_with(f, park_forever).map(|(o, _)| o)
+    }
+
+    /// Runs a read-write transaction, which gives up waiting on `AWAIT_RETRY` once `deadline`
+    /// passes, or `cancel` is cancelled.
+    #[inline]
+    pub fn run_rw_until<F, O>(
+        self,
+        mut f: F,
+        deadline: Option<Instant>,
+        cancel: Option<&Cancel>,
+    ) -> Result<O, AwaitErr>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        self.run_rw_with(
+            move |tx| Ok(f(tx)?),
+            |pin_rw: PinRw<'_, 'tcell>| parking::park(pin_rw, deadline, cancel),
+        )
+        .map(|(o, _)| o)
+    }
+
+    /// Runs a read-write transaction, registering `waker` instead of parking the thread on
+    /// `AWAIT_RETRY`. Returns the registration if the waker was registered.
+    #[inline]
+    pub fn run_rw_or_register<F, O>(self, mut f: F, waker: &Waker) -> Result<O, Registration>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        self.run_rw_with(
+            move |tx| Ok(f(tx)?),
+            |pin_rw: PinRw<'_, 'tcell>| match parking::register_task(pin_rw, waker) {
+                Some(registration) => Err(registration),
+                None => Ok(()),
+            },
+        )
+        .map(|(o, _)| o)
+    }
+
+    /// Runs a read-write transaction, calling `park` whenever the transaction returns
+    /// `AWAIT_RETRY`. Errors returned by `park`, and user aborts, end the transaction.
+    #[inline]
+    fn run_rw_with<F, P, O, E>(mut self, mut f: F, mut park: P) -> Result<(O, TxInfo), E>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Abort<E>>,
+        P: FnMut(PinRw<'_, 'tcell>) -> Result<(), E>,
+    {
+        let thread = self.pin_ref.thread;
+        let observer = &thread.observer;
+        observer.start(TxKind::Rw);
         let mut eager_conflicts = 0;
         let mut commit_conflicts = 0;
-        let result = loop {
+        let mut parked = false;
+        let (result, committed, (read_log_len, write_log_word_len)) = loop {
+            observer.begin();
             self.logs().validate_start_state();
+            debug_assert!(self.logs().hooks.is_empty());
             {
                 let mut pin_rw = unsafe { PinRw::new(&mut self) };
-                let r = f(RwTx::new(&mut pin_rw));
+                let r = {
+                    let _timer = stats::rw_closure_time();
+                    let _nesting = NestingGuard::new(thread, Nesting::Rw);
+                    thread.take_restart(f(RwTx::new(&mut pin_rw)))
+                };
                 match r {
                     Ok(o) => {
-                        if likely!(pin_rw.commit()) {
+                        let event = observer.event(|| pin_rw.logs().sizes());
+                        let log_lens = pin_rw.logs().lens();
+                        if let Some(committed) = pin_rw.commit() {
+                            observer.commit(event, committed.epoch);
                             self.logs().validate_start_state();
-                            break o;
+                            break (o, committed, log_lens);
                         }
+                        observer.notify(event, TxObserver::on_conflict);
                         commit_conflicts += 1;
                     }
-                    Err(Status {
-                        kind: InternalStatus::Error(Error::CONFLICT),
+                    Err(Abort {
+                        kind: AbortKind::User(e),
                     }) => {
+                        // dropping pin_rw discards the logs
+                        drop(pin_rw);
+                        return Err(e);
+                    }
+                    Err(Abort {
+                        kind:
+                            AbortKind::Status(Status {
+                                kind: InternalStatus::Error(_),
+                            }),
+                    }) => {
+                        observer.notify(
+                            observer.event(|| pin_rw.logs().sizes()),
+                            TxObserver::on_conflict,
+                        );
                         eager_conflicts += 1;
                     }
-                    Err(Status::AWAIT_RETRY) => {
-                        crate::internal::parking::park(pin_rw);
+                    Err(Abort {
+                        kind: AbortKind::Status(Status::AWAIT_RETRY),
+                    }) => {
+                        park(pin_rw)?;
+                        parked = true;
                         self.repin();
                         continue;
                     }
@@ -352,6 +1399,47 @@ impl<'tcell> Pin<'tcell> {
         self.unpin_without_progress();
         stats::write_transaction_eager_conflicts(eager_conflicts);
         stats::write_transaction_commit_conflicts(commit_conflicts);
+        thread.notify_commit();
+        thread.run_commit_hooks();
+        let info = TxInfo {
+            eager_conflicts,
+            commit_conflicts,
+            htm_committed: committed.htm,
+            read_log_len,
+            write_log_word_len,
+            parked,
+            commit_epoch: committed.epoch,
+        };
+        Ok((result, info))
+    }
+}
+
+impl<'tcell> Pin<'tcell, 'tcell> {
+    /// Runs an irrevocable transaction. The thread must have been pinned by `try_pin_irrevocable`.
+    #[inline]
+    pub fn run_irrevocable<F, O>(self, f: F) -> O
+    where
+        F: FnOnce(&mut IrrevocableTx<'tcell>) -> O,
+    {
+        let thread = self.pin_ref.thread;
+        let observer = &thread.observer;
+        observer.start(TxKind::Irrevocable);
+        observer.begin();
+        self.logs().validate_start_state();
+        let mut tx = IrrevocableTx::new(PinMutRef {
+            pin_ref: PinRef {
+                thread,
+                phantom: PhantomData,
+            },
+        });
+        let result = f(&mut tx);
+        let event = observer.event(|| self.logs().sizes());
+        observer.commit(event, tx.commit());
+        self.logs().validate_start_state();
+        // The starvation lock was released by the commit
+        self.unpin_without_progress();
+        thread.notify_commit();
+        thread.run_commit_hooks();
         result
     }
 }
@@ -368,6 +1456,7 @@ impl<'tx, 'tcell> Drop for PinRw<'tx, 'tcell> {
         logs.read_log.clear();
         logs.garbage.abort_speculative_garbage();
         logs.write_log.clear();
+        logs.hooks.aborted();
     }
 }
 
@@ -387,10 +1476,73 @@ impl<'tx, 'tcell> DerefMut for PinRw<'tx, 'tcell> {
     }
 }
 
+/// A nested read write transaction. Dropping a `NestedRw` discards everything that was logged by
+/// the nested transaction.
+struct NestedRw<'tx, 'tcell> {
+    pin_ref:    PinMutRef<'tx, 'tcell>,
+    checkpoint: Checkpoint,
+}
+
+impl<'tx, 'tcell> Drop for NestedRw<'tx, 'tcell> {
+    #[inline(never)]
+    #[cold]
+    fn drop(&mut self) {
+        let checkpoint = self.checkpoint;
+        let _nesting = NestingGuard::suspend(self.pin_ref.thread);
+        self.pin_ref.logs_mut().rollback(checkpoint)
+    }
+}
+
+impl<'tx, 'tcell> Deref for NestedRw<'tx, 'tcell> {
+    type Target = PinMutRef<'tx, 'tcell>;
+
+    #[inline]
+    fn deref(&self) -> &PinMutRef<'tx, 'tcell> {
+        &self.pin_ref
+    }
+}
+
+impl<'tx, 'tcell> DerefMut for NestedRw<'tx, 'tcell> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut PinMutRef<'tx, 'tcell> {
+        &mut self.pin_ref
+    }
+}
+
+impl<'tx, 'tcell> NestedRw<'tx, 'tcell> {
+    #[inline]
+    fn new(mut pin_ref: PinMutRef<'tx, 'tcell>) -> Self {
+        let checkpoint = pin_ref.logs_mut().checkpoint();
+        NestedRw {
+            pin_ref,
+            checkpoint,
+        }
+    }
+
+    /// Keeps everything logged by the nested transaction as part of the enclosing transaction.
+    #[inline]
+    fn merge(mut self) {
+        let checkpoint = self.checkpoint;
+        self.pin_ref.logs_mut().merge(checkpoint);
+        mem::forget(self)
+    }
+
+    /// Discards everything written by the nested transaction, but keeps its reads as part of the
+    /// enclosing transaction.
+    #[inline(never)]
+    #[cold]
+    fn discard_writes(mut self) {
+        let checkpoint = self.checkpoint;
+        let _nesting = NestingGuard::suspend(self.pin_ref.thread);
+        self.pin_ref.logs_mut().rollback_writes(checkpoint);
+        mem::forget(self)
+    }
+}
+
 impl<'tx, 'tcell> PinRw<'tx, 'tcell> {
     /// It is not safe to mem::forget PinRw
     #[inline]
-    unsafe fn new(pin: &'tx mut Pin<'tcell>) -> Self {
+    unsafe fn new(pin: &'tx mut Pin<'_, 'tcell>) -> Self {
         PinRw {
             pin_ref: PinMutRef {
                 pin_ref: pin.pin_ref.reborrow(),
@@ -453,8 +1605,9 @@ impl<'tx, 'tcell> ParkPinMutRef<'tx, 'tcell> {
 
         result.logs.garbage.abort_speculative_garbage();
 
-        // this can panic
+        // these can panic
         unsafe { result.logs.write_log.drop_writes() };
+        result.logs.hooks.aborted();
         synch.unpin(Relaxed);
         result
     }
@@ -0,0 +1,153 @@
+//! Previous values of `TCell`s, kept for snapshot transactions.
+//!
+//! While any snapshot transaction is running, committing threads copy the values they overwrite
+//! into a global table, along with the range of epochs during which each value was current. A
+//! snapshot transaction that finds a `TCell` was written after its pinned epoch reads the value in
+//! the table instead of failing.
+//!
+//! The copies are shallow. Anything they own is kept alive by the garbage of the transaction that
+//! overwrote them, and the table entries are removed when that garbage is collected. Since garbage
+//! is only collected once every thread that was pinned before the overwrite has unpinned, a
+//! snapshot transaction never observes a removed entry that it could have read.
+//!
+//! Entries are keyed by the address of the `TCell`. Every `TCell` a snapshot transaction can reach
+//! was at its address before the transaction was pinned, and stays there until it unpins. So an
+//! entry whose range contains the pinned epoch always belongs to the `TCell` being read.
+
+use crate::internal::{
+    buckets::Buckets,
+    epoch::{EpochRange, QuiesceEpoch},
+    gc::ThreadGarbage,
+    tcell_erased::TCellErased,
+    write_log::WriteLog,
+};
+use core::{
+    mem::{self, ManuallyDrop},
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+};
+use fxhash::FxHashMap;
+
+/// The number of snapshot transactions that are running.
+static SNAPSHOTS: AtomicUsize = AtomicUsize::new(0);
+
+struct Version {
+    range: EpochRange,
+    value: Box<[usize]>,
+}
+
+lazy_static::lazy_static! {
+    /// Versions keyed by the address of the `TCellErased` they were read from. The table is split
+    /// into buckets by the same address, so that commits writing unrelated `TCell`s do not contend.
+    static ref VERSIONS: Buckets<FxHashMap<usize, Vec<Version>>> = Buckets::new();
+}
+
+/// Returns true if committing threads should keep the values they overwrite.
+///
+/// Missing versions only cause snapshot transactions to retry, so this is only a hint.
+#[inline]
+pub fn is_active() -> bool {
+    SNAPSHOTS.load(Relaxed) != 0
+}
+
+/// Requests that committing threads keep the values they overwrite, until dropped.
+#[derive(Debug)]
+pub struct SnapshotGuard(());
+
+impl Drop for SnapshotGuard {
+    #[inline]
+    fn drop(&mut self) {
+        drop(SNAPSHOTS.fetch_sub(1, Relaxed))
+    }
+}
+
+impl SnapshotGuard {
+    #[inline]
+    pub fn new() -> Self {
+        drop(SNAPSHOTS.fetch_add(1, Relaxed));
+        SnapshotGuard(())
+    }
+}
+
+/// Reads the value of `tcell` that was current at `pin_epoch`, if it was kept.
+///
+/// Must be called while pinned at `pin_epoch`.
+#[inline(never)]
+#[cold]
+pub unsafe fn find<T>(tcell: &TCellErased, pin_epoch: QuiesceEpoch) -> Option<ManuallyDrop<T>> {
+    let tcell = tcell as *const _ as usize;
+    let versions = VERSIONS.lock(tcell);
+    let version = versions
+        .get(&tcell)?
+        .iter()
+        .find(|version| version.range.contains(pin_epoch))?;
+    debug_assert!(
+        version.value.len() * mem::size_of::<usize>() >= mem::size_of::<T>(),
+        "`TCell` version is smaller than expected"
+    );
+    Some(ptr::read_unaligned(
+        version.value.as_ptr() as *const ManuallyDrop<T>
+    ))
+}
+
+/// Values that are about to be overwritten by a commit.
+pub struct Pending {
+    versions: Vec<(usize, Version)>,
+}
+
+impl Pending {
+    /// Copies the current values of every `TCell` in the write log. The calling thread must hold
+    /// the locks of those `TCell`s, and not have performed the writes yet.
+    #[inline(never)]
+    #[cold]
+    pub unsafe fn capture(write_log: &WriteLog<'_>) -> Self {
+        let versions = write_log
+            .write_entries()
+            .filter_map(|entry| {
+                let tcell = (*entry.tcell())?;
+                let len = mem::size_of_val(entry) / mem::size_of::<usize>() - 1;
+                let src = NonNull::from(tcell).cast::<usize>().as_ptr().sub(len);
+                let value = std::slice::from_raw_parts(src, len).into();
+                let version = Version {
+                    range: EpochRange::start_locked(&tcell.current_epoch),
+                    value,
+                };
+                Some((tcell as *const _ as usize, version))
+            })
+            .collect();
+        Pending { versions }
+    }
+
+    /// Makes the versions visible to snapshot transactions. `end` is the epoch the writes are about
+    /// to be published with. The versions are removed once `garbage` is collected.
+    #[inline(never)]
+    #[cold]
+    pub fn publish(self, end: QuiesceEpoch, garbage: &mut ThreadGarbage) {
+        let mut retired = Vec::with_capacity(self.versions.len());
+        for (tcell, mut version) in self.versions {
+            version.range.end(end);
+            retired.push((tcell, version.range));
+            VERSIONS.lock(tcell).entry(tcell).or_default().push(version);
+        }
+        garbage.dispose(ManuallyDrop::new(Retire { retired }))
+    }
+}
+
+/// Removes versions from the table when dropped by the garbage collector.
+struct Retire {
+    retired: Vec<(usize, EpochRange)>,
+}
+
+impl Drop for Retire {
+    fn drop(&mut self) {
+        for &(tcell, range) in &self.retired {
+            let mut versions = VERSIONS.lock(tcell);
+            if let Some(tcell_versions) = versions.get_mut(&tcell) {
+                tcell_versions.retain(|version| version.range != range);
+                if tcell_versions.is_empty() {
+                    drop(versions.remove(&tcell))
+                }
+            }
+        }
+    }
+}
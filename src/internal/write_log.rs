@@ -1,6 +1,9 @@
 use crate::{
     internal::{
-        alloc::dyn_vec::{self, DynElemMut, TraitObject},
+        alloc::{
+            dyn_vec::{self, DynElemMut, TraitObject},
+            FVec,
+        },
         bloom::{Bloom, Contained},
         epoch::{EpochLock, QuiesceEpoch},
         tcell_erased::TCellErased,
@@ -86,20 +89,43 @@ impl<'tcell> dyn WriteEntry + 'tcell {
 
 dyn_vec_decl! {struct DynVecWriteEntry: WriteEntry;}
 
+/// A write log entry of a parent transaction that has been superseded by a nested transaction.
+#[derive(Copy, Clone)]
+struct Tombstone<'tcell> {
+    index:          usize,
+    tcell:          &'tcell TCellErased,
+    drop_on_commit: bool,
+}
+
+/// The state of the `WriteLog` at the start of a nested transaction.
+#[derive(Copy, Clone, Debug)]
+pub struct WriteCheckpoint {
+    word_len:   usize,
+    tombstones: usize,
+    parent_len: usize,
+}
+
 /// TODO: WriteLog is very very slow if the bloom filter fails.
 /// probably worth looking into some true hashmaps
 #[repr(C)]
 pub struct WriteLog<'tcell> {
-    bloom: Bloom<'tcell, TCellErased>,
-    data:  DynVecWriteEntry<'tcell>,
+    bloom:      Bloom<'tcell, TCellErased>,
+    data:       DynVecWriteEntry<'tcell>,
+    tombstones: FVec<Tombstone<'tcell>>,
+
+    /// Entries before this word index belong to a parent transaction, and must not be overwritten
+    /// in place.
+    parent_len: usize,
 }
 
 impl<'tcell> WriteLog<'tcell> {
     #[inline]
     pub fn new() -> Self {
         WriteLog {
-            bloom: Bloom::new(),
-            data:  DynVecWriteEntry::new(),
+            bloom:      Bloom::new(),
+            data:       DynVecWriteEntry::new(),
+            tombstones: FVec::new(),
+            parent_len: 0,
         }
     }
 
@@ -123,7 +149,8 @@ impl<'tcell> WriteLog<'tcell> {
     #[inline]
     pub fn clear(&mut self) {
         self.bloom.clear();
-        // TODO: NESTING: tx's can start here
+        self.tombstones.clear();
+        self.parent_len = 0;
         stats::write_word_size(self.word_len());
         self.data.clear();
     }
@@ -131,17 +158,92 @@ impl<'tcell> WriteLog<'tcell> {
     #[inline]
     pub fn clear_no_drop(&mut self) {
         self.bloom.clear();
+        self.parent_len = 0;
+        if unlikely!(!self.tombstones.is_empty()) {
+            unsafe { self.drop_tombstones() }
+        }
         stats::write_word_size(self.word_len());
         self.data.clear_no_drop();
     }
 
+    #[inline(never)]
+    #[cold]
+    unsafe fn drop_tombstones(&mut self) {
+        for tombstone in self.tombstones.drain(..) {
+            if tombstone.drop_on_commit {
+                ptr::drop_in_place::<dyn WriteEntry>(
+                    &mut *self.data.word_index_unchecked_mut(tombstone.index),
+                )
+            }
+        }
+    }
+
     #[inline]
     pub unsafe fn drop_writes(&mut self) {
+        // tombstoned entries are dropped here as well
+        self.tombstones.clear();
         for mut elem in self.data.iter_mut() {
             ptr::drop_in_place::<dyn WriteEntry>(&mut *elem)
         }
     }
 
+    /// Marks the start of a nested transaction. Entries recorded before the checkpoint will no
+    /// longer be overwritten in place.
+    #[inline]
+    pub fn checkpoint(&mut self) -> WriteCheckpoint {
+        let checkpoint = WriteCheckpoint {
+            word_len:   self.word_len(),
+            tombstones: self.tombstones.len(),
+            parent_len: self.parent_len,
+        };
+        self.parent_len = checkpoint.word_len;
+        checkpoint
+    }
+
+    /// Ends a nested transaction, keeping all of its writes.
+    #[inline]
+    pub fn merge(&mut self, checkpoint: WriteCheckpoint) {
+        debug_assert!(
+            self.parent_len == checkpoint.word_len,
+            "merged an out of order checkpoint"
+        );
+        self.parent_len = checkpoint.parent_len;
+    }
+
+    /// Ends a nested transaction, discarding all of its writes.
+    #[inline(never)]
+    #[cold]
+    pub fn rollback(&mut self, checkpoint: WriteCheckpoint) {
+        debug_assert!(
+            self.parent_len == checkpoint.word_len,
+            "rolled back an out of order checkpoint"
+        );
+        let word_len = checkpoint.word_len;
+        let overflowed = self.bloom.has_overflowed();
+        if overflowed {
+            self.bloom.overflow_retain(|_, &mut index| index < word_len);
+        }
+
+        // Any entries of the parent that were superseded by the nested transaction are revived.
+        for tombstone in self.tombstones.drain(checkpoint.tombstones..) {
+            if tombstone.index < word_len {
+                unsafe {
+                    let mut entry = self.data.word_index_unchecked_mut(tombstone.index);
+                    debug_assert!(entry.tcell().is_none(), "revived a live write log entry");
+                    *entry.tcell_mut() = Some(tombstone.tcell);
+                }
+                debug_assert!(overflowed, "tombstones require an overflowed bloom filter");
+                drop(self.bloom.overflow_insert(tombstone.tcell, tombstone.index));
+            }
+        }
+
+        unsafe { self.data.truncate(word_len) };
+        if self.data.is_empty() {
+            self.bloom.clear();
+        }
+        self.parent_len = checkpoint.parent_len;
+    }
+
     #[inline]
     pub fn is_empty(&self) -> bool {
         let empty = self.data.is_empty();
@@ -231,7 +333,7 @@ impl<'tcell> WriteLog<'tcell> {
                 stats::bloom_success_slow();
                 stats::write_after_write();
                 debug_assert!(*o.get() < self.data.word_len());
-                Entry::new_occupied(o, &mut self.data)
+                Entry::new_occupied(o, &mut self.data, &mut self.tombstones, self.parent_len)
             }
             HashMapEntry::Vacant(v) => {
                 drop(v.insert(self.data.word_len()));
@@ -286,22 +388,35 @@ impl<'a, 'tcell> Entry<'a, 'tcell> {
     fn new_occupied(
         entry: HashMapOccupiedEntry<'a, *const TCellErased, usize>,
         data: &'a mut DynVecWriteEntry<'tcell>,
+        tombstones: &'a mut FVec<Tombstone<'tcell>>,
+        parent_len: usize,
     ) -> Self {
         Entry::Occupied(OccupiedEntry {
             entry,
             data,
+            tombstones,
+            parent_len,
             phantom: PhantomData,
         })
     }
 }
 
 pub struct OccupiedEntry<'a, 'tcell> {
-    entry:   HashMapOccupiedEntry<'a, *const TCellErased, usize>,
-    data:    &'a mut DynVecWriteEntry<'tcell>,
-    phantom: PhantomData<Vec<&'tcell TCellErased>>,
+    entry:      HashMapOccupiedEntry<'a, *const TCellErased, usize>,
+    data:       &'a mut DynVecWriteEntry<'tcell>,
+    tombstones: &'a mut FVec<Tombstone<'tcell>>,
+    parent_len: usize,
+    phantom:    PhantomData<Vec<&'tcell TCellErased>>,
 }
 
 impl<'a, 'tcell> OccupiedEntry<'a, 'tcell> {
+    /// Returns true if the entry was written by a parent of the currently running nested
+    /// transaction.
+    #[inline]
+    pub fn belongs_to_parent(&self) -> bool {
+        *self.entry.get() < self.parent_len
+    }
+
     pub fn overwrite<T: 'static>(self, dest_tcell: &'tcell TCellErased, val: T) {
         let new_entry = WriteEntryImpl::new(dest_tcell, val);
         let new_vtable = dyn_vec::vtable::<dyn WriteEntry + 'tcell>(&new_entry);
@@ -311,7 +426,15 @@ impl<'a, 'tcell> OccupiedEntry<'a, 'tcell> {
         }
     }
 
-    pub fn tombstone_replace<T: 'static>(mut self, dest_tcell: &'tcell TCellErased, val: T) {
+    /// Hides the previous entry, and pushes a new one. If the previous entry belonged to a parent
+    /// transaction, `drop_on_commit` controls whether its value is dropped at commit time.
+    pub fn tombstone_replace<T: 'static>(
+        mut self,
+        dest_tcell: &'tcell TCellErased,
+        val: T,
+        drop_on_commit: bool,
+    ) {
+        let parent_len = self.parent_len;
         let prev = self.entry.insert(self.data.word_len());
         let mut entry = unsafe { self.data.word_index_unchecked_mut(prev) };
         debug_assert!(
@@ -319,6 +442,13 @@ impl<'a, 'tcell> OccupiedEntry<'a, 'tcell> {
             "unexpectedly tombstoning an already tombstoned write log entry"
         );
         *entry.tcell_mut() = None;
+        if prev < parent_len {
+            self.tombstones.push(Tombstone {
+                index: prev,
+                tcell: dest_tcell,
+                drop_on_commit,
+            });
+        }
         self.data.push(WriteEntryImpl::new(dest_tcell, val));
     }
 }
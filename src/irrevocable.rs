@@ -0,0 +1,215 @@
+//! Irrevocable transactions run user code exactly once.
+//!
+//! Before the transaction starts, the starvation lock is acquired, preventing any other thread from
+//! starting a commit. Threads that were already committing when the lock was acquired may still
+//! publish their writes, so every TCell accessed by an irrevocable transaction is locked until it
+//! commits (strict two phase locking). Those commits are short, and no new ones can start, so
+//! waiting on the lock of a TCell always terminates.
+
+use crate::{
+    internal::{
+        epoch::{ParkStatus, QuiesceEpoch},
+        tcell_erased::TCellErased,
+        thread::{Logs, PinMutRef},
+        write_log::Entry,
+    },
+    stats,
+    tcell::{Ref, TCell},
+    tx::{self, Error, Ordering, SetError, Write, _TValue},
+};
+use core::{
+    cell::{Cell, UnsafeCell},
+    fmt::{self, Debug, Formatter},
+    mem::{self, ManuallyDrop},
+    ptr,
+    sync::atomic::{self, Ordering::Acquire},
+};
+use crossbeam_utils::Backoff;
+use fxhash::FxHashSet;
+
+/// A read write transaction that can not fail.
+///
+/// See [`ThreadKey::rw_irrevocable`](crate::thread_key::ThreadKey::rw_irrevocable).
+pub struct IrrevocableTx<'tcell> {
+    pin_ref: UnsafeCell<PinMutRef<'tcell, 'tcell>>,
+
+    /// Every TCell locked by this transaction. The read log holds the same TCells in the order
+    /// they were locked.
+    locked: UnsafeCell<FxHashSet<*const TCellErased>>,
+
+    /// Whether any of the locked TCells had threads parked on them.
+    has_parked: Cell<bool>,
+}
+impl<'tcell> !Send for IrrevocableTx<'tcell> {}
+impl<'tcell> !Sync for IrrevocableTx<'tcell> {}
+
+impl<'tcell> Debug for IrrevocableTx<'tcell> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("IrrevocableTx")
+            .field("pin_mut_ref", unsafe { &*self.pin_ref.get() })
+            .finish()
+    }
+}
+
+impl<'tcell> Drop for IrrevocableTx<'tcell> {
+    #[inline(never)]
+    #[cold]
+    fn drop(&mut self) {
+        // Only reached if user code panics.
+        let logs = self.logs_mut();
+        logs.read_log
+            .epoch_locks()
+            .for_each(|epoch_lock| unsafe { epoch_lock.unlock_undo() });
+        logs.read_log.clear();
+        logs.garbage.abort_speculative_garbage();
+        logs.write_log.clear();
+        logs.hooks.aborted();
+    }
+}
+
+impl<'tcell> IrrevocableTx<'tcell> {
+    /// The current thread must hold the starvation lock.
+    #[inline]
+    pub(crate) fn new(pin_ref: PinMutRef<'tcell, 'tcell>) -> Self {
+        IrrevocableTx {
+            pin_ref:    UnsafeCell::new(pin_ref),
+            locked:     UnsafeCell::new(FxHashSet::default()),
+            has_parked: Cell::new(false),
+        }
+    }
+
+    /// Publishes the writes, releases every lock held by the transaction, and returns the epoch it
+    /// committed at.
+    #[inline]
+    pub(crate) fn commit(self) -> QuiesceEpoch {
+        let park_status = if self.has_parked.get() {
+            ParkStatus::HasParked
+        } else {
+            ParkStatus::NoParked
+        };
+        let this = ManuallyDrop::new(self);
+        unsafe {
+            drop(ptr::read(&this.locked));
+            ptr::read(&this.pin_ref)
+                .into_inner()
+                .commit_irrevocable(park_status)
+        }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    #[inline]
+    fn logs_mut(&self) -> &mut Logs<'tcell> {
+        // Only the current thread has access to the logs, and references to them never escape a
+        // single method call.
+        unsafe { (*self.pin_ref.get()).logs_mut() }
+    }
+
+    /// Locks `erased`, unless it has already been locked by this transaction.
+    #[inline]
+    fn lock(&self, erased: &'tcell TCellErased) {
+        let locked = unsafe { &mut *self.locked.get() };
+        if locked.insert(erased as *const _) {
+            self.lock_slow(erased)
+        }
+    }
+
+    #[inline(never)]
+    fn lock_slow(&self, erased: &'tcell TCellErased) {
+        let backoff = Backoff::new();
+        let park_status = loop {
+            match erased.current_epoch.try_lock_any() {
+                Some(park_status) => break park_status,
+                // Another thread is in the middle of committing.
+                None => backoff.snooze(),
+            }
+        };
+        // Synchronizes with the commit that last published a value to the TCell.
+        atomic::fence(Acquire);
+        if park_status == ParkStatus::HasParked {
+            self.has_parked.set(true)
+        }
+        self.logs_mut().read_log.record(erased)
+    }
+
+    #[inline]
+    fn set_impl<T: Send + 'static, V: _TValue<T>>(&mut self, tcell: &'tcell TCell<T>, value: V) {
+        self.lock(&tcell.erased);
+        unsafe {
+            match self.logs_mut().write_log.entry(&tcell.erased) {
+                Entry::Vacant => {
+                    let logs = self.logs_mut();
+                    logs.write_log.record(&tcell.erased, value);
+                    if mem::needs_drop::<T>() && !V::PRIVATIZES_PREVIOUS {
+                        logs.garbage.dispose(tcell.optimistic_read_relaxed())
+                    }
+                }
+                Entry::Occupied(o) => {
+                    if V::REQUEST_TCELL_LIFETIME || V::PRIVATIZES_PREVIOUS {
+                        o.tombstone_replace(&tcell.erased, value, false);
+                    } else {
+                        o.overwrite(&tcell.erased, value);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'tcell> tx::Read<'tcell> for IrrevocableTx<'tcell> {
+    #[inline]
+    fn borrow<'tx, T>(
+        &'tx self,
+        tcell: &'tcell TCell<T>,
+        _: Ordering,
+    ) -> Result<Ref<'tx, T>, Error> {
+        unsafe {
+            if mem::size_of::<T>() != 0 {
+                self.lock(&tcell.erased);
+                Ok(match self.logs_mut().write_log.find(&tcell.erased) {
+                    None => Ref::new(tcell.optimistic_read_acquire()),
+                    Some(entry) => {
+                        stats::read_after_write();
+                        Ref::new(entry.read::<T>())
+                    }
+                })
+            } else {
+                // If the type is zero sized, there's no need to any synchronization.
+                Ok(Ref::new(mem::zeroed::<ManuallyDrop<T>>()))
+            }
+        }
+    }
+}
+
+impl<'tcell> Write<'tcell> for IrrevocableTx<'tcell> {
+    #[inline]
+    fn set<T: Send + 'static>(
+        &mut self,
+        tcell: &'tcell TCell<T>,
+        value: impl _TValue<T>,
+    ) -> Result<(), SetError<T>> {
+        assert_eq!(
+            mem::size_of_val(&value),
+            mem::size_of::<T>(),
+            "write log values must be the same size as the value of the `TCell`"
+        );
+        if mem::size_of::<T>() != 0 {
+            self.set_impl(tcell, value)
+        } else {
+            // If the type is zero sized, there's no need to any synchronization.
+            drop(value)
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn _privatize<F: FnOnce() + Send + 'static>(&mut self, privatizer: F) {
+        self.logs_mut().garbage.dispose_fn(privatizer)
+    }
+
+    #[inline]
+    fn _on_abort<F: FnOnce() + Send + 'static>(&mut self, f: F) {
+        // irrevocable transactions only abort if user code panics
+        self.logs_mut().hooks.on_abort(f)
+    }
+}
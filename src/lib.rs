@@ -43,35 +43,65 @@
 //! * Highly optimized for read mostly data structures and modern caches. `TCell` stores all of its
 //!   data inline. Read only transactions don't modify any global state, and read write transactions
 //!   only modify global state on commit.
-//! * Parking retry is supported via [`AWAIT_RETRY`](crate::tx::Status::AWAIT_RETRY).
+//! * Parking retry is supported via [`AWAIT_RETRY`](crate::tx::Status::AWAIT_RETRY). Async tasks
+//!   can wait on retries without blocking the thread using [`future::rw`].
 //! * The number of allocations imposed by swym per transaction should average 0 through reuse of
 //!   read logs/write logs/garbage bags.
 //! * Support for building recursive data structures using `TPtr` is still experimental but looks
 //!   promising - see examples on github.
 //! * Backed by a custom epoch based reclaimation style garbage collector (still lots of
 //!   optimization work to do there).
-//! * Support for nested transactions is planned, but not yet implemented.
+//! * Closed nested transactions. Transactions started inside of a running transaction are nested
+//!   inside of it, and can be retried without retrying the enclosing transaction. [`RwTx::nested`]
+//!   runs a nested transaction directly on the enclosing transaction.
+//! * Configurable backoff under contention via a per thread
+//!   [`ContentionManager`](crate::contention::ContentionManager).
+//! * Opt-in [`diagnostics`] identifying the `TCell` that caused a conflict, and a [`profile`]r
+//!   reporting the most contended `TCell`s.
+//! * Instrumentation of every transaction attempt through an [`observer`], with optional `tracing`
+//!   support.
 //!
 //! ## Shared Memory
 //!
 //! * [`TCell`], a low level transactional memory location - does not perform any heap allocation.
 //! * [`TPtr`], a low level transactional pointer for building heap allocated data structures.
+//! * [`TBox`], a safe transactional pointer owning its heap allocated pointee.
+//! * [`TArc`] and [`TWeak`], transactional reference counted pointers for sharing heap allocated
+//!   values between multiple owners.
 //!
 //! ## Running Transactions
 //!
 //! * [`rw`], starts a read write transaction.
 //! * [`read`], starts a read only transaction.
+//! * [`read_awaitable`], starts a read only transaction which can wait on `AWAIT_RETRY`.
+//! * [`read_upgradable`], starts a read only transaction which can be upgraded to write.
 //! * [`try_rw`], starts a read write transaction returning an error if the transaction could not be
 //!   started.
 //! * [`try_read`], starts a read only transaction returning an error if the transaction could not
 //!   be started.
+//! * [`read_snapshot`], starts a read only transaction that is not restarted by concurrent writes.
+//! * [`rw_irrevocable`], starts a read write transaction that runs exactly once, blocking other
+//!   threads from committing until it finishes.
+//! * [`rw_with_info`] and [`read_with_info`], start transactions that also return how many times
+//!   they conflicted, the size of their logs, and the epoch they committed at.
 //!
 //! [`TCell`]: tcell/struct.TCell.html
 //! [`TPtr`]: tptr/struct.TPtr.html
+//! [`TBox`]: tbox/struct.TBox.html
+//! [`TArc`]: tarc/struct.TArc.html
+//! [`TWeak`]: tarc/struct.TWeak.html
 //! [`rw`]: thread_key/struct.ThreadKey.html#method.rw
+//! [`RwTx::nested`]: struct.RwTx.html#method.nested
 //! [`read`]: thread_key/struct.ThreadKey.html#method.read
+//! [`read_awaitable`]: thread_key/struct.ThreadKey.html#method.read_awaitable
+//! [`read_upgradable`]: thread_key/struct.ThreadKey.html#method.read_upgradable
 //! [`try_rw`]: thread_key/struct.ThreadKey.html#method.try_rw
 //! [`try_read`]: thread_key/struct.ThreadKey.html#method.try_read
+//! [`read_snapshot`]: thread_key/struct.ThreadKey.html#method.read_snapshot
+//! [`rw_irrevocable`]: thread_key/struct.ThreadKey.html#method.rw_irrevocable
+//! [`rw_with_info`]: thread_key/struct.ThreadKey.html#method.rw_with_info
+//! [`read_with_info`]: thread_key/struct.ThreadKey.html#method.read_with_info
+//! [`future::rw`]: future/fn.rw.html
 
 #![feature(optin_builtin_traits)]
 #![cfg_attr(feature = "nightly", feature(cfg_target_thread_local))]
@@ -91,16 +121,27 @@
 #[macro_use]
 mod internal;
 
+pub mod contention;
+pub mod diagnostics;
+pub mod future;
+mod irrevocable;
+pub mod observer;
+pub mod profile;
 mod read;
 mod rw;
+mod snapshot;
 pub mod stats;
+pub mod tarc;
+pub mod tbox;
 pub mod tcell;
 pub mod thread_key;
 pub mod tptr;
 pub mod tx;
 
+pub use irrevocable::IrrevocableTx;
 pub use read::ReadTx;
 pub use rw::RwTx;
+pub use snapshot::SnapshotTx;
 #[doc(inline)]
 pub use swym_htm as htm;
 
@@ -369,25 +410,34 @@ mod panic {
         thread::scope(|s| {
             s.spawn(|_| {
                 let thread_key = thread_key::get();
+                let mut x = TCell::new(0);
                 assert!(
                     thread_key
-                        .try_rw(|_| {
-                            assert!(
-                                thread_key.try_rw(|_| Ok(())).is_err(),
-                                "nesting unexpectedly did not cause an error"
-                            );
+                        .try_rw(|tx| {
+                            x.set(tx, 1)?;
+                            let inner = thread_key.try_rw(|tx| {
+                                let inner = x.get(tx, Default::default())?;
+                                x.set(tx, inner + 1)?;
+                                Ok(inner)
+                            });
+                            assert_eq!(inner.ok(), Some(1), "failed to nest a read write tx");
                             Ok(())
                         })
                         .is_ok(),
                     "nesting prevented the root transaction from committing"
                 );
+                assert_eq!(x.borrow_mut(), &2, "nested writes were not committed");
 
                 assert!(
                     thread_key
-                        .try_rw(|_| {
-                            assert!(
-                                thread_key.try_read(|_| Ok(())).is_err(),
-                                "nesting unexpectedly did not cause an error"
+                        .try_rw(|tx| {
+                            x.set(tx, 3)?;
+                            assert_eq!(
+                                thread_key
+                                    .try_read(|tx| Ok(x.get(tx, Default::default())?))
+                                    .ok(),
+                                Some(3),
+                                "failed to nest a read only tx inside of a read write tx"
                             );
                             Ok(())
                         })
@@ -397,10 +447,14 @@ mod panic {
 
                 assert!(
                     thread_key
-                        .try_read(|_| {
-                            assert!(
-                                thread_key.try_read(|_| Ok(())).is_err(),
-                                "nesting unexpectedly did not cause an error"
+                        .try_read(|tx| {
+                            let outer = x.get(tx, Default::default())?;
+                            assert_eq!(
+                                thread_key
+                                    .try_read(|tx| Ok(x.get(tx, Default::default())?))
+                                    .ok(),
+                                Some(outer),
+                                "failed to nest a read only tx inside of a read only tx"
                             );
                             Ok(())
                         })
@@ -413,7 +467,7 @@ mod panic {
                         .try_read(|_| {
                             assert!(
                                 thread_key.try_rw(|_| Ok(())).is_err(),
-                                "nesting unexpectedly did not cause an error"
+                                "nesting a read write tx inside of a read only tx did not fail"
                             );
                             Ok(())
                         })
@@ -424,4 +478,28 @@ mod panic {
         })
         .unwrap();
     }
+
+    #[test]
+    fn nested_panic() {
+        let tcell = TCell::new("hello".to_owned());
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                let result = thread_key.rw(|tx| {
+                    tcell.set(tx, "outer".to_owned())?;
+                    let caught = panic::catch_unwind(AssertUnwindSafe(|| {
+                        tx.nested(|tx| -> Result<(), _> {
+                            tcell.set(tx, "inner".to_owned())?;
+                            panic!("test panic")
+                        })
+                    }));
+                    assert!(caught.is_err());
+                    Ok(tcell.borrow(tx, Ordering::default())?.clone())
+                });
+                assert_eq!(result, "outer", "failed to roll back a panicking nested tx");
+            });
+        })
+        .unwrap();
+        assert_eq!(tcell.into_inner(), "outer");
+    }
 }
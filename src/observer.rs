@@ -0,0 +1,416 @@
+//! Instrumentation of transactions.
+//!
+//! A [`TxObserver`] is notified as transaction attempts begin, conflict and commit, as transactions
+//! are aborted, and as threads park, or are blocked by a starving thread. An observer is either set
+//! globally with [`set_global`], receiving the events of every thread, or on a single thread with
+//! [`ThreadKey::set_observer`](crate::thread_key::ThreadKey::set_observer). Events are only built
+//! while an observer is set.
+//!
+//! With the `tracing` feature, `Tracing` emits a span for every transaction attempt.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+//! use swym::{
+//!     observer::{TxEvent, TxObserver},
+//!     tcell::TCell,
+//!     thread_key,
+//! };
+//!
+//! #[derive(Default)]
+//! struct WriteSetSizes {
+//!     total: AtomicUsize,
+//! }
+//!
+//! impl TxObserver for WriteSetSizes {
+//!     fn on_commit(&self, event: &TxEvent) {
+//!         drop(self.total.fetch_add(event.write_set_size(), Relaxed));
+//!     }
+//! }
+//!
+//! let thread_key = thread_key::get();
+//! thread_key.set_observer(WriteSetSizes::default());
+//!
+//! let x = TCell::new(0);
+//! let y = TCell::new(0);
+//! thread_key.rw(|tx| {
+//!     x.set(tx, 1)?;
+//!     Ok(y.set(tx, 1)?)
+//! });
+//! # drop(thread_key.take_observer());
+//! ```
+
+use crate::internal::epoch::QuiesceEpoch;
+use core::{
+    cell::{Cell, RefCell},
+    fmt::{self, Debug, Formatter},
+    mem,
+    sync::atomic::{AtomicBool, Ordering::Relaxed},
+};
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+/// Receives the events of transactions.
+///
+/// Callbacks are run while the thread is inside of a transaction, so they must not start
+/// transactions of their own.
+pub trait TxObserver {
+    /// Called before each attempt of a transaction runs its closure.
+    #[inline]
+    fn on_begin(&self, _event: &TxEvent) {}
+
+    /// Called after an attempt fails, before the transaction is retried.
+    #[inline]
+    fn on_conflict(&self, _event: &TxEvent) {}
+
+    /// Called after a transaction commits.
+    #[inline]
+    fn on_commit(&self, _event: &TxEvent) {}
+
+    /// Called after a transaction ends without committing: its closure aborted it or panicked, or
+    /// waiting for a change to its read set timed out, was cancelled, or was left to the waker of a
+    /// future.
+    #[inline]
+    fn on_abort(&self, _event: &TxEvent) {}
+
+    /// Called before the thread parks, waiting for a change to the read set of an attempt that
+    /// returned [`AWAIT_RETRY`](crate::tx::Status::AWAIT_RETRY).
+    #[inline]
+    fn on_park(&self, _event: &TxEvent) {}
+
+    /// Called after the thread stops parking, before the transaction is retried.
+    #[inline]
+    fn on_unpark(&self, _event: &TxEvent) {}
+
+    /// Called after the thread was blocked from committing by another thread that was starving, or
+    /// running an irrevocable transaction.
+    #[inline]
+    fn on_starvation(&self, _event: &TxEvent) {}
+}
+
+impl Debug for dyn TxObserver {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter.pad("TxObserver { .. }")
+    }
+}
+
+impl Debug for dyn TxObserver + Send + Sync {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter.pad("TxObserver { .. }")
+    }
+}
+
+/// The kind of transaction an event belongs to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TxKind {
+    /// A read only transaction, started by [`read`](crate::thread_key::ThreadKey::read).
+    Read,
+
+    /// A snapshot transaction, started by
+    /// [`read_snapshot`](crate::thread_key::ThreadKey::read_snapshot).
+    Snapshot,
+
+    /// A read write transaction, started by [`rw`](crate::thread_key::ThreadKey::rw), or one of
+    /// its variants.
+    Rw,
+
+    /// An irrevocable transaction, started by
+    /// [`rw_irrevocable`](crate::thread_key::ThreadKey::rw_irrevocable).
+    Irrevocable,
+}
+
+/// An event of a transaction attempt.
+#[derive(Copy, Clone, Debug)]
+pub struct TxEvent {
+    kind:           TxKind,
+    attempt:        u32,
+    read_set_size:  usize,
+    write_set_size: usize,
+    commit_epoch:   Option<QuiesceEpoch>,
+}
+
+impl TxEvent {
+    /// Returns the kind of the transaction.
+    #[inline]
+    pub fn kind(&self) -> TxKind {
+        self.kind
+    }
+
+    /// Returns the number of the attempt, starting from 1.
+    #[inline]
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Returns the number of `TCell`s in the read log when the event happened. Read only
+    /// transactions only log their reads after returning
+    /// [`AWAIT_RETRY`](crate::tx::Status::AWAIT_RETRY), or upgrading.
+    #[inline]
+    pub fn read_set_size(&self) -> usize {
+        self.read_set_size
+    }
+
+    /// Returns the number of writes in the write log when the event happened.
+    #[inline]
+    pub fn write_set_size(&self) -> usize {
+        self.write_set_size
+    }
+
+    /// Returns the epoch the transaction committed at, for commit events.
+    ///
+    /// Epochs are logical timestamps. A transaction that commits after another one, and writes to
+    /// a `TCell` it accessed, has a larger commit epoch. Transactions without writes commit at the
+    /// epoch they started at, which may be shared with other commits.
+    #[inline]
+    pub fn commit_epoch(&self) -> Option<usize> {
+        self.commit_epoch.map(|epoch| epoch.get().get())
+    }
+}
+
+/// Whether a global observer is set.
+static GLOBAL_SET: AtomicBool = AtomicBool::new(false);
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: RwLock<Option<Arc<dyn TxObserver + Send + Sync>>> = RwLock::new(None);
+}
+
+/// Sets the observer receiving the events of every thread, returning the previous one.
+pub fn set_global<O>(observer: O) -> Option<Arc<dyn TxObserver + Send + Sync>>
+where
+    O: TxObserver + Send + Sync + 'static,
+{
+    replace_global(Some(Arc::new(observer)))
+}
+
+/// Removes the observer receiving the events of every thread, returning it.
+pub fn take_global() -> Option<Arc<dyn TxObserver + Send + Sync>> {
+    replace_global(None)
+}
+
+fn replace_global(
+    observer: Option<Arc<dyn TxObserver + Send + Sync>>,
+) -> Option<Arc<dyn TxObserver + Send + Sync>> {
+    let mut global = GLOBAL.write();
+    GLOBAL_SET.store(observer.is_some(), Relaxed);
+    mem::replace(&mut *global, observer)
+}
+
+/// One of the methods of `TxObserver`.
+type Callback = fn(&(dyn TxObserver + 'static), &TxEvent);
+
+/// The observer of a single thread, and the transaction attempt that is running on it.
+pub(crate) struct ThreadObserver {
+    observer: RefCell<Option<Box<dyn TxObserver>>>,
+    set:      Cell<bool>,
+    running:  Cell<bool>,
+    kind:     Cell<TxKind>,
+    attempt:  Cell<u32>,
+}
+
+impl Debug for ThreadObserver {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("ThreadObserver")
+            .field("set", &self.set.get())
+            .field("running", &self.running.get())
+            .field("kind", &self.kind.get())
+            .field("attempt", &self.attempt.get())
+            .finish()
+    }
+}
+
+impl ThreadObserver {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        ThreadObserver {
+            observer: RefCell::new(None),
+            set:      Cell::new(false),
+            running:  Cell::new(false),
+            kind:     Cell::new(TxKind::Rw),
+            attempt:  Cell::new(0),
+        }
+    }
+
+    /// Replaces the observer of the current thread, returning the previous one.
+    pub(crate) fn replace(
+        &self,
+        observer: Option<Box<dyn TxObserver>>,
+    ) -> Option<Box<dyn TxObserver>> {
+        let set = observer.is_some();
+        let prev = self
+            .observer
+            .try_borrow_mut()
+            .map(|mut prev| mem::replace(&mut *prev, observer))
+            .expect("observers cannot be replaced from inside of their own callbacks");
+        self.set.set(set);
+        prev
+    }
+
+    /// Returns whether events are observed.
+    #[inline]
+    pub(crate) fn is_active(&self) -> bool {
+        unlikely!(self.set.get() || GLOBAL_SET.load(Relaxed))
+    }
+
+    /// Called as a root transaction starts.
+    #[inline]
+    pub(crate) fn start(&self, kind: TxKind) {
+        if self.is_active() {
+            self.running.set(true);
+            self.kind.set(kind);
+            self.attempt.set(0);
+        }
+    }
+
+    /// Called before each attempt of the transaction runs its closure.
+    #[inline]
+    pub(crate) fn begin(&self) {
+        if self.is_active() {
+            self.attempt.set(self.attempt.get() + 1);
+            self.notify(self.event(|| (0, 0)), TxObserver::on_begin)
+        }
+    }
+
+    /// Builds an event for the running attempt, if events are observed. `sizes` returns the read
+    /// set size and write set size.
+    #[inline]
+    pub(crate) fn event(&self, sizes: impl FnOnce() -> (usize, usize)) -> Option<TxEvent> {
+        if self.is_active() {
+            let (read_set_size, write_set_size) = sizes();
+            Some(TxEvent {
+                kind: self.kind.get(),
+                attempt: self.attempt.get(),
+                read_set_size,
+                write_set_size,
+                commit_epoch: None,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Notifies the observers that the running attempt committed at `commit_epoch`.
+    #[inline]
+    pub(crate) fn commit(&self, event: Option<TxEvent>, commit_epoch: QuiesceEpoch) {
+        self.running.set(false);
+        self.notify(
+            event.map(move |event| TxEvent {
+                commit_epoch: Some(commit_epoch),
+                ..event
+            }),
+            TxObserver::on_commit,
+        )
+    }
+
+    /// Called as the thread is unpinned. Notifies the observers that the running transaction was
+    /// aborted, if it has not committed. `sizes` returns the read set size and write set size.
+    #[inline]
+    pub(crate) fn end(&self, sizes: impl FnOnce() -> (usize, usize)) {
+        if unlikely!(self.running.get()) {
+            self.running.set(false);
+            self.notify(self.event(sizes), TxObserver::on_abort)
+        }
+    }
+
+    /// Passes `event` to `callback` of every observer, if there is an event.
+    #[inline]
+    pub(crate) fn notify(&self, event: Option<TxEvent>, callback: Callback) {
+        if let Some(event) = event {
+            self.notify_slow(&event, callback)
+        }
+    }
+
+    #[inline(never)]
+    #[cold]
+    fn notify_slow(&self, event: &TxEvent, callback: Callback) {
+        let global = GLOBAL.read().clone();
+        if let Some(global) = global {
+            callback(&*global, event)
+        }
+        if let Some(observer) = &*self.observer.borrow() {
+            callback(&**observer, event)
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+thread_local! {
+    static ATTEMPT: RefCell<Option<tracing::span::EnteredSpan>> = RefCell::new(None);
+}
+
+/// Emits a `tracing` span for every transaction attempt, with events for its outcome, parking and
+/// starvation.
+///
+/// Spans are named `swym::attempt`, and are recorded at the `TRACE` level.
+#[cfg(feature = "tracing")]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Tracing;
+
+#[cfg(feature = "tracing")]
+impl Tracing {
+    fn end_attempt() {
+        // the span is taken out first, so that it is exited before another one is entered
+        let span = ATTEMPT.with(|attempt| attempt.borrow_mut().take());
+        drop(span)
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl TxObserver for Tracing {
+    fn on_begin(&self, event: &TxEvent) {
+        Tracing::end_attempt();
+        let span = tracing::trace_span!(
+            "swym::attempt",
+            kind = ?event.kind(),
+            attempt = event.attempt()
+        )
+        .entered();
+        ATTEMPT.with(move |attempt| *attempt.borrow_mut() = Some(span))
+    }
+
+    fn on_conflict(&self, event: &TxEvent) {
+        tracing::trace!(
+            read_set_size = event.read_set_size(),
+            write_set_size = event.write_set_size(),
+            "conflict"
+        );
+        Tracing::end_attempt()
+    }
+
+    fn on_commit(&self, event: &TxEvent) {
+        tracing::trace!(
+            read_set_size = event.read_set_size(),
+            write_set_size = event.write_set_size(),
+            commit_epoch = ?event.commit_epoch(),
+            "commit"
+        );
+        Tracing::end_attempt()
+    }
+
+    fn on_park(&self, event: &TxEvent) {
+        tracing::trace!(
+            read_set_size = event.read_set_size(),
+            write_set_size = event.write_set_size(),
+            "park"
+        )
+    }
+
+    fn on_abort(&self, event: &TxEvent) {
+        tracing::trace!(
+            read_set_size = event.read_set_size(),
+            write_set_size = event.write_set_size(),
+            "abort"
+        );
+        Tracing::end_attempt()
+    }
+
+    fn on_unpark(&self, _: &TxEvent) {
+        tracing::trace!("unpark");
+        Tracing::end_attempt()
+    }
+
+    fn on_starvation(&self, _: &TxEvent) {
+        tracing::trace!("blocked by a starving thread")
+    }
+}
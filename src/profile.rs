@@ -0,0 +1,224 @@
+//! Per `TCell` contention profiling.
+//!
+//! While [`enable`]d, the profiler counts the conflicts, commit time lock failures and parked
+//! waiters of every `TCell`, keyed by the address of the `TCell`. The counts are only updated on
+//! the slow paths of failed transactions, and parking.
+//!
+//! [`hot_spots`] reports the most contended `TCell`s. `TCell`s given the same name with
+//! [`diagnostics::set_name`](crate::diagnostics::set_name) are reported as a single hot spot, which
+//! is useful for measuring sharded data.
+//!
+//! # Examples
+//!
+//! ```
+//! use swym::{diagnostics, profile, tcell::TCell, thread_key};
+//!
+//! static COUNTER: TCell<usize> = TCell::new(0);
+//! diagnostics::set_name(&COUNTER, "counter");
+//!
+//! profile::enable();
+//! thread_key::get().rw(|tx| {
+//!     let next = COUNTER.get(tx, Default::default())? + 1;
+//!     Ok(COUNTER.set(tx, next)?)
+//! });
+//! profile::disable();
+//!
+//! profile::print_hot_spots(10);
+//! ```
+
+use crate::{
+    diagnostics,
+    internal::{epoch::EpochLock, tcell_erased::TCellErased},
+};
+use core::{
+    cmp::Reverse,
+    sync::atomic::{AtomicBool, Ordering::Relaxed},
+};
+use fxhash::FxHashMap;
+use parking_lot::Mutex;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Copy, Clone, Default, Debug)]
+struct Counts {
+    conflicts:     u64,
+    lock_failures: u64,
+    parked:        u64,
+}
+
+lazy_static::lazy_static! {
+    /// Counts keyed by the address of the `TCellErased` they were recorded for.
+    static ref COUNTS: Mutex<FxHashMap<usize, Counts>> = Mutex::new(FxHashMap::default());
+}
+
+/// Starts counting contention.
+#[inline]
+pub fn enable() {
+    ENABLED.store(true, Relaxed)
+}
+
+/// Stops counting contention. Existing counts are kept until [`reset`].
+#[inline]
+pub fn disable() {
+    ENABLED.store(false, Relaxed)
+}
+
+/// Returns true if contention is being counted.
+#[inline]
+pub fn is_enabled() -> bool {
+    ENABLED.load(Relaxed)
+}
+
+/// Discards all counts.
+pub fn reset() {
+    COUNTS.lock().clear()
+}
+
+/// The contention of a `TCell`, or of every `TCell` sharing a name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HotSpot {
+    name:          Option<&'static str>,
+    address:       Option<usize>,
+    cells:         usize,
+    conflicts:     u64,
+    lock_failures: u64,
+    parked:        u64,
+}
+
+impl HotSpot {
+    /// Returns the name shared by the `TCell`s of the hot spot, if they were named.
+    #[inline]
+    pub fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    /// Returns the address of the `TCell`, if it was not named.
+    #[inline]
+    pub fn address(&self) -> Option<usize> {
+        self.address
+    }
+
+    /// Returns the number of `TCell`s that were counted as part of the hot spot.
+    #[inline]
+    pub fn cells(&self) -> usize {
+        self.cells
+    }
+
+    /// Returns the number of times a transaction conflicted while reading, writing or validating
+    /// the `TCell`s.
+    #[inline]
+    pub fn conflicts(&self) -> u64 {
+        self.conflicts
+    }
+
+    /// Returns the number of times a committing transaction failed to lock the `TCell`s.
+    #[inline]
+    pub fn lock_failures(&self) -> u64 {
+        self.lock_failures
+    }
+
+    /// Returns the number of times a thread parked waiting for the `TCell`s to be modified.
+    #[inline]
+    pub fn parked(&self) -> u64 {
+        self.parked
+    }
+
+    /// Returns the sum of all counts.
+    #[inline]
+    pub fn total(&self) -> u64 {
+        self.conflicts + self.lock_failures + self.parked
+    }
+}
+
+/// Returns up to `count` of the most contended hot spots, most contended first.
+pub fn hot_spots(count: usize) -> Vec<HotSpot> {
+    let counts = COUNTS.lock().clone();
+    let mut named = FxHashMap::<&'static str, HotSpot>::default();
+    let mut hot_spots = Vec::new();
+    for (address, counts) in counts {
+        let name = diagnostics::name_of(address);
+        let hot_spot = match name {
+            Some(name) => named.entry(name).or_insert_with(|| HotSpot {
+                name:          Some(name),
+                address:       None,
+                cells:         0,
+                conflicts:     0,
+                lock_failures: 0,
+                parked:        0,
+            }),
+            None => {
+                hot_spots.push(HotSpot {
+                    name:          None,
+                    address:       Some(address),
+                    cells:         0,
+                    conflicts:     0,
+                    lock_failures: 0,
+                    parked:        0,
+                });
+                hot_spots.last_mut().unwrap()
+            }
+        };
+        hot_spot.cells += 1;
+        hot_spot.conflicts += counts.conflicts;
+        hot_spot.lock_failures += counts.lock_failures;
+        hot_spot.parked += counts.parked;
+    }
+    hot_spots.extend(named.drain().map(|(_, hot_spot)| hot_spot));
+    hot_spots.sort_by_key(|hot_spot| Reverse(hot_spot.total()));
+    hot_spots.truncate(count);
+    hot_spots
+}
+
+/// Prints up to `count` of the most contended hot spots.
+pub fn print_hot_spots(count: usize) {
+    println!(
+        "{:>24} {:>8} {:>12} {:>14} {:>12}",
+        "tcell", "cells", "conflicts", "lock failures", "parked"
+    );
+    for hot_spot in hot_spots(count) {
+        let label = match hot_spot.name {
+            Some(name) => name.to_owned(),
+            None => format!("{:#x}", hot_spot.address.unwrap_or_default()),
+        };
+        println!(
+            "{:>24} {:>8} {:>12} {:>14} {:>12}",
+            label, hot_spot.cells, hot_spot.conflicts, hot_spot.lock_failures, hot_spot.parked
+        );
+    }
+}
+
+#[inline(never)]
+#[cold]
+fn record(tcell: &TCellErased, f: impl FnOnce(&mut Counts)) {
+    f(COUNTS.lock().entry(tcell as *const _ as usize).or_default())
+}
+
+#[inline]
+pub(crate) fn conflict(tcell: &TCellErased) {
+    if unlikely!(is_enabled()) {
+        record(tcell, |counts| counts.conflicts += 1)
+    }
+}
+
+#[inline]
+pub(crate) fn lock_failure(epoch_lock: &EpochLock) {
+    if unlikely!(is_enabled()) {
+        record(TCellErased::from_epoch_lock(epoch_lock), |counts| {
+            counts.lock_failures += 1
+        })
+    }
+}
+
+#[inline]
+pub(crate) fn parked<'a>(epoch_locks: impl Iterator<Item = &'a EpochLock>) {
+    if unlikely!(is_enabled()) {
+        let mut all_counts = COUNTS.lock();
+        for epoch_lock in epoch_locks {
+            let tcell = TCellErased::from_epoch_lock(epoch_lock);
+            all_counts
+                .entry(tcell as *const _ as usize)
+                .or_default()
+                .parked += 1;
+        }
+    }
+}
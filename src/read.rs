@@ -1,7 +1,12 @@
 use crate::{
-    internal::{epoch::QuiesceEpoch, thread::Pin},
+    diagnostics,
+    internal::{
+        epoch::QuiesceEpoch,
+        thread::{PinRef, Thread},
+    },
+    rw::RwTx,
     tcell::{Ref, TCell},
-    tx::{Borrow, Error, Ordering, Read},
+    tx::{Borrow, Error, Ordering, Read, Status},
 };
 use core::{
     fmt::{self, Debug, Formatter},
@@ -12,7 +17,9 @@ use core::{
 /// A read only transaction.
 ///
 /// No instances of this type are ever created. References to values of this type are created by
-/// transmuting QuiesceEpoch's.
+/// transmuting QuiesceEpoch's, or, for transactions that keep a read log, references to the pinned
+/// `Thread`. Pinned epochs are always odd, and `Thread`s are aligned, so the low bit of the address
+/// tells the two apart.
 ///
 /// The lifetime contravariance allows conversions of ReadTx<'a> into ReadTx<'static>.
 pub struct ReadTx<'tcell>(PhantomData<fn(&'tcell ())>);
@@ -29,19 +36,127 @@ impl<'tcell> Debug for ReadTx<'tcell> {
 }
 
 impl<'tcell> ReadTx<'tcell> {
+    #[allow(clippy::mut_from_ref)]
     #[inline]
-    pub(crate) fn new<'tx>(pin: &'tx mut Pin<'tcell>) -> &'tx Self {
+    pub(crate) fn new<'tx>(pin: &'tx PinRef<'_, 'tcell>) -> &'tx mut Self {
         assert!(mem::align_of::<Self>() == 1, "unsafe alignment on ReadTx");
         // we smuggle the pinned epoch through as a reference
         // QuiesceEpoch is NonZero
         let pin_epoch: QuiesceEpoch = pin.pin_epoch();
-        unsafe { mem::transmute::<QuiesceEpoch, &'tx Self>(pin_epoch) }
+        unsafe { mem::transmute::<QuiesceEpoch, &'tx mut Self>(pin_epoch) }
+    }
+
+    /// Runs `f` as a read write transaction, whose writes are committed when the read only
+    /// transaction completes.
+    ///
+    /// Upgradable transactions are started with
+    /// [`ThreadKey::read_upgradable`](crate::thread_key::ThreadKey::read_upgradable), which records
+    /// every read without checking it against a write log. Any writes of `f` are visible to later
+    /// reads of the read only transaction, and are committed after validating every read.
+    ///
+    /// Transactions started with [`ThreadKey::read`](crate::thread_key::ThreadKey::read) do not
+    /// record their reads, so there is nothing to validate the writes against. They are only
+    /// handed a shared `&ReadTx`, and can not be upgraded.
+    ///
+    /// # Errors
+    ///
+    /// If `f` conflicts, it is retried without restarting the read only transaction, as long as
+    /// nothing the transaction has read has been modified. Otherwise, the conflict is returned, and
+    /// should be passed on with `?` so that the transaction is retried. Returning
+    /// [`Status::AWAIT_RETRY`] from `f` discards its writes, and returns `AWAIT_RETRY`, which waits
+    /// on every read of the transaction when passed on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key, tx::Ordering};
+    ///
+    /// let cells: Vec<_> = (0..10).map(TCell::new).collect();
+    ///
+    /// let thread_key = thread_key::get();
+    /// let sum = thread_key.read_upgradable(|tx| {
+    ///     let mut sum = 0;
+    ///     for cell in &cells {
+    ///         sum += cell.get(tx, Ordering::default())?;
+    ///     }
+    ///     if sum == 45 {
+    ///         tx.upgrade(|tx| Ok(cells[0].set(tx, 1)?))?;
+    ///     }
+    ///     Ok(sum)
+    /// });
+    /// assert_eq!(sum, 45);
+    /// assert_eq!(thread_key.read(|tx| Ok(cells[0].get(tx, Ordering::default())?)), 1);
+    /// ```
+    #[inline]
+    pub fn upgrade<F, O>(&mut self, f: F) -> Result<O, Status>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        // Only upgradable transactions hand out mutable references, and they always keep a read
+        // log.
+        debug_assert!(self.is_logged());
+        unsafe { self.pin_ref().run_upgrade(f) }
+    }
+
+    /// Creates a read only transaction that records every read in the read log, so that it can be
+    /// parked on `AWAIT_RETRY`, or upgraded.
+    #[allow(clippy::mut_from_ref)]
+    #[inline]
+    pub(crate) fn new_logged<'tx>(pin: &'tx PinRef<'_, 'tcell>) -> &'tx mut Self {
+        unsafe { &mut *(pin.thread_ptr() as *mut Self) }
+    }
+
+    /// Reinterprets a read write transaction as a logged read only transaction. Both are
+    /// references to the pinned `Thread`.
+    #[inline]
+    pub(crate) fn from_rw<'tx>(rw_tx: &'tx mut RwTx<'tcell>) -> &'tx mut Self {
+        unsafe { &mut *(rw_tx as *mut RwTx<'tcell> as *mut Self) }
+    }
+
+    #[inline]
+    fn is_logged(&self) -> bool {
+        self as *const Self as usize & 1 == 0
+    }
+
+    #[inline]
+    fn pin_ref(&self) -> PinRef<'_, 'tcell> {
+        debug_assert!(self.is_logged());
+        unsafe { PinRef::from_thread_ptr(self as *const Self as *const Thread) }
     }
 
     #[inline]
     fn pin_epoch(&self) -> QuiesceEpoch {
-        // convert the reference back into the smuggled pinned epoch
-        unsafe { mem::transmute::<&Self, _>(self) }
+        if self.is_logged() {
+            self.pin_ref().pin_epoch()
+        } else {
+            // convert the reference back into the smuggled pinned epoch
+            unsafe { mem::transmute::<&Self, QuiesceEpoch>(self) }
+        }
+    }
+
+    #[inline(never)]
+    #[cold]
+    unsafe fn borrow_logged<'tx, T: Borrow>(
+        &'tx self,
+        tcell: &'tcell TCell<T>,
+    ) -> Result<Ref<'tx, T>, Error> {
+        let pin_ref = self.pin_ref();
+        if unlikely!(!pin_ref.logs().write_log.is_empty()) {
+            // The transaction has been upgraded, so it has to see its own writes. Both types of
+            // transactions are references to the pinned `Thread`.
+            let rw_tx = &*(self as *const Self as *const RwTx<'tcell>);
+            return rw_tx.borrow(tcell, Ordering::ReadWrite);
+        }
+        let value = Ref::new(tcell.optimistic_read_acquire());
+        if likely!(pin_ref
+            .pin_epoch()
+            .read_write_valid_lockable(&tcell.erased.current_epoch))
+        {
+            pin_ref.record_read(&tcell.erased);
+            Ok(value)
+        } else {
+            Err(diagnostics::access_conflict(&tcell.erased))
+        }
     }
 }
 
@@ -53,7 +168,9 @@ impl<'tcell> Read<'tcell> for ReadTx<'tcell> {
         _: Ordering,
     ) -> Result<Ref<'tx, T>, Error> {
         unsafe {
-            if mem::size_of::<T>() != 0 {
+            if unlikely!(self.is_logged()) {
+                self.borrow_logged(tcell)
+            } else if mem::size_of::<T>() != 0 {
                 // In a read only transaction, there is no read log, write log or gc.
                 // The only thing that needs to be done is reading of the value, and then a check,
                 // to see if that value was written before this transaction began.
@@ -64,7 +181,7 @@ impl<'tcell> Read<'tcell> for ReadTx<'tcell> {
                 {
                     Ok(value)
                 } else {
-                    Err(Error::CONFLICT)
+                    Err(diagnostics::access_conflict(&tcell.erased))
                 }
             } else {
                 // If the type is zero sized, there's no need to any synchronization.
@@ -10,21 +10,17 @@
 //! 2007 masters thesis: https://run.unl.pt/bitstream/10362/2312/1/Cunha_2007.pdf
 
 use crate::{
-    internal::{
-        bloom::Contained,
-        tcell_erased::TCellErased,
-        thread::{PinMutRef, PinRw},
-        write_log::Entry,
-    },
+    diagnostics,
+    internal::{bloom::Contained, tcell_erased::TCellErased, thread::PinMutRef, write_log::Entry},
     stats,
     tcell::{Ref, TCell},
-    tx::{self, Error, Ordering, SetError, Write, _TValue},
+    tx::{self, Abort, Error, Ordering, SetError, Status, Write, _TValue},
 };
 use core::{
+    convert::Infallible,
     fmt::{self, Debug, Formatter},
     marker::PhantomData,
     mem::{self, ManuallyDrop},
-    ptr,
 };
 
 #[derive(Debug)]
@@ -50,9 +46,9 @@ impl<'tx, 'tcell> core::ops::DerefMut for RwTxImpl<'tx, 'tcell> {
 
 impl<'tx, 'tcell> RwTxImpl<'tx, 'tcell> {
     #[inline]
-    fn new(pin_rw: &'tx mut PinRw<'_, 'tcell>) -> Self {
+    fn new(pin_ref: &'tx mut PinMutRef<'_, 'tcell>) -> Self {
         RwTxImpl {
-            pin_ref: pin_rw.reborrow(),
+            pin_ref: pin_ref.reborrow(),
         }
     }
 
@@ -85,7 +81,7 @@ impl<'tx, 'tcell> RwTxImpl<'tx, 'tcell> {
                 }
             }
         }
-        Err(Error::CONFLICT)
+        Err(diagnostics::access_conflict(&tcell.erased))
     }
 
     #[inline]
@@ -123,11 +119,12 @@ impl<'tx, 'tcell> RwTxImpl<'tx, 'tcell> {
                 return Ok(snapshot);
             }
         }
-        Err(Error::CONFLICT)
+        Err(diagnostics::access_conflict(&tcell.erased))
     }
 
     #[inline]
-    fn borrow_unlogged_impl<T>(self, tcell: &'tcell TCell<T>) -> Result<Ref<'tx, T>, Error> {
+    fn borrow_unlogged_impl<T>(mut self, tcell: &'tcell TCell<T>) -> Result<Ref<'tx, T>, Error> {
+        self.logs_mut().read_log.record_unlogged();
         let logs = self.logs();
         if likely!(logs.write_log.contained(&tcell.erased) == Contained::No) {
             unsafe {
@@ -147,21 +144,31 @@ impl<'tx, 'tcell> RwTxImpl<'tx, 'tcell> {
         tcell: &'tcell TCell<T>,
         value: V,
     ) -> Result<(), SetError<T>> {
+        // Overwriting an entry drops the value it held while the write log is borrowed.
+        let _nesting = self.suspend_nesting();
         unsafe {
             match self.logs_mut().write_log.entry(&tcell.erased) {
                 Entry::Vacant => {
                     if likely!(self.rw_valid(&tcell.erased)) {
                         let logs = self.logs_mut();
                         logs.write_log.record(&tcell.erased, value);
-                        if mem::needs_drop::<T>() {
+                        if mem::needs_drop::<T>() && !V::PRIVATIZES_PREVIOUS {
                             logs.garbage.dispose(tcell.optimistic_read_relaxed())
                         }
                         return Ok(());
                     }
                 }
                 Entry::Occupied(o) => {
-                    if V::REQUEST_TCELL_LIFETIME {
-                        o.tombstone_replace(&tcell.erased, value);
+                    // Values written by a parent transaction must survive until the nested
+                    // transaction either commits or rolls back. A privatized value is owned by its
+                    // privatizer once the transaction commits.
+                    if V::REQUEST_TCELL_LIFETIME || V::PRIVATIZES_PREVIOUS || o.belongs_to_parent()
+                    {
+                        o.tombstone_replace(
+                            &tcell.erased,
+                            value,
+                            !V::REQUEST_TCELL_LIFETIME && !V::PRIVATIZES_PREVIOUS,
+                        );
                     } else {
                         o.overwrite(&tcell.erased, value);
                     }
@@ -173,7 +180,7 @@ impl<'tx, 'tcell> RwTxImpl<'tx, 'tcell> {
             mem::forget(value);
             Err(SetError {
                 value: casted,
-                error: Error::CONFLICT,
+                error: diagnostics::access_conflict(&tcell.erased),
             })
         }
     }
@@ -186,14 +193,16 @@ impl<'tx, 'tcell> RwTxImpl<'tx, 'tcell> {
     ) -> Result<(), SetError<T>> {
         let logs = self.logs();
         if likely!(!logs.write_log.next_push_allocates::<V>())
-            && (!mem::needs_drop::<T>() || likely!(!logs.garbage.next_dispose_allocates::<T>()))
+            && (!mem::needs_drop::<T>()
+                || V::PRIVATIZES_PREVIOUS
+                || likely!(!logs.garbage.next_dispose_allocates::<T>()))
             && likely!(logs.write_log.contained_set(&tcell.erased) == Contained::No)
             && likely!(self.rw_valid(&tcell.erased))
         {
             let logs = self.logs_mut();
             unsafe {
                 logs.write_log.record_unchecked(&tcell.erased, value);
-                if mem::needs_drop::<T>() {
+                if mem::needs_drop::<T>() && !V::PRIVATIZES_PREVIOUS {
                     logs.garbage
                         .dispose_unchecked(tcell.optimistic_read_relaxed())
                 }
@@ -224,14 +233,171 @@ impl<'tcell> Debug for RwTx<'tcell> {
 
 impl<'tcell> RwTx<'tcell> {
     #[inline]
-    pub(crate) fn new<'tx>(pin_rw: &'tx mut PinRw<'_, 'tcell>) -> &'tx mut Self {
-        unsafe { mem::transmute(RwTxImpl::new(pin_rw)) }
+    pub(crate) fn new<'tx>(pin_ref: &'tx mut PinMutRef<'_, 'tcell>) -> &'tx mut Self {
+        unsafe { mem::transmute(RwTxImpl::new(pin_ref)) }
     }
 
     #[inline]
     fn as_impl(&self) -> RwTxImpl<'_, 'tcell> {
         unsafe { mem::transmute(self) }
     }
+
+    /// Registers a closure to be run after the transaction successfully commits.
+    ///
+    /// The closure runs on the current thread after the transaction's writes have been published,
+    /// and the thread has left the transaction, so it is free to perform I/O or start new
+    /// transactions. Closures run in the order they were registered. If the transaction does not
+    /// commit, the closure is dropped without being called.
+    ///
+    /// When registered by a nested transaction, the closure only runs if the outermost transaction
+    /// commits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::mpsc;
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let (sender, receiver) = mpsc::channel();
+    /// let x = TCell::new(0);
+    ///
+    /// thread_key::get().rw(|tx| {
+    ///     x.set(tx, 42)?;
+    ///     let sender = sender.clone();
+    ///     tx.on_commit(move || sender.send("committed").unwrap());
+    ///     Ok(())
+    /// });
+    /// assert_eq!(receiver.try_iter().collect::<Vec<_>>(), ["committed"]);
+    /// ```
+    #[inline]
+    pub fn on_commit<F: FnOnce() + Send + 'tcell>(&mut self, f: F) {
+        self.as_impl().logs_mut().hooks.on_commit(f)
+    }
+
+    /// Registers a closure to be run if this attempt at the transaction is discarded.
+    ///
+    /// This includes conflicts, returning [`Status::AWAIT_RETRY`], aborting with a user defined
+    /// error, and panics. The closure is dropped without being called if the transaction commits.
+    /// Closures registered by a nested transaction also run when only the nested transaction is
+    /// discarded, but not until the outermost transaction has finished, or is retried.
+    ///
+    /// Like [`on_commit`](RwTx::on_commit), the closure runs after the thread has left the
+    /// transaction, so it is free to perform I/O or start new transactions. It may borrow anything
+    /// that outlives the outermost transaction.
+    #[inline]
+    pub fn on_abort<F: FnOnce() + Send + 'tcell>(&mut self, f: F) {
+        self.as_impl().logs_mut().hooks.on_abort(f)
+    }
+
+    /// Runs `first`, falling back to `second` if `first` returns [`Status::AWAIT_RETRY`].
+    ///
+    /// When `first` requests a retry, everything it has written is discarded before `second` runs
+    /// as part of the same transaction. Everything `first` read stays in the read set, so if
+    /// `second` also returns `AWAIT_RETRY`, the thread is parked until a change to a `TCell` read
+    /// by either alternative. Any other result of `first` is returned without running `second`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key, tx::Status, RwTx};
+    ///
+    /// fn pop<'tcell>(
+    ///     tx: &mut RwTx<'tcell>,
+    ///     queue: &'tcell TCell<Vec<usize>>,
+    /// ) -> Result<usize, Status> {
+    ///     let mut v = queue.borrow(tx, Default::default())?.clone();
+    ///     match v.pop() {
+    ///         Some(x) => {
+    ///             queue.set(tx, v)?;
+    ///             Ok(x)
+    ///         }
+    ///         None => Err(Status::AWAIT_RETRY),
+    ///     }
+    /// }
+    ///
+    /// let queue_a = TCell::new(Vec::new());
+    /// let queue_b = TCell::new(vec![42]);
+    ///
+    /// let thread_key = thread_key::get();
+    /// let x = thread_key.rw(|tx| tx.or_else(|tx| pop(tx, &queue_a), |tx| pop(tx, &queue_b)));
+    /// assert_eq!(x, 42);
+    /// ```
+    #[inline]
+    pub fn or_else<F1, F2, O>(&mut self, first: F1, second: F2) -> Result<O, Status>
+    where
+        F1: FnOnce(&mut RwTx<'tcell>) -> Result<O, Status>,
+        F2: FnOnce(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        self.as_impl().or_else(first, second)
+    }
+
+    /// Runs `f` as a closed nested transaction.
+    ///
+    /// The nested transaction runs on the logs of this transaction. It sees everything this
+    /// transaction has written, and its own writes are only published when the outermost
+    /// transaction commits. When `f` conflicts, only the writes of the nested transaction are
+    /// discarded, and `f` is retried, as long as everything this transaction has read is still
+    /// valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this transaction has to be retried, either because it is no longer
+    /// valid, or because `f` returned [`Status::AWAIT_RETRY`]. The writes of `f` are discarded,
+    /// but its reads are kept, so that waiting covers the reads of both transactions. The error
+    /// should be returned from the enclosing transaction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key, tx::Status, RwTx};
+    ///
+    /// fn increment<'tcell>(tx: &mut RwTx<'tcell>, x: &'tcell TCell<usize>) -> Result<(), Status> {
+    ///     tx.nested(|tx| {
+    ///         let next = x.get(tx, Default::default())? + 1;
+    ///         Ok(x.set(tx, next)?)
+    ///     })
+    /// }
+    ///
+    /// let x = TCell::new(0);
+    /// let y = TCell::new(0);
+    ///
+    /// thread_key::get().rw(|tx| {
+    ///     increment(tx, &x)?;
+    ///     increment(tx, &y)?;
+    ///     assert_eq!(x.get(tx, Default::default())?, 1);
+    ///     Ok(())
+    /// });
+    /// assert_eq!((x.into_inner(), y.into_inner()), (1, 1));
+    /// ```
+    #[inline]
+    pub fn nested<F, O>(&mut self, mut f: F) -> Result<O, Status>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        match self.nested_abortable::<_, _, Infallible>(move |tx| Ok(f(tx)?))? {
+            Ok(o) => Ok(o),
+            Err(never) => match never {},
+        }
+    }
+
+    /// Runs `f` as a closed nested transaction, which can be aborted with a user defined error.
+    ///
+    /// Returning [`Abort::new(e)`](Abort::new) from `f` discards everything the nested transaction
+    /// has written, and returns `Ok(Err(e))`. The enclosing transaction is free to continue, and
+    /// the reads of `f` are kept as part of it. Otherwise, this behaves like
+    /// [`nested`](RwTx::nested).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the enclosing transaction has to be retried. See
+    /// [`nested`](RwTx::nested).
+    #[inline]
+    pub fn nested_abortable<F, O, E>(&mut self, f: F) -> Result<Result<O, E>, Status>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Abort<E>>,
+    {
+        self.as_impl().nested(f)
+    }
 }
 
 impl<'tcell> tx::Read<'tcell> for RwTx<'tcell> {
@@ -263,7 +429,7 @@ impl<'tcell> Write<'tcell> for RwTx<'tcell> {
         assert_eq!(
             mem::size_of_val(&value),
             mem::size_of::<T>(),
-            "swym currently requires undo callbacks to be zero sized"
+            "write log values must be the same size as the value of the `TCell`"
         );
         if mem::size_of::<T>() != 0 {
             self.as_impl().set_impl(tcell, value)
@@ -285,35 +451,12 @@ impl<'tcell> Write<'tcell> for RwTx<'tcell> {
     }
 
     #[inline]
-    fn _privatize<F: FnOnce() + Copy + Send + 'static>(&mut self, privatizer: F) {
-        self.as_impl()
-            .logs_mut()
-            .garbage
-            .dispose(ManuallyDrop::new(After::new(privatizer, |p| p())));
-    }
-}
-
-struct After<T, F: FnOnce(T)> {
-    t: ManuallyDrop<T>,
-    f: ManuallyDrop<F>,
-}
-
-impl<T, F: FnOnce(T)> Drop for After<T, F> {
-    fn drop(&mut self) {
-        unsafe {
-            ManuallyDrop::into_inner(ptr::read(&self.f))(ManuallyDrop::into_inner(ptr::read(
-                &self.t,
-            )))
-        }
+    fn _privatize<F: FnOnce() + Send + 'static>(&mut self, privatizer: F) {
+        self.as_impl().logs_mut().garbage.dispose_fn(privatizer)
     }
-}
 
-impl<T, F: FnOnce(T)> After<T, F> {
     #[inline]
-    fn new(t: T, f: F) -> Self {
-        After {
-            t: ManuallyDrop::new(t),
-            f: ManuallyDrop::new(f),
-        }
+    fn _on_abort<F: FnOnce() + Send + 'static>(&mut self, f: F) {
+        self.on_abort(f)
     }
 }
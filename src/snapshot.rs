@@ -0,0 +1,106 @@
+use crate::{
+    diagnostics,
+    internal::{epoch::QuiesceEpoch, thread::PinRef, versions},
+    tcell::{Ref, TCell},
+    tx::{Borrow, Error, Ordering, Read},
+};
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    mem::{self, ManuallyDrop},
+    sync::atomic::{
+        self,
+        Ordering::{Acquire, Relaxed},
+    },
+};
+use crossbeam_utils::Backoff;
+
+/// A read only transaction that reads the values that were current when it started.
+///
+/// No instances of this type are ever created. References to values of this type are created by
+/// transmuting QuiesceEpoch's.
+///
+/// See [`ThreadKey::read_snapshot`](crate::thread_key::ThreadKey::read_snapshot).
+pub struct SnapshotTx<'tcell>(PhantomData<fn(&'tcell ())>);
+impl<'tcell> !Send for SnapshotTx<'tcell> {}
+impl<'tcell> !Sync for SnapshotTx<'tcell> {}
+
+impl<'tcell> Debug for SnapshotTx<'tcell> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("SnapshotTx")
+            .field("pin_epoch", &self.pin_epoch())
+            .finish()
+    }
+}
+
+impl<'tcell> SnapshotTx<'tcell> {
+    #[inline]
+    pub(crate) fn new<'tx>(pin: &'tx PinRef<'_, 'tcell>) -> &'tx Self {
+        assert!(
+            mem::align_of::<Self>() == 1,
+            "unsafe alignment on SnapshotTx"
+        );
+        let pin_epoch: QuiesceEpoch = pin.pin_epoch();
+        unsafe { mem::transmute::<QuiesceEpoch, &'tx Self>(pin_epoch) }
+    }
+
+    #[inline]
+    fn pin_epoch(&self) -> QuiesceEpoch {
+        unsafe { mem::transmute::<&Self, _>(self) }
+    }
+
+    #[inline(never)]
+    #[cold]
+    unsafe fn borrow_version<T>(&self, tcell: &'tcell TCell<T>) -> Result<ManuallyDrop<T>, Error> {
+        let pin_epoch = self.pin_epoch();
+        let epoch_lock = &tcell.erased.current_epoch;
+        let backoff = Backoff::new();
+        loop {
+            // Synchronizes with the commit that published the newer value, which made the version
+            // visible beforehand.
+            atomic::fence(Acquire);
+            if let Some(value) = versions::find::<T>(&tcell.erased, pin_epoch) {
+                return Ok(value);
+            }
+            if epoch_lock.is_locked(Relaxed) && !backoff.is_completed() {
+                // The version becomes visible just before the lock is released.
+                backoff.snooze()
+            } else if !pin_epoch.read_write_valid_lockable(epoch_lock) {
+                // Either the value was overwritten without keeping a version, or the commit is
+                // taking too long.
+                return Err(diagnostics::access_conflict(&tcell.erased));
+            }
+            let value = tcell.optimistic_read_acquire();
+            if pin_epoch.read_write_valid_lockable(epoch_lock) {
+                return Ok(value);
+            }
+        }
+    }
+}
+
+impl<'tcell> Read<'tcell> for SnapshotTx<'tcell> {
+    #[inline]
+    fn borrow<'tx, T: Borrow>(
+        &'tx self,
+        tcell: &'tcell TCell<T>,
+        _: Ordering,
+    ) -> Result<Ref<'tx, T>, Error> {
+        unsafe {
+            if mem::size_of::<T>() != 0 {
+                let value = tcell.optimistic_read_acquire();
+                if likely!(self
+                    .pin_epoch()
+                    .read_write_valid_lockable(&tcell.erased.current_epoch))
+                {
+                    Ok(Ref::new(value))
+                } else {
+                    self.borrow_version(tcell).map(Ref::new)
+                }
+            } else {
+                // If the type is zero sized, there's no need to any synchronization.
+                Ok(Ref::new(mem::zeroed::<ManuallyDrop<T>>()))
+            }
+        }
+    }
+}
@@ -1,12 +1,45 @@
-//! Statistics collection. Enabled with `--features stats`.
+//! Statistics collection.
+//!
+//! Statistics are always collected with `--features stats`, or for individual statistics whose
+//! `SWYM_<KIND>` environment variable was set to `1` at build time. Otherwise, they can be turned
+//! on and off at runtime with [`enable`] and [`disable`].
+//!
+//! The `*_time` statistics measure wall clock time in nanoseconds, separating the time spent in
+//! transaction closures, commits, parking and garbage collection. Each thread collects its own
+//! statistics, which are returned by
+//! [`ThreadKey::stats`](crate::thread_key::ThreadKey::stats), and merged by [`snapshot`].
+//!
+//! Transactions run with [`ThreadKey::rw_named`](crate::thread_key::ThreadKey::rw_named) are also
+//! collected separately under their name, and returned by [`Stats::named`].
+//!
+//! # Examples
+//!
+//! ```
+//! use swym::{stats, tcell::TCell, thread_key};
+//!
+//! stats::enable(stats::Kind::ALL);
+//!
+//! let x = TCell::new(0);
+//! thread_key::get().rw(|tx| Ok(x.set(tx, 1)?));
+//!
+//! stats::disable(stats::Kind::ALL);
+//! let stats = stats::take();
+//! assert!(stats.write_transaction_eager_conflicts.count() >= 1);
+//! ```
 
 use crate::internal::phoenix_tls::PhoenixTarget;
 use core::{
-    cell::RefCell,
+    cell::Cell,
+    cmp::Reverse,
     fmt::{self, Debug, Formatter},
-    ops::{Deref, DerefMut},
+    ops::{BitOr, Deref, DerefMut},
+    sync::atomic::{AtomicU32, Ordering::Relaxed},
 };
 use parking_lot::Mutex;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 #[derive(Copy, Clone, Default, Debug)]
 struct MinMaxTotal {
@@ -15,11 +48,81 @@ struct MinMaxTotal {
     total: u64,
 }
 
+/// The percentiles that are exported, along with their JSON names and Prometheus quantiles.
+const PERCENTILES: [(&str, &str, f64); 3] = [
+    ("p50", "0.5", 50.0),
+    ("p99", "0.99", 99.0),
+    ("p999", "0.999", 99.9),
+];
+
+/// One bucket for zero, and one per bit length of a nonzero `u64`.
+const BUCKET_COUNT: usize = 65;
+
+/// Counts of sizes, bucketed by the position of their highest set bit.
+///
+/// Bucket `i` holds the sizes in `2^(i - 1)..2^i`, so percentiles are accurate to within a factor
+/// of two, and merging is a fixed number of additions.
+#[derive(Copy, Clone)]
+struct Histogram {
+    buckets: [u64; BUCKET_COUNT],
+}
+
+impl Default for Histogram {
+    #[inline]
+    fn default() -> Self {
+        Histogram {
+            buckets: [0; BUCKET_COUNT],
+        }
+    }
+}
+
+impl Histogram {
+    #[inline]
+    fn bucket(size: u64) -> usize {
+        (64 - size.leading_zeros()) as usize
+    }
+
+    /// The largest size that falls into `bucket`.
+    #[inline]
+    fn upper_bound(bucket: usize) -> u64 {
+        match bucket {
+            0 => 0,
+            64 => u64::max_value(),
+            bucket => (1 << bucket) - 1,
+        }
+    }
+
+    #[inline]
+    fn record(&mut self, size: u64) {
+        self.buckets[Self::bucket(size)] += 1
+    }
+
+    fn merge(&mut self, rhs: &Self) {
+        for (lhs, rhs) in self.buckets.iter_mut().zip(rhs.buckets.iter()) {
+            *lhs += rhs
+        }
+    }
+
+    /// Returns the upper bound of the bucket containing the `rank`th smallest size. Ranks start at
+    /// 1.
+    fn rank(&self, rank: u64) -> u64 {
+        let mut seen = 0;
+        for (bucket, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Self::upper_bound(bucket);
+            }
+        }
+        u64::max_value()
+    }
+}
+
 #[doc(hidden)]
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Size {
     min_max_total: Option<MinMaxTotal>,
     count:         u64,
+    histogram:     Histogram,
 }
 
 impl Debug for Size {
@@ -36,13 +139,60 @@ impl Debug for Size {
                     .min_max_total
                     .map(|x| x.total as f64 / self.count as f64),
             )
+            .field("p50", &self.percentile(50.0))
+            .field("p99", &self.percentile(99.0))
+            .field("p999", &self.percentile(99.9))
             .finish()
     }
 }
 
 impl Size {
+    /// Returns the number of recorded sizes.
+    #[inline]
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the smallest recorded size.
+    #[inline]
+    pub fn min(&self) -> Option<u64> {
+        self.min_max_total.map(|x| x.min)
+    }
+
+    /// Returns the largest recorded size.
+    #[inline]
+    pub fn max(&self) -> Option<u64> {
+        self.min_max_total.map(|x| x.max)
+    }
+
+    /// Returns the sum of all recorded sizes.
+    #[inline]
+    pub fn total(&self) -> u64 {
+        self.min_max_total.map_or(0, |x| x.total)
+    }
+
+    /// Returns an estimate of the size that `percentile` percent of the recorded sizes are less
+    /// than or equal to, or `None` if no sizes were recorded.
+    ///
+    /// The estimate is never less than the exact answer, and is less than twice it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `percentile` is not in the range `0.0..=100.0`.
+    pub fn percentile(&self, percentile: f64) -> Option<u64> {
+        assert!(
+            (0.0..=100.0).contains(&percentile),
+            "percentile must be in the range `0.0..=100.0`"
+        );
+        let min_max_total = self.min_max_total?;
+        let rank = (percentile / 100.0 * self.count as f64).ceil() as u64;
+        let estimate = self.histogram.rank(rank.max(1));
+        Some(estimate.max(min_max_total.min).min(min_max_total.max))
+    }
+
     pub(crate) fn record(&mut self, size: u64) {
         self.count += 1;
+        self.histogram.record(size);
         if let Some(ref mut min_max_total) = &mut self.min_max_total {
             min_max_total.min = min_max_total.min.min(size);
             min_max_total.max = min_max_total.max.max(size);
@@ -58,6 +208,7 @@ impl Size {
 
     pub(crate) fn merge(&mut self, rhs: &Self) {
         self.count += rhs.count;
+        self.histogram.merge(&rhs.histogram);
         self.min_max_total = match (self.min_max_total, rhs.min_max_total) {
             (Some(a), Some(b)) => Some(MinMaxTotal {
                 min:   a.min.min(b.min),
@@ -69,13 +220,23 @@ impl Size {
     }
 }
 
+/// A [`Size`] of durations measured in nanoseconds.
+#[doc(hidden)]
+pub type Time = Size;
+
 #[doc(hidden)]
-#[derive(Default, Debug)]
+#[derive(Clone, Default, Debug)]
 pub struct Event {
     count: usize,
 }
 
 impl Event {
+    /// Returns the number of times the event happened.
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
     fn happened(&mut self) {
         self.count += 1
     }
@@ -85,19 +246,259 @@ impl Event {
     }
 }
 
+/// The statistics of named transactions, along with their name.
+///
+/// Few names are expected, so they are searched linearly. Threads cache the index of the name of
+/// their running transaction, see `ThreadStats::record`.
+#[derive(Clone, Default)]
+struct Named(Vec<(&'static str, Stats)>);
+
+impl Debug for Named {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_set()
+            .entries(self.0.iter().map(|&(name, _)| name))
+            .finish()
+    }
+}
+
+impl Named {
+    /// Returns the index of the statistics of `name`, adding them if they are missing.
+    fn index(&mut self, name: &'static str) -> usize {
+        match self.0.iter().position(|&(other, _)| other == name) {
+            Some(index) => index,
+            None => {
+                self.0.push((name, Stats::default()));
+                self.0.len() - 1
+            }
+        }
+    }
+
+    fn merge(&mut self, rhs: &Self) {
+        for &(name, ref stats) in &rhs.0 {
+            let index = self.index(name);
+            self.0[index].1.merge(stats)
+        }
+    }
+}
+
+/// Writes `value` as a JSON number, or `null` if it is not finite.
+fn write_json_f64(out: &mut dyn fmt::Write, value: f64) -> fmt::Result {
+    if value.is_finite() {
+        write!(out, "{}", value)
+    } else {
+        out.write_str("null")
+    }
+}
+
+/// Writes `value` as a Prometheus sample value.
+fn write_prometheus_f64(out: &mut dyn fmt::Write, value: f64) -> fmt::Result {
+    if value.is_nan() {
+        out.write_str("NaN")
+    } else if value.is_infinite() {
+        out.write_str(if value > 0.0 { "+Inf" } else { "-Inf" })
+    } else {
+        write!(out, "{}", value)
+    }
+}
+
+/// Writes `value` as a JSON string.
+fn write_json_str(out: &mut dyn fmt::Write, value: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => write!(out, "\\{}", c)?,
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+/// Writes `value` as a JSON number, or `null` if it is `None`.
+fn write_json_u64(out: &mut dyn fmt::Write, value: Option<u64>) -> fmt::Result {
+    match value {
+        Some(value) => write!(out, "{}", value),
+        None => out.write_str("null"),
+    }
+}
+
+/// Writes a Prometheus label, escaping its value.
+fn write_prometheus_label(out: &mut dyn fmt::Write, name: &str, value: &str) -> fmt::Result {
+    write!(out, "{}=\"", name)?;
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => write!(out, "\\{}", c)?,
+            '\n' => out.write_str("\\n")?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+/// Writes the name and comma separated `labels` of a Prometheus sample, followed by the space
+/// before its value.
+fn write_prometheus_sample(out: &mut dyn fmt::Write, metric: &str, labels: &str) -> fmt::Result {
+    out.write_str(metric)?;
+    if !labels.is_empty() {
+        write!(out, "{{{}}}", labels)?;
+    }
+    out.write_char(' ')
+}
+
+/// Returns the unit suffix of the Prometheus metrics of the statistic `name`. Times are recorded
+/// in nanoseconds, but Prometheus expects them in seconds.
+fn prometheus_unit(name: &str) -> &'static str {
+    if name.ends_with("_time") {
+        "_seconds"
+    } else {
+        ""
+    }
+}
+
+/// A statistic that can be exported by [`Stats::write_json`] and [`Stats::write_prometheus`].
+trait Export {
+    fn write_json(&self, out: &mut dyn fmt::Write) -> fmt::Result;
+
+    /// Returns the suffix and type of each Prometheus metric family of the statistic.
+    fn families(&self) -> &'static [(&'static str, &'static str)];
+
+    /// Writes the samples of the `family`th metric family of the statistic `name`, with `labels`.
+    fn write_prometheus(
+        &self,
+        name: &str,
+        family: usize,
+        labels: &str,
+        out: &mut dyn fmt::Write,
+    ) -> fmt::Result;
+}
+
+impl Export for Size {
+    fn write_json(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        write!(out, "{{\"count\":{},\"min\":", self.count)?;
+        write_json_u64(out, self.min())?;
+        out.write_str(",\"max\":")?;
+        write_json_u64(out, self.max())?;
+        write!(out, ",\"total\":{}", self.total())?;
+        for &(label, _, percentile) in &PERCENTILES {
+            write!(out, ",\"{}\":", label)?;
+            write_json_u64(out, self.percentile(percentile))?;
+        }
+        out.write_char('}')
+    }
+
+    fn families(&self) -> &'static [(&'static str, &'static str)] {
+        &[("", "summary"), ("_min", "gauge"), ("_max", "gauge")]
+    }
+
+    fn write_prometheus(
+        &self,
+        name: &str,
+        family: usize,
+        labels: &str,
+        out: &mut dyn fmt::Write,
+    ) -> fmt::Result {
+        let unit = prometheus_unit(name);
+        let write_value = |out: &mut dyn fmt::Write, value: u64| {
+            if unit.is_empty() {
+                write!(out, "{}", value)?;
+            } else {
+                write_prometheus_f64(out, value as f64 / 1e9)?;
+            }
+            writeln!(out)
+        };
+        if family == 0 {
+            let metric = format!("swym_{}{}", name, unit);
+            for &(_, quantile, percentile) in &PERCENTILES {
+                if let Some(value) = self.percentile(percentile) {
+                    let mut quantile_labels = labels.to_owned();
+                    if !labels.is_empty() {
+                        quantile_labels.push(',')
+                    }
+                    write_prometheus_label(&mut quantile_labels, "quantile", quantile)?;
+                    write_prometheus_sample(out, &metric, &quantile_labels)?;
+                    write_value(out, value)?;
+                }
+            }
+            write_prometheus_sample(out, &format!("{}_sum", metric), labels)?;
+            write_value(out, self.total())?;
+            write_prometheus_sample(out, &format!("{}_count", metric), labels)?;
+            writeln!(out, "{}", self.count)
+        } else {
+            let (suffix, value) = if family == 1 {
+                ("_min", self.min())
+            } else {
+                ("_max", self.max())
+            };
+            match value {
+                Some(value) => {
+                    let metric = format!("swym_{}{}{}", name, suffix, unit);
+                    write_prometheus_sample(out, &metric, labels)?;
+                    write_value(out, value)
+                }
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+impl Export for Event {
+    fn write_json(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        write!(out, "{{\"count\":{}}}", self.count)
+    }
+
+    fn families(&self) -> &'static [(&'static str, &'static str)] {
+        &[("_total", "counter")]
+    }
+
+    fn write_prometheus(
+        &self,
+        name: &str,
+        _family: usize,
+        labels: &str,
+        out: &mut dyn fmt::Write,
+    ) -> fmt::Result {
+        write_prometheus_sample(out, &format!("swym_{}_total", name), labels)?;
+        writeln!(out, "{}", self.count)
+    }
+}
+
+/// Records the time from its creation until it is dropped, if its statistic was active when it was
+/// created.
+#[must_use]
+pub(crate) struct Timer {
+    start:  Option<Instant>,
+    record: fn(Duration),
+}
+
+impl Drop for Timer {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            (self.record)(start.elapsed())
+        }
+    }
+}
+
 macro_rules! env_var_set {
     ($env_var:ident) => {
         option_env!(concat!("SWYM_", stringify!($env_var))) == Some("1")
     };
 }
 
+macro_rules! stat_active {
+    ($env_var:ident) => {
+        cfg!(feature = "stats") || env_var_set!($env_var) || is_enabled(Kind::$env_var)
+    };
+}
+
 macro_rules! stats_func {
     ($(#[$attr:meta])* $name:ident: Event @ $env_var:ident) => {
         #[inline]
         $(#[$attr])*
         pub(crate) fn $name() {
-            if cfg!(feature = "stats") || env_var_set!($env_var) {
-                THREAD_STAT.get().get().$name.happened()
+            if stat_active!($env_var) {
+                THREAD_STAT.get().record(|stats| stats.$name.happened())
             }
         }
     };
@@ -105,9 +506,27 @@ macro_rules! stats_func {
         #[inline]
         $(#[$attr])*
         pub(crate) fn $name(size: usize) {
-            if cfg!(feature = "stats") || env_var_set!($env_var) {
+            if stat_active!($env_var) {
                 let size = size as u64;
-                THREAD_STAT.with(move |x| x.get().$name.record(size))
+                THREAD_STAT.with(move |x| x.record(|stats| stats.$name.record(size)))
+            }
+        }
+    };
+    ($(#[$attr:meta])* $name:ident: Time @ $env_var:ident) => {
+        #[inline]
+        $(#[$attr])*
+        pub(crate) fn $name() -> Timer {
+            fn record(elapsed: Duration) {
+                let nanos = elapsed.as_nanos() as u64;
+                THREAD_STAT.with(move |x| x.record(|stats| stats.$name.record(nanos)))
+            }
+            Timer {
+                start: if stat_active!($env_var) {
+                    Some(Instant::now())
+                } else {
+                    None
+                },
+                record,
             }
         }
     };
@@ -116,21 +535,47 @@ macro_rules! stats_func {
 macro_rules! stats {
     ($($(#[$attr:meta])* $names:ident: $kinds:tt @ $env_var:ident),* $(,)*) => {
         /// A collection of swym statistics.
-        #[derive(Default, Debug)]
+        #[derive(Clone, Default, Debug)]
         pub struct Stats {
             $($(#[$attr])*pub $names: $kinds,)*
 
+            named:     Named,
             __private: (),
         }
 
         impl Stats {
             fn merge(&mut self, rhs: &Self) {
-                $(self.$names.merge(&rhs.$names));*
+                $(self.$names.merge(&rhs.$names);)*
+                self.named.merge(&rhs.named)
+            }
+
+            /// Calls `f` with the name of every statistic, and a function returning it.
+            fn for_each(
+                mut f: impl FnMut(&'static str, fn(&Stats) -> &dyn Export) -> fmt::Result,
+            ) -> fmt::Result {
+                $(f(stringify!($names), |stats| &stats.$names)?;)*
+                Ok(())
             }
         }
 
+        #[allow(non_camel_case_types)]
+        enum Index {
+            $($env_var),*
+        }
+
+        /// A set of statistics that can be enabled at runtime.
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+        pub struct Kind(u32);
+
+        impl Kind {
+            $($(#[$attr])* pub const $env_var: Kind = Kind(1 << Index::$env_var as u32);)*
+
+            /// Every statistic.
+            pub const ALL: Kind = Kind(0 $(| 1 << Index::$env_var as u32)*);
+        }
+
         fn any_stats_active() -> bool {
-            cfg!(feature = "stats") $(|| env_var_set!($env_var))*
+            cfg!(feature = "stats") $(|| env_var_set!($env_var))* || ENABLED.load(Relaxed) != 0
         }
 
         $(stats_func!{$(#[$attr])* $names: $kinds @ $env_var})*
@@ -139,68 +584,302 @@ macro_rules! stats {
 
 include! {"./stats_list.rs"}
 
+impl BitOr for Kind {
+    type Output = Kind;
+
+    #[inline]
+    fn bitor(self, rhs: Kind) -> Kind {
+        Kind(self.0 | rhs.0)
+    }
+}
+
+/// The `Kind`s enabled at runtime.
+static ENABLED: AtomicU32 = AtomicU32::new(0);
+
+#[inline]
+fn is_enabled(kind: Kind) -> bool {
+    ENABLED.load(Relaxed) & kind.0 != 0
+}
+
+/// Starts collecting the statistics in `kind`.
+#[inline]
+pub fn enable(kind: Kind) {
+    drop(ENABLED.fetch_or(kind.0, Relaxed))
+}
+
+/// Stops collecting the statistics in `kind`, unless they are enabled at build time. Statistics
+/// that were already collected are kept.
+#[inline]
+pub fn disable(kind: Kind) {
+    drop(ENABLED.fetch_and(!kind.0, Relaxed))
+}
+
 impl Stats {
+    /// Returns the number of transactions that completed successfully.
+    pub fn transactions(&self) -> u64 {
+        // Retries are recorded once after the transaction has completed. Eager conflicts and commit
+        // conflicts are recorded in equal amounts, so just picking one of them is correct here.
+        self.read_transaction_conflicts.count + self.write_transaction_eager_conflicts.count
+    }
+
+    /// Returns the number of conflicts of all successful transactions.
+    pub fn conflicts(&self) -> u64 {
+        self.read_transaction_conflicts.total()
+            + self.write_transaction_eager_conflicts.total()
+            + self.write_transaction_commit_conflicts.total()
+    }
+
+    /// Returns the average number of conflicts per successful transaction.
+    pub fn conflict_avg(&self) -> f64 {
+        self.conflicts() as f64 / self.transactions() as f64
+    }
+
+    /// Returns the average number of hardware conflicts per successful transaction.
+    pub fn htm_conflict_avg(&self) -> f64 {
+        self.htm_conflicts.total() as f64 / self.transactions() as f64
+    }
+
+    /// Returns the fraction of bloom filter checks that were collisions.
+    pub fn bloom_fail_rate(&self) -> f64 {
+        self.bloom_collision.count as f64 / self.bloom_check.count as f64
+    }
+
+    /// Returns the fraction of bloom filter checks that required a full lookup of the write log.
+    pub fn bloom_slow_rate(&self) -> f64 {
+        (self.bloom_success_slow.count + self.bloom_collision.count) as f64
+            / self.bloom_check.count as f64
+    }
+
+    /// Returns the statistics of the transactions run with `name` by
+    /// [`ThreadKey::rw_named`](crate::thread_key::ThreadKey::rw_named).
+    pub fn named(&self, name: &str) -> Option<&Stats> {
+        self.named
+            .0
+            .iter()
+            .find(|&&(other, _)| other == name)
+            .map(|(_, stats)| stats)
+    }
+
+    /// Returns the statistics of every name given to a transaction, ordered by name.
+    pub fn named_stats<'a>(&'a self) -> impl Iterator<Item = (&'static str, &'a Stats)> + 'a {
+        let mut named: Vec<_> = self
+            .named
+            .0
+            .iter()
+            .map(|&(name, ref stats)| (name, stats))
+            .collect();
+        named.sort_by_key(|&(name, _)| name);
+        named.into_iter()
+    }
+
+    /// The statistics derived from the others, that are not collected directly.
+    fn derived(&self) -> [(&'static str, f64); 4] {
+        [
+            ("conflict_avg", self.conflict_avg()),
+            ("htm_conflict_avg", self.htm_conflict_avg()),
+            ("bloom_fail_rate", self.bloom_fail_rate()),
+            ("bloom_slow_rate", self.bloom_slow_rate()),
+        ]
+    }
+
+    /// Writes the statistics as a JSON object.
+    ///
+    /// Every statistic is a member named after its field. Sizes and times are objects holding the
+    /// `count`, `min`, `max`, `total`, `p50`, `p99` and `p999` of the recorded values, events hold
+    /// their `count`, and the derived statistics are numbers. Missing and undefined values are
+    /// `null`. The statistics of named transactions are objects of the same form, in the `named`
+    /// member.
+    pub fn write_json<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        self.write_json_dyn(out)
+    }
+
+    fn write_json_dyn(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        out.write_char('{')?;
+        Stats::for_each(|name, stat| {
+            write!(out, "\"{}\":", name)?;
+            stat(self).write_json(out)?;
+            out.write_char(',')
+        })?;
+        write!(out, "\"transactions\":{}", self.transactions())?;
+        for &(name, value) in &self.derived() {
+            write!(out, ",\"{}\":", name)?;
+            write_json_f64(out, value)?;
+        }
+        if !self.named.0.is_empty() {
+            out.write_str(",\"named\":{")?;
+            for (index, (name, stats)) in self.named_stats().enumerate() {
+                if index != 0 {
+                    out.write_char(',')?;
+                }
+                write_json_str(out, name)?;
+                out.write_char(':')?;
+                stats.write_json_dyn(out)?;
+            }
+            out.write_char('}')?;
+        }
+        out.write_char('}')
+    }
+
+    /// Returns the statistics as a JSON object. See [`write_json`](Stats::write_json).
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        self.write_json(&mut json)
+            .expect("writing to a `String` failed");
+        json
+    }
+
+    /// Writes the statistics in the Prometheus text exposition format.
+    ///
+    /// Every metric is named after its field, prefixed with `swym_`. Sizes and times are summaries
+    /// with `p50`, `p99` and `p999` quantiles, along with `_min` and `_max` gauges. Times are in
+    /// seconds, and suffixed with `_seconds`. Events are counters suffixed with `_total`, and the
+    /// derived statistics are gauges. The statistics of named transactions are additional samples
+    /// of the same metrics, with a `name` label.
+    pub fn write_prometheus<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        self.write_prometheus_dyn(out)
+    }
+
+    fn write_prometheus_dyn(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        // Each metric family is written once, with the samples of every name following the totals.
+        let mut labelled = vec![(String::new(), self)];
+        for (name, stats) in self.named_stats() {
+            let mut labels = String::new();
+            write_prometheus_label(&mut labels, "name", name)?;
+            labelled.push((labels, stats));
+        }
+        Stats::for_each(|name, stat| {
+            let unit = prometheus_unit(name);
+            for (family, &(suffix, kind)) in stat(self).families().iter().enumerate() {
+                writeln!(out, "# TYPE swym_{}{}{} {}", name, suffix, unit, kind)?;
+                for (labels, stats) in &labelled {
+                    stat(stats).write_prometheus(name, family, labels, out)?;
+                }
+            }
+            Ok(())
+        })?;
+        writeln!(out, "# TYPE swym_transactions_total counter")?;
+        for (labels, stats) in &labelled {
+            write_prometheus_sample(out, "swym_transactions_total", labels)?;
+            writeln!(out, "{}", stats.transactions())?;
+        }
+        for (index, &(name, _)) in self.derived().iter().enumerate() {
+            let metric = format!("swym_{}", name);
+            writeln!(out, "# TYPE {} gauge", metric)?;
+            for (labels, stats) in &labelled {
+                write_prometheus_sample(out, &metric, labels)?;
+                write_prometheus_f64(out, stats.derived()[index].1)?;
+                writeln!(out)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the statistics in the Prometheus text exposition format. See
+    /// [`write_prometheus`](Stats::write_prometheus).
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        self.write_prometheus(&mut text)
+            .expect("writing to a `String` failed");
+        text
+    }
+
     /// Prints a summary of the stats object.
     pub fn print_summary(&self) {
         println!("{:#?}", self);
 
-        // Retries are recorded once after the transaction has completed. Eager conflicts and commit
-        // conflicts are recorded in equal amounts, so just picking one of them is correct here.
-        let successful_transactions =
-            self.read_transaction_conflicts.count + self.write_transaction_eager_conflicts.count;
-
-        let conflicts = self
-            .read_transaction_conflicts
-            .min_max_total
-            .unwrap_or_default()
-            .total
-            + self
-                .write_transaction_eager_conflicts
-                .min_max_total
-                .unwrap_or_default()
-                .total
-            + self
-                .write_transaction_commit_conflicts
-                .min_max_total
-                .unwrap_or_default()
-                .total;
         println!(
             "{:>12}: {:>12} {:>9}: {:.4} {:>13}: {:.4}",
             "transactions",
-            successful_transactions,
+            self.transactions(),
             "conflict avg",
-            conflicts as f64 / successful_transactions as f64,
+            self.conflict_avg(),
             "htm conflict avg",
-            self.htm_conflicts.min_max_total.unwrap_or_default().total as f64
-                / successful_transactions as f64
+            self.htm_conflict_avg()
         );
         println!(
             "{:>12}: {:>12} {:>9}: {:.4} {:>13}: {:.4}",
             "bloom checks",
             self.bloom_check.count,
             "fail rate",
-            self.bloom_collision.count as f64 / self.bloom_check.count as f64,
+            self.bloom_fail_rate(),
             "slow rate",
-            (self.bloom_success_slow.count + self.bloom_collision.count) as f64
-                / self.bloom_check.count as f64
+            self.bloom_slow_rate()
         );
+
+        let percentile = |size: &Size, percentile| {
+            size.percentile(percentile)
+                .map_or_else(|| "-".to_owned(), |size| size.to_string())
+        };
+        println!("{:>24}: {:>8} {:>8} {:>8}", "", "p50", "p99", "p999");
+        for &(name, size) in &[
+            ("read conflicts", &self.read_transaction_conflicts),
+            ("eager conflicts", &self.write_transaction_eager_conflicts),
+            ("commit conflicts", &self.write_transaction_commit_conflicts),
+            ("htm conflicts", &self.htm_conflicts),
+            ("read size", &self.read_size),
+            ("write word size", &self.write_word_size),
+            ("read closure ns", &self.read_closure_time),
+            ("rw closure ns", &self.rw_closure_time),
+            ("htm commit ns", &self.htm_commit_time),
+            ("software commit ns", &self.software_commit_time),
+            ("parked ns", &self.parked_time),
+            ("gc quiesce ns", &self.gc_quiesce_time),
+        ] {
+            println!(
+                "{:>24}: {:>8} {:>8} {:>8}",
+                name,
+                percentile(size, 50.0),
+                percentile(size, 99.0),
+                percentile(size, 99.9)
+            );
+        }
+
+        let mut named: Vec<_> = self.named_stats().collect();
+        if !named.is_empty() {
+            named.sort_by_key(|&(_, stats)| Reverse((stats.conflicts(), stats.parked_size.count)));
+            println!(
+                "{:>24}: {:>12} {:>9} {:>12} {:>12} {:>8}",
+                "worst offenders", "transactions", "conflicts", "p99 read", "p99 write", "parked"
+            );
+            for &(name, stats) in named.iter().take(10) {
+                println!(
+                    "{:>24}: {:>12} {:>9} {:>12} {:>12} {:>8}",
+                    name,
+                    stats.transactions(),
+                    stats.conflicts(),
+                    percentile(&stats.read_size, 99.0),
+                    percentile(&stats.write_word_size, 99.0),
+                    stats.parked_size.count
+                );
+            }
+        }
     }
 }
 
 /// Thread local statistics.
 ///
 /// To reduce overhead of stats tracking, each thread has it's own `Stats` object which is flushed
-/// to the global `Stats` object on thread exit or when manually requested.
+/// to the global `Stats` object on thread exit or when manually requested. The lock around it is
+/// only contended while another thread takes a [`snapshot`].
 #[derive(Debug)]
-pub struct ThreadStats(RefCell<Stats>);
+pub struct ThreadStats {
+    stats: Arc<Mutex<Stats>>,
+
+    /// The name of the running transaction, if it was given one, and the index of its statistics
+    /// in `Stats::named` when they were last recorded.
+    name: Cell<Option<(&'static str, usize)>>,
+}
 
 impl Default for ThreadStats {
     #[inline]
     fn default() -> Self {
-        fn force(_: &Mutex<Stats>) {}
-        force(&GLOBAL); // initialize global now, else we may get panics on drop because
-                        // lazy_static uses thread_locals to initialize it.
-        ThreadStats(Default::default())
+        fn force<T>(_: &Mutex<T>) {}
+        force(&GLOBAL); // initialize globals now, else we may get panics on drop because
+        force(&LIVE); // lazy_static uses thread_locals to initialize it.
+        ThreadStats {
+            stats: Default::default(),
+            name:  Cell::new(None),
+        }
     }
 }
 
@@ -211,18 +890,62 @@ impl Drop for ThreadStats {
     }
 }
 
-impl PhoenixTarget for ThreadStats {}
+impl PhoenixTarget for ThreadStats {
+    fn subscribe(&mut self) {
+        LIVE.lock().push(self.stats.clone())
+    }
+
+    fn unsubscribe(&mut self) {
+        // Flushing while `LIVE` is held means `collect` sees the stats either here or in `GLOBAL`.
+        let mut live = LIVE.lock();
+        if let Some(index) = live
+            .iter()
+            .position(|stats| Arc::ptr_eq(stats, &self.stats))
+        {
+            drop(live.swap_remove(index))
+        }
+        self.merge_into_global()
+    }
+}
 
 impl ThreadStats {
     /// Returns the actual statistics object.
     pub fn get<'a>(&'a self) -> impl DerefMut<Target = Stats> + 'a {
-        self.0.borrow_mut()
+        self.stats.lock()
+    }
+
+    /// Records a statistic with `f`, and again under the name of the running transaction, if it
+    /// was given one.
+    #[inline]
+    fn record(&self, f: impl Fn(&mut Stats)) {
+        let mut stats = self.get();
+        f(&mut stats);
+        if let Some((name, index)) = self.name.get() {
+            // The statistics may have been taken or reset since the index was cached.
+            let index = match stats.named.0.get(index) {
+                Some(&(other, _)) if other == name => index,
+                _ => {
+                    let index = stats.named.index(name);
+                    self.name.set(Some((name, index)));
+                    index
+                }
+            };
+            f(&mut stats.named.0[index].1)
+        }
     }
 
     /// Flushes the thread stats to the global thread stats object.
     ///
     /// After flushing, `self` is reset.
     pub fn flush(&mut self) {
+        let live = LIVE.lock();
+        self.merge_into_global();
+        drop(live)
+    }
+
+    /// Moves the thread stats into `GLOBAL`. `LIVE` must be held, so that `collect` does not see
+    /// the stats in both places.
+    fn merge_into_global(&self) {
         let mut borrow = self.get();
         GLOBAL.lock().merge(&*borrow);
         *borrow = Default::default()
@@ -233,8 +956,84 @@ phoenix_tls! {
     static THREAD_STAT: ThreadStats
 }
 
+/// Restores the previous transaction name of the thread when dropped.
+#[must_use]
+pub(crate) struct NameGuard {
+    prev: Option<(&'static str, usize)>,
+}
+
+impl Drop for NameGuard {
+    #[inline]
+    fn drop(&mut self) {
+        let prev = self.prev;
+        THREAD_STAT.with(move |stats| stats.name.set(prev))
+    }
+}
+
+/// Records the statistics of the current thread under `name`, in addition to the usual statistics,
+/// until the returned guard is dropped.
+#[inline]
+pub(crate) fn named(name: &'static str) -> NameGuard {
+    // The index is looked up by the first statistic that is recorded.
+    NameGuard {
+        prev: THREAD_STAT.with(move |stats| stats.name.replace(Some((name, usize::MAX)))),
+    }
+}
+
 lazy_static::lazy_static! {
     static ref GLOBAL: Mutex<Stats> = Mutex::default();
+
+    /// The `ThreadStats` of every thread that has not exited.
+    static ref LIVE: Mutex<Vec<Arc<Mutex<Stats>>>> = Mutex::default();
+}
+
+/// Returns the statistics collected so far by every thread, including threads that are still
+/// running.
+pub fn snapshot() -> Stats {
+    collect(false)
+}
+
+/// Returns the statistics collected so far by every thread, including threads that are still
+/// running, and resets them.
+pub fn take() -> Stats {
+    collect(true)
+}
+
+/// Discards the statistics collected so far by every thread.
+pub fn reset() {
+    let live = LIVE.lock();
+    for stats in live.iter() {
+        *stats.lock() = Default::default()
+    }
+    *GLOBAL.lock() = Default::default();
+    drop(live)
+}
+
+fn collect(reset: bool) -> Stats {
+    // Locks are taken in the order `LIVE`, thread stats, `GLOBAL`. Holding `LIVE` throughout keeps
+    // threads from flushing, so no stats are counted twice or missed.
+    let mut result = Stats::default();
+    let live = LIVE.lock();
+    for stats in live.iter() {
+        let mut stats = stats.lock();
+        result.merge(&stats);
+        if reset {
+            *stats = Default::default()
+        }
+    }
+    let mut global = GLOBAL.lock();
+    result.merge(&global);
+    if reset {
+        *global = Default::default()
+    }
+    drop(global);
+    drop(live);
+    result
+}
+
+/// Returns a copy of the statistics collected by the current thread since it last flushed them.
+pub(crate) fn current_thread() -> Stats {
+    THREAD_STAT.with(|x| x.get().clone())
 }
 
 /// Returns the global stats object, or None if the feature is disabled.
@@ -255,12 +1054,11 @@ pub fn thread_stats() -> Option<impl Deref<Target = ThreadStats>> {
     }
 }
 
-/// Prints a summary of the global stats object.
-///
-/// It may be necessary to run `stats::thread_stats().borrow_mut().flush()` first.
+/// Prints a summary of the statistics collected so far by every thread.
 pub fn print_stats() {
-    match self::stats() {
-        Some(stats) => stats.print_summary(),
-        None => println!("`swym/stats` feature is not enabled"),
+    if any_stats_active() {
+        snapshot().print_summary()
+    } else {
+        println!("`swym/stats` feature is not enabled, and no statistics were enabled at runtime")
     }
 }
@@ -28,7 +28,7 @@ stats! {
     bloom_check:                       Event @ BLOOM_CHECK,
 
     /// A bloom filter collision.
-    bloom_collision:                   Event @ BLOOM_CHECK,
+    bloom_collision:                   Event @ BLOOM_COLLISION,
 
     /// A bloom filter hit that required a full lookup to verify.
     bloom_success_slow:                Event @ BLOOM_SUCCESS_SLOW,
@@ -73,4 +73,24 @@ stats! {
     /// Number of `EpochLock`s a parked (via [`AWAIT_RETRY`](crate::tx::Status::AWAIT_RETRY)) thread
     /// can be woken up from.
     parked_size:                        Size @ PARKED_SIZE,
+
+    /// Time spent running the closure of a read only, or snapshot transaction, per attempt.
+    read_closure_time:                  Time @ READ_CLOSURE_TIME,
+
+    /// Time spent running the closure of a read write transaction, per attempt.
+    rw_closure_time:                    Time @ RW_CLOSURE_TIME,
+
+    /// Time spent in a commit that used a hardware transaction, per attempt.
+    htm_commit_time:                    Time @ HTM_COMMIT_TIME,
+
+    /// Time spent in a commit that used the software fallback, per attempt.
+    software_commit_time:               Time @ SOFTWARE_COMMIT_TIME,
+
+    /// Time spent parked waiting for a change to the read set of a transaction that returned
+    /// [`AWAIT_RETRY`](crate::tx::Status::AWAIT_RETRY).
+    parked_time:                        Time @ PARKED_TIME,
+
+    /// Time spent by the garbage collector waiting for another thread to leave an epoch. This is
+    /// the wait counted by `should_park_gc`.
+    gc_quiesce_time:                    Time @ GC_QUIESCE_TIME,
 }
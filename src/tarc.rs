@@ -0,0 +1,482 @@
+//! Transactional reference counted pointers.
+//!
+//! A [`TArc`] is a transactional memory location holding a strong reference to a heap allocated
+//! value, which can be shared with any number of other `TArc`s. A [`TWeak`] holds a weak
+//! reference to the same allocation, which does not keep the value alive.
+//!
+//! The reference counts are themselves stored in `TCell`s, so cloning and releasing references are
+//! ordinary transactional writes. Once the last strong reference is released, the value is queued
+//! up to be dropped, and once the last weak reference is released the allocation is queued up to
+//! be freed. Like any other garbage, this only happens if the transaction commits, and not before
+//! every thread that could still be reading the value has quiesced.
+//!
+//! Shared values are never mutated, only replaced. As with [`TBox`](crate::tbox::TBox), `TCell`s
+//! stored inside of a value, including other `TArc`s, cannot be accessed through the reference
+//! returned by [`TArc::get`].
+//!
+//! Dropping a non empty `TArc` or `TWeak` releases its reference in a transaction of its own. If
+//! the thread is already running a transaction, or collecting garbage, the reference is instead
+//! released once it is done, whether the transaction commits, aborts, or panics.
+//!
+//! # Examples
+//!
+//! ```
+//! use swym::{
+//!     tarc::{TArc, TWeak},
+//!     thread_key,
+//! };
+//!
+//! let a = TArc::new(String::from("shared"));
+//! let b = TArc::empty();
+//! let weak = TWeak::empty();
+//!
+//! let thread_key = thread_key::get();
+//! thread_key.rw(|tx| {
+//!     b.clone_from(tx, &a)?;
+//!     weak.downgrade_from(tx, &b)?;
+//!     Ok(a.clear(tx)?)
+//! });
+//! assert_eq!(thread_key.read(|tx| Ok(b.strong_count(tx)?)), 1);
+//!
+//! thread_key.rw(|tx| Ok(b.clear(tx)?));
+//! assert!(thread_key.read(|tx| Ok(weak.get(tx, Default::default())?.is_none())));
+//! ```
+
+use crate::{
+    rw::RwTx,
+    tcell::TCell,
+    thread_key,
+    tx::{Error, Ordering, Read, Rw},
+};
+use core::{
+    fmt::{self, Debug, Formatter},
+    ptr::NonNull,
+};
+
+/// A pointer to memory shared between threads.
+#[repr(transparent)]
+struct Ptr<T>(NonNull<T>);
+
+unsafe impl<T: Send + Sync> Send for Ptr<T> {}
+unsafe impl<T: Send + Sync> Sync for Ptr<T> {}
+
+impl<T> Clone for Ptr<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Ptr<T> {}
+
+impl<T> Ptr<T> {
+    #[inline]
+    fn from_box(value: Box<T>) -> Self {
+        Ptr(unsafe { NonNull::new_unchecked(Box::into_raw(value)) })
+    }
+}
+
+/// The allocation shared by `TArc`s and `TWeak`s.
+///
+/// The value is allocated separately, so that the thread dropping it never touches memory which
+/// may have already been freed by the thread that released the last weak reference.
+struct Inner<T> {
+    strong: TCell<usize>,
+    /// Includes one weak reference shared by all of the strong references.
+    weak:   TCell<usize>,
+    value:  Ptr<T>,
+}
+
+impl<T: Send + Sync + 'static> Inner<T> {
+    fn alloc(value: T) -> Ptr<Self> {
+        Ptr::from_box(Box::new(Inner {
+            strong: TCell::new(1),
+            weak:   TCell::new(1),
+            value:  Ptr::from_box(Box::new(value)),
+        }))
+    }
+
+    /// Gets a reference to the `Inner` a reference is held to.
+    ///
+    /// # Safety
+    ///
+    /// The reference count must have been observed by the current transaction, or be owned by the
+    /// caller, and the thread must stay pinned for as long as the `Inner` is used.
+    #[inline]
+    unsafe fn get<'a>(ptr: Ptr<Self>) -> &'a Self {
+        &*ptr.0.as_ptr()
+    }
+
+    #[inline]
+    unsafe fn value<'a>(&self) -> &'a T {
+        &*self.value.0.as_ptr()
+    }
+
+    fn release_strong<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>) -> Result<(), Error> {
+        let strong = self.strong.get(tx, Ordering::ReadWrite)? - 1;
+        self.strong.set(tx, strong)?;
+        if strong == 0 {
+            let value = self.value;
+            tx._privatize(move || unsafe { drop(Box::from_raw(value.0.as_ptr())) });
+            self.release_weak(tx)?;
+        }
+        Ok(())
+    }
+
+    fn release_weak<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>) -> Result<(), Error> {
+        let weak = self.weak.get(tx, Ordering::ReadWrite)? - 1;
+        self.weak.set(tx, weak)?;
+        if weak == 0 {
+            let this = Ptr(NonNull::from(self));
+            tx._privatize(move || unsafe { drop(Box::from_raw(this.0.as_ptr())) });
+        }
+        Ok(())
+    }
+}
+
+/// Increments a reference count.
+fn acquire<'tcell>(count: &'tcell TCell<usize>, tx: &mut impl Rw<'tcell>) -> Result<(), Error> {
+    let prev = count.get(tx, Ordering::ReadWrite)?;
+    Ok(count.set(tx, prev + 1)?)
+}
+
+/// A reference held by a dropped `TArc` or `TWeak`, that still needs to be released.
+pub(crate) struct Release {
+    inner:   NonNull<()>,
+    release: unsafe fn(NonNull<()>, &mut RwTx<'_>) -> Result<(), Error>,
+}
+
+impl Release {
+    #[inline]
+    fn strong<T: Send + Sync + 'static>(inner: Ptr<Inner<T>>) -> Self {
+        unsafe fn release<T: Send + Sync + 'static>(
+            inner: NonNull<()>,
+            tx: &mut RwTx<'_>,
+        ) -> Result<(), Error> {
+            Inner::<T>::get(Ptr(inner.cast())).release_strong(tx)
+        }
+        Release {
+            inner:   inner.0.cast(),
+            release: release::<T>,
+        }
+    }
+
+    #[inline]
+    fn weak<T: Send + Sync + 'static>(inner: Ptr<Inner<T>>) -> Self {
+        unsafe fn release<T: Send + Sync + 'static>(
+            inner: NonNull<()>,
+            tx: &mut RwTx<'_>,
+        ) -> Result<(), Error> {
+            Inner::<T>::get(Ptr(inner.cast())).release_weak(tx)
+        }
+        Release {
+            inner:   inner.0.cast(),
+            release: release::<T>,
+        }
+    }
+
+    /// Releases the reference as part of `tx`. Every attempt of the transaction must call this,
+    /// until one commits.
+    #[inline]
+    pub(crate) unsafe fn run(&self, tx: &mut RwTx<'_>) -> Result<(), Error> {
+        (self.release)(self.inner, tx)
+    }
+}
+
+/// A transactional memory location holding a strong reference to a shared value, or nothing.
+///
+/// See the [module level documentation](crate::tarc) for more.
+pub struct TArc<T: Send + Sync + 'static> {
+    ptr: TCell<Option<Ptr<Inner<T>>>>,
+}
+
+impl<T: Send + Sync + 'static> Debug for TArc<T> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("TArc")
+            .field("ptr", &self.ptr)
+            .finish()
+    }
+}
+
+impl<T: Send + Sync + 'static> Default for TArc<T> {
+    #[inline]
+    fn default() -> Self {
+        TArc::empty()
+    }
+}
+
+impl<T: Send + Sync + 'static> Drop for TArc<T> {
+    fn drop(&mut self) {
+        if let Some(inner) = self.ptr.borrow_mut().take() {
+            thread_key::release(Release::strong(inner))
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> TArc<T> {
+    /// Moves `value` to the heap, and constructs a `TArc` holding the only reference to it.
+    #[inline]
+    pub fn new(value: T) -> Self {
+        TArc {
+            ptr: TCell::new(Some(Inner::alloc(value))),
+        }
+    }
+
+    /// Constructs an empty `TArc`.
+    ///
+    /// This does not perform any memory allocation.
+    #[inline]
+    pub const fn empty() -> Self {
+        TArc {
+            ptr: TCell::new(None),
+        }
+    }
+
+    /// Gets a reference to the shared value using the specified memory [`Ordering`], or `None` if
+    /// the `TArc` is empty.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified this `TArc` during the current transaction, an error is
+    /// returned.
+    #[inline]
+    pub fn get<'tcell, 'tx>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+        ordering: Ordering,
+    ) -> Result<Option<&'tx T>, Error> {
+        let inner = self.ptr.get(tx, ordering)?;
+        // The value is only dropped after the transaction releasing the last strong reference
+        // commits, and the current thread unpins.
+        Ok(inner.map(|inner| unsafe { Inner::get(inner).value() }))
+    }
+
+    /// Makes this `TArc` share the value of `source`, releasing the reference it previously held.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified either `TArc`, or the reference counts of their values,
+    /// during the current transaction, an error is returned.
+    #[inline]
+    pub fn clone_from<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        source: &'tcell TArc<T>,
+    ) -> Result<(), Error> {
+        let inner = source.ptr.get(tx, Ordering::ReadWrite)?;
+        if let Some(inner) = inner {
+            acquire(&unsafe { Inner::get(inner) }.strong, tx)?;
+        }
+        self.replace(tx, inner)
+    }
+
+    /// Releases the reference held by this `TArc`, leaving it empty.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified this `TArc`, or the reference counts of its value, during
+    /// the current transaction, an error is returned.
+    #[inline]
+    pub fn clear<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>) -> Result<(), Error> {
+        self.replace(tx, None)
+    }
+
+    /// Returns the number of `TArc`s sharing the value of this `TArc`, or 0 if it is empty.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified this `TArc`, or the reference counts of its value, during
+    /// the current transaction, an error is returned.
+    #[inline]
+    pub fn strong_count<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<usize, Error> {
+        match self.ptr.get(tx, Ordering::default())? {
+            Some(inner) => unsafe { Inner::get(inner) }
+                .strong
+                .get(tx, Ordering::default()),
+            None => Ok(0),
+        }
+    }
+
+    /// Returns the number of `TWeak`s referencing the value of this `TArc`, or 0 if it is empty.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified this `TArc`, or the reference counts of its value, during
+    /// the current transaction, an error is returned.
+    #[inline]
+    pub fn weak_count<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<usize, Error> {
+        match self.ptr.get(tx, Ordering::default())? {
+            Some(inner) => Ok(unsafe { Inner::get(inner) }
+                .weak
+                .get(tx, Ordering::default())?
+                - 1),
+            None => Ok(0),
+        }
+    }
+
+    /// Returns true if both `TArc`s share the same value, or are both empty.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified either `TArc` during the current transaction, an error is
+    /// returned.
+    #[inline]
+    pub fn ptr_eq<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        other: &'tcell TArc<T>,
+    ) -> Result<bool, Error> {
+        let this = self.ptr.get(tx, Ordering::default())?.map(|inner| inner.0);
+        let other = other.ptr.get(tx, Ordering::default())?.map(|inner| inner.0);
+        Ok(this == other)
+    }
+
+    fn replace<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        inner: Option<Ptr<Inner<T>>>,
+    ) -> Result<(), Error> {
+        if let Some(prev) = self.ptr.get(tx, Ordering::ReadWrite)? {
+            unsafe { Inner::get(prev) }.release_strong(tx)?;
+        }
+        Ok(self.ptr.set(tx, inner)?)
+    }
+}
+
+/// A transactional memory location holding a weak reference to a shared value, or nothing.
+///
+/// A weak reference does not keep the value alive, so it has to be checked for on every access.
+///
+/// See the [module level documentation](crate::tarc) for more.
+pub struct TWeak<T: Send + Sync + 'static> {
+    ptr: TCell<Option<Ptr<Inner<T>>>>,
+}
+
+impl<T: Send + Sync + 'static> Debug for TWeak<T> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("TWeak")
+            .field("ptr", &self.ptr)
+            .finish()
+    }
+}
+
+impl<T: Send + Sync + 'static> Default for TWeak<T> {
+    #[inline]
+    fn default() -> Self {
+        TWeak::empty()
+    }
+}
+
+impl<T: Send + Sync + 'static> Drop for TWeak<T> {
+    fn drop(&mut self) {
+        if let Some(inner) = self.ptr.borrow_mut().take() {
+            thread_key::release(Release::weak(inner))
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> TWeak<T> {
+    /// Constructs an empty `TWeak`.
+    ///
+    /// This does not perform any memory allocation.
+    #[inline]
+    pub const fn empty() -> Self {
+        TWeak {
+            ptr: TCell::new(None),
+        }
+    }
+
+    /// Gets a reference to the value using the specified memory [`Ordering`], or `None` if the
+    /// `TWeak` is empty, or the value has been dropped.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified this `TWeak`, or the strong reference count of its value,
+    /// during the current transaction, an error is returned.
+    #[inline]
+    pub fn get<'tcell, 'tx>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+        ordering: Ordering,
+    ) -> Result<Option<&'tx T>, Error> {
+        match self.ptr.get(tx, ordering)? {
+            Some(inner) => {
+                let inner = unsafe { Inner::get(inner) };
+                if inner.strong.get(tx, ordering)? > 0 {
+                    Ok(Some(unsafe { inner.value() }))
+                } else {
+                    Ok(None)
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Makes this `TWeak` reference the value of `source`, releasing the reference it previously
+    /// held. If `source` is empty, so is this `TWeak`.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified this `TWeak`, `source`, or the reference counts of their
+    /// values, during the current transaction, an error is returned.
+    #[inline]
+    pub fn downgrade_from<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        source: &'tcell TArc<T>,
+    ) -> Result<(), Error> {
+        let inner = source.ptr.get(tx, Ordering::ReadWrite)?;
+        if let Some(inner) = inner {
+            acquire(&unsafe { Inner::get(inner) }.weak, tx)?;
+        }
+        self.replace(tx, inner)
+    }
+
+    /// Makes `dest` share the value referenced by this `TWeak`, if it has not been dropped.
+    /// Returns false, leaving `dest` untouched, otherwise.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified this `TWeak`, `dest`, or the reference counts of their
+    /// values, during the current transaction, an error is returned.
+    #[inline]
+    pub fn upgrade_into<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        dest: &'tcell TArc<T>,
+    ) -> Result<bool, Error> {
+        if let Some(inner) = self.ptr.get(tx, Ordering::ReadWrite)? {
+            let strong = &unsafe { Inner::get(inner) }.strong;
+            if strong.get(tx, Ordering::ReadWrite)? > 0 {
+                acquire(strong, tx)?;
+                dest.replace(tx, Some(inner))?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Releases the reference held by this `TWeak`, leaving it empty.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified this `TWeak`, or the reference counts of its value, during
+    /// the current transaction, an error is returned.
+    #[inline]
+    pub fn clear<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>) -> Result<(), Error> {
+        self.replace(tx, None)
+    }
+
+    fn replace<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        inner: Option<Ptr<Inner<T>>>,
+    ) -> Result<(), Error> {
+        if let Some(prev) = self.ptr.get(tx, Ordering::ReadWrite)? {
+            unsafe { Inner::get(prev) }.release_weak(tx)?;
+        }
+        Ok(self.ptr.set(tx, inner)?)
+    }
+}
@@ -0,0 +1,160 @@
+//! A transactional owning pointer.
+//!
+//! [`TBox`] is a safe alternative to [`TPtr`](crate::tptr::TPtr) for heap allocations that have a
+//! single owner. Setting a `TBox` publishes the new `Box` when the transaction commits, and
+//! privatizes the previous one, which is dropped once no running transaction could still be
+//! reading it. If the transaction does not commit, the new `Box` is dropped instead.
+//!
+//! Reads return a reference to the pointee that lives as long as the borrow of the transaction.
+//! Pointees are shared with other threads, so they are never mutated while published, only
+//! replaced. `TCell`s stored inside of a pointee cannot be accessed through that reference, as
+//! they are not guaranteed to outlive the transaction.
+//!
+//! # Examples
+//!
+//! ```
+//! use swym::{tbox::TBox, thread_key};
+//!
+//! let config = TBox::new(vec!["a", "b"]);
+//!
+//! let thread_key = thread_key::get();
+//! thread_key.rw(|tx| {
+//!     let mut next = config.get(tx, Default::default())?.unwrap().clone();
+//!     next.push("c");
+//!     Ok(config.set(tx, Box::new(next))?)
+//! });
+//!
+//! let len = thread_key.read(|tx| Ok(config.get(tx, Default::default())?.map_or(0, Vec::len)));
+//! assert_eq!(len, 3);
+//! ```
+
+use crate::{
+    tcell::TCell,
+    tx::{Error, Ordering, Read, SetError, Write},
+};
+use core::fmt::{self, Debug, Formatter};
+
+/// A transactional pointer that owns its pointee, or is empty.
+///
+/// See the [module level documentation](crate::tbox) for more.
+pub struct TBox<T> {
+    ptr: TCell<Option<Box<T>>>,
+}
+
+impl<T> Debug for TBox<T> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("TBox")
+            .field("ptr", &self.ptr)
+            .finish()
+    }
+}
+
+impl<T> Default for TBox<T> {
+    #[inline]
+    fn default() -> Self {
+        TBox::empty()
+    }
+}
+
+impl<T> From<Box<T>> for TBox<T> {
+    #[inline]
+    fn from(value: Box<T>) -> Self {
+        TBox {
+            ptr: TCell::new(Some(value)),
+        }
+    }
+}
+
+impl<T> TBox<T> {
+    /// Allocates `value` on the heap, and constructs a `TBox` owning it.
+    #[inline]
+    pub fn new(value: T) -> Self {
+        TBox::from(Box::new(value))
+    }
+
+    /// Constructs an empty `TBox`.
+    ///
+    /// This does not perform any memory allocation.
+    #[inline]
+    pub const fn empty() -> Self {
+        TBox {
+            ptr: TCell::new(None),
+        }
+    }
+
+    /// Consumes the `TBox`, returning the `Box` it owns, if any.
+    #[inline]
+    pub fn into_inner(self) -> Option<Box<T>> {
+        self.ptr.into_inner()
+    }
+
+    /// Returns a mutable reference to the pointee, if any.
+    ///
+    /// Since this call borrows the `TBox` mutably, no synchronization needs to take place.
+    #[inline]
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.ptr.borrow_mut().as_mut().map(|value| &mut **value)
+    }
+
+    /// Gets a reference to the pointee using the specified memory [`Ordering`], or `None` if the
+    /// `TBox` is empty.
+    ///
+    /// The reference is valid until the transaction is next borrowed mutably, which is required to
+    /// replace the pointee.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has set this `TBox` during the current transaction, an error is returned.
+    #[inline]
+    pub fn get<'tcell, 'tx>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+        ordering: Ordering,
+    ) -> Result<Option<&'tx T>, Error> {
+        let ptr = self.ptr.borrow(tx, ordering)?;
+        // The `Ref` only copies the pointer. A published pointee is not dropped before the thread
+        // is unpinned, and a pointee in the write log is not dropped before it is overwritten,
+        // which requires `tx` to be borrowed mutably.
+        Ok(ptr
+            .as_ref()
+            .map(|value| unsafe { &*(&**value as *const T) }))
+    }
+}
+
+impl<T: Send + 'static> TBox<T> {
+    /// Replaces the pointee with `value`.
+    ///
+    /// `value` is published when the transaction commits, and the previous pointee is dropped once
+    /// no other transaction can still be reading it. If the transaction does not commit, `value`
+    /// is dropped instead.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has set this `TBox` during the current transaction, `value` is returned
+    /// along with an error.
+    #[inline]
+    pub fn set<'tcell>(
+        &'tcell self,
+        tx: &mut impl Write<'tcell>,
+        value: Box<T>,
+    ) -> Result<(), SetError<Box<T>>> {
+        self.ptr.set(tx, Some(value)).map_err(|error| {
+            error.map(|value| match value {
+                Some(value) => value,
+                None => unreachable!("`TBox::set` failed to return its value"),
+            })
+        })
+    }
+
+    /// Empties the `TBox`. The previous pointee is dropped once the transaction commits, and no
+    /// other transaction can still be reading it.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has set this `TBox` during the current transaction, an error is returned.
+    #[inline]
+    pub fn clear<'tcell>(&'tcell self, tx: &mut impl Write<'tcell>) -> Result<(), Error> {
+        Ok(self.ptr.set(tx, None)?)
+    }
+}
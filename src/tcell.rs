@@ -300,9 +300,144 @@ impl<T: 'static + Send> TCell<T> {
         // it's important to bundle value and undo together early for panic safety
         self.set_impl(tx, value)
     }
+
+    /// Sets the contained value, moving the previous value into `privatizer`.
+    ///
+    /// Other transactions may still be reading the previous value when the current transaction
+    /// commits, so `privatizer` is run by the garbage collector once that is no longer possible.
+    /// If the transaction does not commit, `privatizer` is dropped without being called.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has written to this `TCell` during the current transaction, the value is
+    /// not set, and an error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let pool = Arc::new(Mutex::new(Vec::new()));
+    /// let buffer = TCell::new(vec![1, 2, 3]);
+    ///
+    /// thread_key::get().rw(|tx| {
+    ///     let pool = pool.clone();
+    ///     Ok(buffer.replace_and_privatize(tx, vec![4], move |mut prev| {
+    ///         // reuse the allocation of the previous buffer
+    ///         prev.clear();
+    ///         pool.lock().unwrap().push(prev)
+    ///     })?)
+    /// });
+    /// assert_eq!(buffer.into_inner(), [4]);
+    /// ```
+    #[inline]
+    pub fn replace_and_privatize<'tcell, F: FnOnce(T) + Send + 'static>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        value: T,
+        privatizer: F,
+    ) -> Result<(), SetError<T>> {
+        // The previous value is only moved once the transaction commits, so it is never observed
+        // through a shared reference.
+        let this = unsafe { &*(self as *const Self as *const TCell<AssertBorrow<T>>) };
+        let prev = match this.borrow(tx, Ordering::Read) {
+            Ok(prev) => ManuallyDrop::new(unsafe { ptr::read(&**prev) }),
+            Err(error) => return Err(SetError { value, error }),
+        };
+        self.set_impl(tx, Replacement(value))?;
+        // If the transaction does not commit, the closure is dropped, leaking `prev` which is still
+        // owned by the `TCell`.
+        tx._privatize(move || privatizer(ManuallyDrop::into_inner(prev)));
+        Ok(())
+    }
+}
+
+impl<T: 'static + Borrow + Send> TCell<T> {
+    /// Sets the contained value to the result of calling `f` with a reference to the current
+    /// value.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has written to this `TCell` during the current transaction, an error is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let x = TCell::new(String::from("hello"));
+    /// thread_key::get().rw(|tx| Ok(x.update(tx, |s| s.to_uppercase())?));
+    /// assert_eq!(x.into_inner(), "HELLO");
+    /// ```
+    #[inline]
+    pub fn update<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        f: impl FnOnce(&T) -> T,
+    ) -> Result<(), Error> {
+        // the write validates the read at commit time
+        let value = f(&*self.borrow(tx, Ordering::Read)?);
+        Ok(self.set(tx, value)?)
+    }
 }
 
 impl<T: 'static + Borrow + Clone + Send> TCell<T> {
+    /// Sets the contained value to the result of calling `f` with a reference to the current
+    /// value, and returns a clone of the previous value.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has written to this `TCell` during the current transaction, an error is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let x = TCell::new(1);
+    /// let prev = thread_key::get().rw(|tx| Ok(x.fetch_update(tx, |x| x * 10)?));
+    /// assert_eq!(prev, 1);
+    /// assert_eq!(x.into_inner(), 10);
+    /// ```
+    #[inline]
+    pub fn fetch_update<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        f: impl FnOnce(&T) -> T,
+    ) -> Result<T, Error> {
+        let (prev, value) = {
+            let prev = self.borrow(tx, Ordering::Read)?;
+            let value = f(&*prev);
+            ((*prev).clone(), value)
+        };
+        self.set(tx, value)?;
+        Ok(prev)
+    }
+
+    /// Sets the contained value, and returns a clone of the previous value.
+    ///
+    /// The previous value itself is dropped once no other transaction can be reading it. See
+    /// [`replace_and_privatize`](TCell::replace_and_privatize) to take ownership of it instead.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has written to this `TCell` during the current transaction, the value is
+    /// not set, and an error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let x = TCell::new("hello");
+    /// let prev = thread_key::get().rw(|tx| Ok(x.replace(tx, "world")?));
+    /// assert_eq!(prev, "hello");
+    /// assert_eq!(x.into_inner(), "world");
+    /// ```
+    #[inline]
     pub fn replace<'tcell, 'tx>(
         &'tcell self,
         tx: &'tx mut impl Rw<'tcell>,
@@ -315,6 +450,72 @@ impl<T: 'static + Borrow + Clone + Send> TCell<T> {
         self.set(tx, value)?;
         Ok(prev)
     }
+
+    /// Replaces the contained value with its default, and returns a clone of the previous value.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has written to this `TCell` during the current transaction, an error is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let x = TCell::new(vec![1, 2]);
+    /// let prev = thread_key::get().rw(|tx| Ok(x.take(tx)?));
+    /// assert_eq!(prev, [1, 2]);
+    /// assert!(x.into_inner().is_empty());
+    /// ```
+    #[inline]
+    pub fn take<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>) -> Result<T, Error>
+    where
+        T: Default,
+    {
+        Ok(self.replace(tx, T::default())?)
+    }
+
+    /// Swaps the contained values of `self` and `other`.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has written to either `TCell` during the current transaction, an error is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let x = TCell::new("x");
+    /// let y = TCell::new("y");
+    /// thread_key::get().rw(|tx| Ok(x.swap(tx, &y)?));
+    /// assert_eq!((x.into_inner(), y.into_inner()), ("y", "x"));
+    /// ```
+    #[inline]
+    pub fn swap<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        other: &'tcell TCell<T>,
+    ) -> Result<(), Error> {
+        if ptr::eq(self, other) {
+            return Ok(());
+        }
+        let value = other.borrow(tx, Ordering::Read)?.clone();
+        let other_value = self.replace(tx, value)?;
+        Ok(other.set(tx, other_value)?)
+    }
+}
+
+/// A value written by [`TCell::replace_and_privatize`]. The previous value of the `TCell` is owned
+/// by the privatizer, and must not be dropped by the write.
+#[repr(transparent)]
+struct Replacement<T>(T);
+
+unsafe impl<T: 'static> _TValue<T> for Replacement<T> {
+    const REQUEST_TCELL_LIFETIME: bool = false;
+    const PRIVATIZES_PREVIOUS: bool = true;
 }
 
 /// A snapshot of a [`TCell`] valid for the duration of the current transaction.
@@ -350,6 +551,85 @@ impl<'tx, T> Ref<'tx, T> {
             lifetime: PhantomData,
         }
     }
+
+    /// Makes a new `Ref` for a component of the snapshot, valid for the same transaction.
+    ///
+    /// This is an associated function that needs to be used as `Ref::map(...)`, so that it does
+    /// not conflict with methods of the same name on the contents of the `Ref`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{
+    ///     tcell::{Ref, TCell},
+    ///     thread_key,
+    ///     tx::{Error, Read},
+    /// };
+    ///
+    /// struct Config {
+    ///     name:    String,
+    ///     retries: usize,
+    /// }
+    ///
+    /// fn name<'tx, 'tcell>(
+    ///     tx: &'tx impl Read<'tcell>,
+    ///     config: &'tcell TCell<Config>,
+    /// ) -> Result<Ref<'tx, String>, Error> {
+    ///     Ok(Ref::map(config.borrow(tx, Default::default())?, |config| {
+    ///         &config.name
+    ///     }))
+    /// }
+    ///
+    /// let config = TCell::new(Config {
+    ///     name:    "swym".to_owned(),
+    ///     retries: 3,
+    /// });
+    /// let len = thread_key::get().read(|tx| Ok(name(tx, &config)?.len()));
+    /// assert_eq!(len, 4);
+    /// ```
+    #[inline]
+    pub fn map<U: Borrow, F: FnOnce(&T) -> &U>(this: Self, f: F) -> Ref<'tx, U> {
+        // The component is either part of the snapshot, or reachable through it, and so lives for
+        // the duration of the transaction. `U: Borrow` allows it to be snapshotted in turn.
+        let snapshot = unsafe { ptr::read(f(&this) as *const U as *const ManuallyDrop<U>) };
+        Ref::new(snapshot)
+    }
+
+    /// Makes a new `Ref` for an optional component of the snapshot. If `f` returns `None`, the
+    /// original `Ref` is returned as an error.
+    ///
+    /// This is an associated function that needs to be used as `Ref::filter_map(...)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{
+    ///     tcell::{Ref, TCell},
+    ///     thread_key,
+    /// };
+    ///
+    /// let x = TCell::new(vec![1, 2, 3]);
+    /// thread_key::get().read(|tx| {
+    ///     let first = Ref::filter_map(x.borrow(tx, Default::default())?, |v| v.first());
+    ///     assert_eq!(*first.unwrap(), 1);
+    ///
+    ///     let tenth = Ref::filter_map(x.borrow(tx, Default::default())?, |v| v.get(9));
+    ///     assert_eq!(*tenth.unwrap_err(), [1, 2, 3]);
+    ///     Ok(())
+    /// });
+    /// ```
+    #[inline]
+    pub fn filter_map<U: Borrow, F: FnOnce(&T) -> Option<&U>>(
+        this: Self,
+        f: F,
+    ) -> Result<Ref<'tx, U>, Self> {
+        let snapshot = f(&this)
+            .map(|component| unsafe { ptr::read(component as *const U as *const ManuallyDrop<U>) });
+        match snapshot {
+            Some(snapshot) => Ok(Ref::new(snapshot)),
+            None => Err(this),
+        }
+    }
 }
 
 impl<'tx, T: Borrow> From<&'tx T> for Ref<'tx, T> {
@@ -452,6 +732,7 @@ where
 #[cfg(test)]
 mod test {
     use crate::{
+        diagnostics::ConflictKind,
         tcell::TCell,
         thread_key,
         tx::{Error, _TValue},
@@ -508,7 +789,7 @@ mod test {
                                 TRIGGERED.store(true, Ordering::Relaxed);
                             }),
                         )?;
-                        Err(Error::CONFLICT.into())
+                        Err(Error::conflict(ConflictKind::ReadValidation).into())
                     }
                 });
             });
@@ -554,7 +835,7 @@ mod test {
                             }),
                         )?;
                         x.set(tx, 3)?;
-                        Err(Error::CONFLICT.into())
+                        Err(Error::conflict(ConflictKind::ReadValidation).into())
                     }
                 });
             });
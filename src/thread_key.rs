@@ -3,12 +3,29 @@
 //! A handle to the thread local state can be acquired by calling [`thread_key::get`].
 
 use crate::{
-    internal::{phoenix_tls::Phoenix, thread::Thread},
+    contention::ContentionManager,
+    internal::{
+        epoch::QuiesceEpoch,
+        parking::{Cancel, Registration},
+        phoenix_tls::Phoenix,
+        thread::{NestErr, Thread},
+        versions::SnapshotGuard,
+    },
+    irrevocable::IrrevocableTx,
+    observer::TxObserver,
     read::ReadTx,
     rw::RwTx,
-    tx::{Error, Status},
+    snapshot::SnapshotTx,
+    stats::{self, Stats},
+    tarc::Release,
+    tx::{Abort, Error, Status},
 };
-use core::fmt::{self, Debug, Formatter};
+use core::{
+    convert::Infallible,
+    fmt::{self, Debug, Formatter},
+    task::Waker,
+};
+use std::{sync::Arc, time::Instant};
 
 /// A handle to `swym`'s thread local state.
 ///
@@ -30,9 +47,18 @@ impl Debug for ThreadKey {
 impl ThreadKey {
     /// Performs a transaction capabable of only reading.
     ///
+    /// To wait on [`Status::AWAIT_RETRY`], use
+    /// [`read_awaitable`](struct.ThreadKey.html#method.read_awaitable).
+    ///
+    /// # Nesting
+    ///
+    /// If a transaction is already running on the current thread, `f` runs as a closed nested
+    /// transaction inside of it, and its reads are validated when the enclosing transaction
+    /// commits. See [`rw`](struct.ThreadKey.html#method.rw) for details.
+    ///
     /// # Panics
     ///
-    /// Panics if there is already a running transaction on the current thread.
+    /// Panics if the current thread is committing a transaction (e.g. from a destructor).
     ///
     /// # Examples
     ///
@@ -47,19 +73,120 @@ impl ThreadKey {
     /// assert_eq!(x_clone, "not gonna be overwritten");
     /// ```
     #[inline]
-    pub fn read<'tcell, F, O>(&'tcell self, f: F) -> O
+    pub fn read<'tcell, F, O>(&'tcell self, mut f: F) -> O
     where
         F: FnMut(&ReadTx<'tcell>) -> Result<O, Error>,
     {
-        self.try_read(f)
-            .expect("nested transactions are not yet supported")
+        unwrap_nested(self.read_impl(false, move |tx| Ok(f(tx)?))).0
+    }
+
+    /// Performs a transaction capable of only reading, which may wait on
+    /// [`Status::AWAIT_RETRY`].
+    ///
+    /// Returning `AWAIT_RETRY` blocks the thread until another thread modifies a `TCell` that `f`
+    /// read. Read only transactions normally keep no read log, so after the first `AWAIT_RETRY`,
+    /// `f` is rerun while recording its reads, and the thread waits on those.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread is committing a transaction (e.g. from a destructor), or if `f`
+    /// returns `AWAIT_RETRY` while nested inside of another transaction. Use
+    /// [`try_read`](struct.ThreadKey.html#method.try_read) to wait from inside of a transaction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::thread;
+    /// use swym::{tcell::TCell, thread_key, tx::Status};
+    ///
+    /// static READY: TCell<bool> = TCell::new(false);
+    ///
+    /// let setter = thread::spawn(|| thread_key::get().rw(|tx| Ok(READY.set(tx, true)?)));
+    ///
+    /// thread_key::get().read_awaitable(|tx| {
+    ///     if READY.get(tx, Default::default())? {
+    ///         Ok(())
+    ///     } else {
+    ///         Err(Status::AWAIT_RETRY)
+    ///     }
+    /// });
+    /// setter.join().unwrap();
+    /// ```
+    #[inline]
+    pub fn read_awaitable<'tcell, F, O>(&'tcell self, mut f: F) -> O
+    where
+        F: FnMut(&ReadTx<'tcell>) -> Result<O, Status>,
+    {
+        unwrap_nested(self.read_impl(false, move |tx| f(tx))).0
+    }
+
+    /// Performs a transaction capable of only reading, which may be upgraded to write with
+    /// [`ReadTx::upgrade`].
+    ///
+    /// Unlike [`read`](struct.ThreadKey.html#method.read), every read is recorded in a read log, so
+    /// that the writes of upgrades can be validated against them. The read log is paid for whether
+    /// or not the transaction is upgraded, so `read` remains the better choice for transactions
+    /// that rarely write. The reads are not checked against a write log until the transaction is
+    /// upgraded. Returning [`Status::AWAIT_RETRY`] blocks the thread until another thread modifies
+    /// a `TCell` that `f` read.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within a read only transaction, or while the current thread is
+    /// committing a transaction (e.g. from a destructor). Also panics if `f` returns
+    /// `AWAIT_RETRY` while nested inside of another transaction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key, tx::Ordering};
+    ///
+    /// let x = TCell::new(0);
+    ///
+    /// let thread_key = thread_key::get();
+    /// thread_key.read_upgradable(|tx| {
+    ///     if x.get(tx, Ordering::default())? == 0 {
+    ///         tx.upgrade(|tx| Ok(x.set(tx, 1)?))?;
+    ///     }
+    ///     Ok(())
+    /// });
+    /// assert_eq!(x.into_inner(), 1);
+    /// ```
+    #[inline]
+    pub fn read_upgradable<'tcell, F, O>(&'tcell self, f: F) -> O
+    where
+        F: FnMut(&mut ReadTx<'tcell>) -> Result<O, Status>,
+    {
+        unwrap_nested(self.read_impl(true, f)).0
     }
 
     /// Performs a transaction capabable of reading and writing.
     ///
+    /// # Nesting
+    ///
+    /// If a transaction is already running on the current thread, `f` runs as a closed nested
+    /// transaction inside of it, instead of starting a new one. This lets functions that each run
+    /// their own transaction be composed into a single atomic transaction. The reads and writes of
+    /// `f` become part of the enclosing transaction, and are only committed along with it. If `f`
+    /// conflicts, only its own writes are discarded before it is retried. If the enclosing
+    /// transaction can no longer commit, `f` is run against a newer snapshot, and the enclosing
+    /// transaction is restarted once its closure returns.
+    ///
+    /// Read write transactions can be nested inside of read write and upgradable transactions.
+    /// Read only transactions can be nested inside of any transaction. A nested transaction that
+    /// returns [`Status::AWAIT_RETRY`] discards its writes, and makes the enclosing transaction
+    /// wait, once its closure returns, until something either of them read is modified.
+    ///
+    /// Every `TCell` accessed by a nested `f` must be neither moved nor dropped until the
+    /// enclosing transaction has completed. [`RwTx::nested`](crate::RwTx::nested) runs nested
+    /// transactions whose `TCell`s are checked by the borrow checker.
+    ///
     /// # Panics
     ///
-    /// Panics if there is already a running transaction on the current thread.
+    /// Panics if called from within a read only transaction, or while the current thread is
+    /// committing a transaction (e.g. from a destructor). Also panics if `f` returns
+    /// `AWAIT_RETRY` while nested inside of another transaction, since it has nothing to return.
+    /// Use [`try_rw`](struct.ThreadKey.html#method.try_rw) to wait from inside of a transaction.
     ///
     /// # Examples
     ///
@@ -82,15 +209,117 @@ impl ThreadKey {
     where
         F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
     {
-        self.try_rw(f)
-            .expect("nested transactions are not yet supported")
+        unwrap_nested(self.rw_impl(f)).0
+    }
+
+    /// Performs a transaction capable of reading and writing, collecting its statistics under
+    /// `name` in addition to the totals.
+    ///
+    /// Named statistics are returned by [`Stats::named`], and the names with the most conflicts are
+    /// listed by [`Stats::print_summary`]. Statistics must be collected for names to have any
+    /// effect, see the [`stats`](crate::stats) module.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`rw`](ThreadKey::rw).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{stats, tcell::TCell, thread_key};
+    ///
+    /// let orders = TCell::new(Vec::new());
+    ///
+    /// stats::enable(stats::Kind::ALL);
+    /// let thread_key = thread_key::get();
+    /// thread_key.rw_named("orders::insert", |tx| {
+    ///     let mut new_orders = orders.borrow(tx, Default::default())?.clone();
+    ///     new_orders.push(42);
+    ///     Ok(orders.set(tx, new_orders)?)
+    /// });
+    /// stats::disable(stats::Kind::ALL);
+    ///
+    /// let stats = thread_key.stats();
+    /// assert_eq!(stats.named("orders::insert").unwrap().transactions(), 1);
+    /// ```
+    #[inline]
+    pub fn rw_named<'tcell, F, O>(&'tcell self, name: &'static str, f: F) -> O
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        let _name = stats::named(name);
+        self.rw(f)
+    }
+
+    /// Performs a transaction capable of reading and writing, returning the [`TxInfo`] describing
+    /// how it ran along with its result.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`rw`](ThreadKey::rw).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let x = TCell::new(0);
+    ///
+    /// let (prev_x, info) = thread_key::get().rw_with_info(|tx| {
+    ///     let prev_x = x.get(tx, Default::default())?;
+    ///     x.set(tx, prev_x + 1)?;
+    ///     Ok(prev_x)
+    /// });
+    /// assert_eq!(prev_x, 0);
+    /// if info.conflicts() > 0 {
+    ///     println!("{} conflicts, shrinking the batch size", info.conflicts());
+    /// }
+    /// ```
+    #[inline]
+    pub fn rw_with_info<'tcell, F, O>(&'tcell self, f: F) -> (O, TxInfo)
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        unwrap_nested(self.rw_impl(f))
+    }
+
+    /// Performs a transaction capable of only reading, returning the [`TxInfo`] describing how it
+    /// ran along with its result.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`read`](ThreadKey::read).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let x = TCell::new(0);
+    ///
+    /// let thread_key = thread_key::get();
+    ///
+    /// let (x_value, info) = thread_key.read_with_info(|tx| Ok(x.get(tx, Default::default())?));
+    /// assert_eq!(x_value, 0);
+    /// assert!(!info.parked());
+    /// ```
+    #[inline]
+    pub fn read_with_info<'tcell, F, O>(&'tcell self, mut f: F) -> (O, TxInfo)
+    where
+        F: FnMut(&ReadTx<'tcell>) -> Result<O, Error>,
+    {
+        unwrap_nested(self.read_impl(false, move |tx| Ok(f(tx)?)))
     }
 
     /// Performs a transaction capabable of only reading.
     ///
+    /// Like [`read`](ThreadKey::read), `f` is nested inside of the transaction running on the
+    /// current thread, if any.
+    ///
     /// # Errors
     ///
-    /// Returns a [`TryReadErr`] if a transaction is already running on the current thread.
+    /// Returns a [`TryReadErr`] if the current thread is committing a transaction (e.g. from a
+    /// destructor).
     ///
     /// # Examples
     ///
@@ -106,24 +335,99 @@ impl ThreadKey {
     ///     .unwrap();
     /// assert_eq!(x_clone, "not gonna be overwritten");
     /// ```
-    #[allow(clippy::redundant_closure)]
     #[inline]
-    pub fn try_read<'tcell, F, O>(&'tcell self, f: F) -> Result<O, TryReadErr>
+    pub fn try_read<'tcell, F, O>(&'tcell self, mut f: F) -> Result<O, TryReadErr>
+    where
+        F: FnMut(&ReadTx<'tcell>) -> Result<O, Error>,
+    {
+        self.read_impl(false, move |tx| Ok(f(tx)?))
+            .map(|(o, _)| o)
+            .map_err(|_| TryReadErr::new())
+    }
+
+    /// Performs a transaction capable of only reading, returning the [`TxInfo`] describing how it
+    /// ran along with its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TryReadErr`] under the same conditions as [`try_read`](ThreadKey::try_read).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let x = TCell::new(0);
+    ///
+    /// let thread_key = thread_key::get();
+    ///
+    /// let (x_value, info) = thread_key
+    ///     .try_read_with_info(|tx| Ok(x.get(tx, Default::default())?))
+    ///     .unwrap();
+    /// assert_eq!(x_value, 0);
+    /// assert!(!info.parked());
+    /// ```
+    #[inline]
+    pub fn try_read_with_info<'tcell, F, O>(
+        &'tcell self,
+        mut f: F,
+    ) -> Result<(O, TxInfo), TryReadErr>
     where
         F: FnMut(&ReadTx<'tcell>) -> Result<O, Error>,
     {
-        Ok(self
-            .thread
-            .try_pin()
-            .ok_or_else(|| TryReadErr::new())?
-            .run_read(f))
+        self.read_impl(false, move |tx| Ok(f(tx)?))
+            .map_err(|_| TryReadErr::new())
+    }
+
+    /// Performs a read only transaction that sees the values that were current when it started,
+    /// even if they are overwritten while it runs.
+    ///
+    /// Unlike [`read`](struct.ThreadKey.html#method.read), long running snapshots are not restarted
+    /// by concurrent writes. While any snapshot transaction is running, committing threads keep a
+    /// copy of every value they overwrite, until no snapshot could still read it. `f` is only rerun
+    /// if a value was overwritten by a commit that started before the snapshot.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within another transaction, or while the current thread is committing
+    /// a transaction (e.g. from a destructor). Transactions cannot be started from within `f`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let x = TCell::new(String::from("not gonna be overwritten"));
+    ///
+    /// let thread_key = thread_key::get();
+    ///
+    /// let x_clone = thread_key.read_snapshot(|tx| Ok(x.borrow(tx, Default::default())?.to_owned()));
+    /// assert_eq!(x_clone, "not gonna be overwritten");
+    /// ```
+    #[inline]
+    pub fn read_snapshot<'tcell, F, O>(&'tcell self, f: F) -> O
+    where
+        F: FnMut(&SnapshotTx<'tcell>) -> Result<O, Error>,
+    {
+        let _snapshot = SnapshotGuard::new();
+        match self.thread.try_pin() {
+            Some(pin) => pin.run_snapshot(f),
+            None => panic!("snapshot transactions cannot be nested inside of other transactions"),
+        }
     }
 
     /// Performs a transaction capabable of reading and writing.
     ///
+    /// Like [`rw`](ThreadKey::rw), `f` is nested inside of the transaction running on the current
+    /// thread, if any.
+    ///
     /// # Errors
     ///
-    /// Returns a [`TryRwErr`] if a transaction is already running on the current thread.
+    /// Returns a [`TryRwErr`] if called from within a read only transaction, or while the current
+    /// thread is committing a transaction (e.g. from a destructor). Also returns a `TryRwErr` if
+    /// `f` returned [`Status::AWAIT_RETRY`] while nested inside of another transaction. The
+    /// enclosing transaction then waits on `AWAIT_RETRY` once its closure returns, whatever it
+    /// returns.
     ///
     /// # Examples
     ///
@@ -143,20 +447,421 @@ impl ThreadKey {
     ///     .unwrap();
     /// assert_eq!(prev_x, "gonna be overwritten");
     /// ```
-    #[allow(clippy::redundant_closure)]
     #[inline]
     pub fn try_rw<'tcell, F, O>(&'tcell self, f: F) -> Result<O, TryRwErr>
     where
         F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
     {
-        Ok(self
-            .thread
-            .try_pin()
-            .ok_or_else(|| TryRwErr::new())?
-            .run_rw(f))
+        self.rw_impl(f).map(|(o, _)| o).map_err(|_| TryRwErr::new())
+    }
+
+    /// Performs a transaction capable of reading and writing, returning the [`TxInfo`] describing
+    /// how it ran along with its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TryRwErr`] under the same conditions as [`try_rw`](ThreadKey::try_rw).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let x = TCell::new(0);
+    ///
+    /// let thread_key = thread_key::get();
+    ///
+    /// let ((), info) = thread_key
+    ///     .try_rw_with_info(|tx| Ok(x.set(tx, 1)?))
+    ///     .unwrap();
+    /// assert_eq!(info.conflicts(), 0);
+    /// ```
+    #[inline]
+    pub fn try_rw_with_info<'tcell, F, O>(&'tcell self, f: F) -> Result<(O, TxInfo), TryRwErr>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        self.rw_impl(f).map_err(|_| TryRwErr::new())
+    }
+
+    /// Performs a read write transaction which can be aborted with a user defined error.
+    ///
+    /// Returning [`Abort::new(e)`](Abort::new) (or [`Status::abort(e)`](Status::abort)) from `f`
+    /// discards everything the transaction has written, and returns `Err(e)` without retrying.
+    /// Every other error is handled exactly as it would be by [`rw`](ThreadKey::rw).
+    ///
+    /// When nested inside of another transaction, an abort only discards the writes of `f`, as it
+    /// does for [`RwTx::nested_abortable`](crate::RwTx::nested_abortable).
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`rw`](ThreadKey::rw).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key, tx::Status};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct InsufficientBalance;
+    ///
+    /// let from = TCell::new(10);
+    /// let to = TCell::new(0);
+    ///
+    /// let thread_key = thread_key::get();
+    /// let transfer = |amount| {
+    ///     thread_key.rw_abortable(|tx| {
+    ///         to.set(tx, to.get(tx, Default::default())? + amount)?;
+    ///         let balance = from.get(tx, Default::default())?;
+    ///         if balance < amount {
+    ///             return Err(Status::abort(InsufficientBalance));
+    ///         }
+    ///         Ok(from.set(tx, balance - amount)?)
+    ///     })
+    /// };
+    ///
+    /// assert_eq!(transfer(7), Ok(()));
+    /// assert_eq!(transfer(7), Err(InsufficientBalance));
+    /// assert_eq!(from.into_inner(), 3);
+    /// assert_eq!(to.into_inner(), 7);
+    /// ```
+    #[inline]
+    pub fn rw_abortable<'tcell, F, O, E>(&'tcell self, f: F) -> Result<O, E>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Abort<E>>,
+    {
+        match self.thread.try_pin() {
+            Some(pin) => pin.run_rw_abortable(f),
+            None => unwrap_nested(self.thread.try_nested_rw(f)).0,
+        }
+    }
+
+    /// Performs a read write transaction which runs `f` exactly once, and never conflicts.
+    ///
+    /// Before `f` is called, the current thread waits until it is the only thread allowed to
+    /// commit. Other threads keep running transactions, but wait before committing any writes
+    /// until `f` has returned. Every `TCell` accessed by `f` is locked until the transaction
+    /// commits. This makes it safe for `f` to perform side effects that can not be undone, such
+    /// as I/O or calls through FFI.
+    ///
+    /// Since `f` can not conflict, operations on the transaction never return errors, and their
+    /// results can be unwrapped.
+    ///
+    /// Irrevocable transactions serialize every thread that writes, and should be reserved for
+    /// transactions that can not be retried.
+    ///
+    /// # Deadlocks
+    ///
+    /// `f` must not wait on another thread that is committing a read write transaction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within another transaction, or if `f` attempts to start a transaction.
+    /// If `f` panics, everything it has written is discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let next_id = TCell::new(0);
+    /// let mut log = Vec::new();
+    ///
+    /// let thread_key = thread_key::get();
+    /// let id = thread_key.rw_irrevocable(|tx| {
+    ///     let id = next_id.get(tx, Default::default()).unwrap();
+    ///     next_id.set(tx, id + 1).unwrap();
+    ///     writeln!(log, "allocated id {}", id).unwrap();
+    ///     id
+    /// });
+    /// assert_eq!(id, 0);
+    /// assert_eq!(log, b"allocated id 0\n");
+    /// ```
+    #[inline]
+    pub fn rw_irrevocable<'tcell, F, O>(&'tcell self, f: F) -> O
+    where
+        F: FnOnce(&mut IrrevocableTx<'tcell>) -> O,
+    {
+        match self.thread.try_pin_irrevocable() {
+            Some(pin) => pin.run_irrevocable(f),
+            None => {
+                panic!("irrevocable transactions cannot be nested inside of other transactions")
+            }
+        }
+    }
+
+    /// Performs a read write transaction which stops waiting on [`Status::AWAIT_RETRY`] once
+    /// `deadline` has passed.
+    ///
+    /// The deadline only limits the time spent parked. Transactions that conflict are retried
+    /// regardless of the deadline.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AwaitErr::TimedOut`] if the transaction was still waiting for a change to its read
+    /// set when the deadline passed. Nothing is committed in that case.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`rw`](ThreadKey::rw). When nested inside of another
+    /// transaction, `f` is run as it would be by `rw`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::{Duration, Instant};
+    /// use swym::{
+    ///     tcell::TCell,
+    ///     thread_key::{self, AwaitErr},
+    ///     tx::Status,
+    /// };
+    ///
+    /// let ready = TCell::new(false);
+    ///
+    /// let thread_key = thread_key::get();
+    /// let deadline = Instant::now() + Duration::from_millis(10);
+    ///
+    /// let result = thread_key.rw_timeout(deadline, |tx| {
+    ///     if ready.get(tx, Default::default())? {
+    ///         Ok(())
+    ///     } else {
+    ///         Err(Status::AWAIT_RETRY)
+    ///     }
+    /// });
+    /// assert_eq!(result, Err(AwaitErr::TimedOut));
+    /// ```
+    #[inline]
+    pub fn rw_timeout<'tcell, F, O>(&'tcell self, deadline: Instant, f: F) -> Result<O, AwaitErr>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        self.rw_until(f, Some(deadline), None)
+    }
+
+    /// Performs a read write transaction which stops waiting on [`Status::AWAIT_RETRY`] once
+    /// `cancel` is cancelled, or the optional `deadline` has passed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AwaitErr::Cancelled`] if [`CancelHandle::cancel`] was called, or
+    /// [`AwaitErr::TimedOut`] if the deadline passed while the transaction was waiting for a change
+    /// to its read set. Nothing is committed in either case.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`rw`](ThreadKey::rw). When nested inside of another
+    /// transaction, `f` is run as it would be by `rw`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{
+    ///     tcell::TCell,
+    ///     thread_key::{self, AwaitErr, CancelHandle},
+    ///     tx::Status,
+    /// };
+    ///
+    /// let ready = TCell::new(false);
+    /// let cancel = CancelHandle::new();
+    ///
+    /// crossbeam_utils::thread::scope(|s| {
+    ///     s.spawn(|_| {
+    ///         let result = thread_key::get().rw_cancellable(&cancel, None, |tx| {
+    ///             if ready.get(tx, Default::default())? {
+    ///                 Ok(())
+    ///             } else {
+    ///                 Err(Status::AWAIT_RETRY)
+    ///             }
+    ///         });
+    ///         assert_eq!(result, Err(AwaitErr::Cancelled));
+    ///     });
+    ///     cancel.cancel();
+    /// })
+    /// .unwrap();
+    /// ```
+    #[inline]
+    pub fn rw_cancellable<'tcell, F, O>(
+        &'tcell self,
+        cancel: &CancelHandle,
+        deadline: Option<Instant>,
+        f: F,
+    ) -> Result<O, AwaitErr>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        self.rw_until(f, deadline, Some(&cancel.cancel))
+    }
+
+    /// Sets the [`ContentionManager`] deciding how transactions on the current thread backoff after
+    /// failing, returning the previous one.
+    ///
+    /// The contention manager is shared by every `ThreadKey` on the current thread. See the
+    /// [`contention`](crate::contention) module for an example.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a callback of the current contention manager.
+    #[inline]
+    pub fn set_contention_manager<C>(&self, manager: C) -> Option<Box<dyn ContentionManager>>
+    where
+        C: ContentionManager + 'static,
+    {
+        self.thread.set_contention_manager(Some(Box::new(manager)))
+    }
+
+    /// Removes the [`ContentionManager`] of the current thread, restoring the default backoff
+    /// policy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a callback of the current contention manager.
+    #[inline]
+    pub fn take_contention_manager(&self) -> Option<Box<dyn ContentionManager>> {
+        self.thread.set_contention_manager(None)
+    }
+
+    /// Sets the [`TxObserver`] receiving the events of transactions on the current thread,
+    /// returning the previous one.
+    ///
+    /// The observer is shared by every `ThreadKey` on the current thread, and is notified in
+    /// addition to the [global observer](crate::observer::set_global). See the
+    /// [`observer`](crate::observer) module for an example.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a callback of the current observer.
+    #[inline]
+    pub fn set_observer<O>(&self, observer: O) -> Option<Box<dyn TxObserver>>
+    where
+        O: TxObserver + 'static,
+    {
+        self.thread.set_observer(Some(Box::new(observer)))
+    }
+
+    /// Removes the [`TxObserver`] of the current thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a callback of the current observer.
+    #[inline]
+    pub fn take_observer(&self) -> Option<Box<dyn TxObserver>> {
+        self.thread.set_observer(None)
+    }
+
+    /// Returns the statistics collected by the current thread since they were last flushed, taken
+    /// or reset.
+    ///
+    /// [`stats::snapshot`](crate::stats::snapshot) returns the statistics of every thread.
+    #[inline]
+    pub fn stats(&self) -> Stats {
+        stats::current_thread()
+    }
+
+    /// Runs a read write transaction for [`future::Rw`](crate::future::Rw), registering `waker`
+    /// instead of parking the thread on `AWAIT_RETRY`. Returns the registration if the waker was
+    /// registered.
+    ///
+    /// Unlike the other methods, `'tcell` is not tied to the borrow of `self`, since the future
+    /// outlives the `ThreadKey` used to poll it.
+    #[inline]
+    pub(crate) fn rw_or_register<'tcell, F, O>(
+        &self,
+        f: F,
+        waker: &Waker,
+    ) -> Result<O, Registration>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        match self.thread.try_pin() {
+            Some(pin) => pin.run_rw_or_register(f, waker),
+            None => panic!("`future::rw` cannot be polled from inside of a transaction"),
+        }
+    }
+
+    #[inline]
+    fn rw_until<'tcell, F, O>(
+        &'tcell self,
+        f: F,
+        deadline: Option<Instant>,
+        cancel: Option<&Cancel>,
+    ) -> Result<O, AwaitErr>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        match self.thread.try_pin() {
+            Some(pin) => pin.run_rw_until(f, deadline, cancel),
+            // The enclosing transaction does the waiting, so the deadline and cancel do not apply.
+            None => Ok(self.rw(f)),
+        }
+    }
+
+    /// Runs a read only transaction, nested inside of the transaction running on the current
+    /// thread, if any.
+    #[inline]
+    fn read_impl<'tcell, F, O>(&'tcell self, upgradable: bool, f: F) -> Result<(O, TxInfo), NestErr>
+    where
+        F: FnMut(&mut ReadTx<'tcell>) -> Result<O, Status>,
+    {
+        match self.thread.try_pin() {
+            Some(pin) => Ok(pin.run_read_with(upgradable, f)),
+            None => self.thread.try_nested_read(upgradable, f),
+        }
+    }
+
+    /// Runs a read write transaction, nested inside of the transaction running on the current
+    /// thread, if any.
+    #[inline]
+    fn rw_impl<'tcell, F, O>(&'tcell self, mut f: F) -> Result<(O, TxInfo), NestErr>
+    where
+        F: FnMut(&mut RwTx<'tcell>) -> Result<O, Status>,
+    {
+        match self.thread.try_pin() {
+            Some(pin) => Ok(pin.run_rw_with_info(f)),
+            None => match self
+                .thread
+                .try_nested_rw::<_, _, Infallible>(move |tx| Ok(f(tx)?))?
+            {
+                (Ok(o), info) => Ok((o, info)),
+                (Err(never), _) => match never {},
+            },
+        }
     }
 }
 
+/// Returns the result of a transaction that may have been nested, panicking if it could not be.
+#[inline]
+fn unwrap_nested<T>(result: Result<T, NestErr>) -> T {
+    match result {
+        Ok(t) => t,
+        Err(e) => nest_failed(e),
+    }
+}
+
+#[inline(never)]
+#[cold]
+fn nest_failed(e: NestErr) -> ! {
+    match e {
+        NestErr::Unsupported => panic!(
+            "the transaction can not be nested inside of the transaction running on the current \
+             thread"
+        ),
+        NestErr::AwaitRetry => panic!(
+            "a nested transaction returned `AWAIT_RETRY`, use `try_rw` or `try_read` to wait from \
+             inside of another transaction"
+        ),
+    }
+}
+
+/// Releases a reference held by a dropped `TArc` or `TWeak` on the current thread.
+#[inline]
+pub(crate) fn release(release: Release) {
+    Thread::with_exiting(|exiting| match exiting {
+        Some(thread) => thread.release(release),
+        None => get().thread.release(release),
+    })
+}
+
 mod tls {
     use crate::internal::thread::Thread;
 
@@ -193,7 +898,8 @@ pub fn get() -> ThreadKey {
     }
 }
 
-/// Error type indicating that the read transaction failed to even start due to nesting.
+/// Error type indicating that the read transaction could not be nested inside of the transaction
+/// running on the current thread.
 pub struct TryReadErr {
     _private: (),
 }
@@ -212,7 +918,127 @@ impl TryReadErr {
     }
 }
 
-/// Error type indicating that the read-write transaction failed to even start due to nesting.
+/// A handle that can be used from any thread to wake up, and cancel, transactions started with
+/// [`ThreadKey::rw_cancellable`].
+///
+/// Cancellation is permanent. Every transaction that waits on a cancelled handle returns
+/// [`AwaitErr::Cancelled`] instead of parking.
+#[derive(Clone, Debug, Default)]
+pub struct CancelHandle {
+    cancel: Arc<Cancel>,
+}
+
+impl CancelHandle {
+    /// Creates a new handle that has not been cancelled.
+    #[inline]
+    pub fn new() -> Self {
+        CancelHandle::default()
+    }
+
+    /// Cancels all transactions using this handle that are, or will be, waiting on
+    /// [`Status::AWAIT_RETRY`].
+    #[inline]
+    pub fn cancel(&self) {
+        self.cancel.cancel()
+    }
+
+    /// Returns true if [`CancelHandle::cancel`] has been called.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+}
+
+/// Error type indicating that a read-write transaction gave up waiting for a change to its read
+/// set.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AwaitErr {
+    /// The deadline passed before the read set was modified.
+    TimedOut,
+
+    /// The [`CancelHandle`] of the transaction was cancelled.
+    Cancelled,
+}
+
+/// Details of how a transaction ran, returned by [`ThreadKey::rw_with_info`] and
+/// [`ThreadKey::read_with_info`].
+///
+/// Unlike [`stats`](crate::stats), the details are always collected, and describe a single call.
+/// The details of a nested transaction only cover the nested transaction, which is committed by
+/// the enclosing transaction. Its commit epoch is the epoch it read at.
+#[derive(Copy, Clone, Debug)]
+pub struct TxInfo {
+    pub(crate) eager_conflicts:    usize,
+    pub(crate) commit_conflicts:   usize,
+    pub(crate) htm_committed:      bool,
+    pub(crate) read_log_len:       usize,
+    pub(crate) write_log_word_len: usize,
+    pub(crate) parked:             bool,
+    pub(crate) commit_epoch:       QuiesceEpoch,
+}
+
+impl TxInfo {
+    /// Returns the number of attempts that conflicted before committing, while running the closure
+    /// of the transaction.
+    #[inline]
+    pub fn eager_conflicts(&self) -> usize {
+        self.eager_conflicts
+    }
+
+    /// Returns the number of attempts that ran the closure of the transaction to completion, and
+    /// then failed to commit.
+    #[inline]
+    pub fn commit_conflicts(&self) -> usize {
+        self.commit_conflicts
+    }
+
+    /// Returns the total number of attempts that conflicted.
+    #[inline]
+    pub fn conflicts(&self) -> usize {
+        self.eager_conflicts + self.commit_conflicts
+    }
+
+    /// Returns true if the writes of the transaction were published by a hardware transaction.
+    #[inline]
+    pub fn htm_committed(&self) -> bool {
+        self.htm_committed
+    }
+
+    /// Returns the number of `TCell`s in the read log of the attempt that committed.
+    ///
+    /// Read only transactions only log their reads after returning
+    /// [`AWAIT_RETRY`](crate::tx::Status::AWAIT_RETRY), or upgrading.
+    #[inline]
+    pub fn read_log_len(&self) -> usize {
+        self.read_log_len
+    }
+
+    /// Returns the size of the write log of the attempt that committed, in cpu words. Each write is
+    /// a minimum of 3 words.
+    #[inline]
+    pub fn write_log_word_len(&self) -> usize {
+        self.write_log_word_len
+    }
+
+    /// Returns true if the thread parked waiting for a change to the read set of an attempt that
+    /// returned [`AWAIT_RETRY`](crate::tx::Status::AWAIT_RETRY).
+    #[inline]
+    pub fn parked(&self) -> bool {
+        self.parked
+    }
+
+    /// Returns the epoch the transaction committed at.
+    ///
+    /// Epochs are logical timestamps, see
+    /// [`TxEvent::commit_epoch`](crate::observer::TxEvent::commit_epoch).
+    #[inline]
+    pub fn commit_epoch(&self) -> usize {
+        self.commit_epoch.get().get()
+    }
+}
+
+/// Error type indicating that the read-write transaction could not be nested inside of the
+/// transaction running on the current thread, or returned `AWAIT_RETRY` while nested.
 pub struct TryRwErr {
     _private: (),
 }
@@ -22,7 +22,8 @@
 //! write set change throughout the course of a transaction, and may be overwritten.
 //!
 //! `TPtr` is the current experimental workaround akin to a raw pointer, but capable of publishing
-//! and privatizing Box's.
+//! and privatizing Box's. Pointees that do not contain any `TCell`s can instead be stored in a
+//! [`TBox`](crate::tbox::TBox), which is safe.
 //!
 //! # EXPERIMENTAL
 //!
@@ -32,7 +33,10 @@ use crate::{
     tcell::TCell,
     tx::{Error, Ordering, Read, SetError, Write, _TValue},
 };
-use core::{mem, ptr};
+use core::{
+    mem::{self, ManuallyDrop},
+    ptr,
+};
 
 #[repr(transparent)]
 #[derive(Debug)]
@@ -167,19 +171,39 @@ impl<T: Send + Sync + 'static> TPtr<T> {
     ///
     /// If the transaction fails at any later point, the desctructor with the pulisher is run.
     /// Publishing No cleanup/deallocation of the previously container pointer is performed.
+    ///
+    /// Destructors that capture state are dropped without being called if the transaction
+    /// succeeds.
+    ///
+    /// Only zero sized destructors fit in the write log entry of the pointer. Other destructors
+    /// bypass the write log, and are registered as an abort hook instead, like
+    /// [`RwTx::on_abort`](crate::RwTx::on_abort). They run after the thread has left the
+    /// transaction, rather than as its writes are discarded.
     #[inline]
-    pub fn publish<'tcell, F: FnOnce(*mut T) + Copy + 'static>(
+    pub fn publish<'tcell, F: FnOnce(*mut T) + Send + 'static>(
         &'tcell self,
         tx: &mut impl Write<'tcell>,
         publisher: Publisher<T, F>,
     ) -> Result<(), SetError<Publisher<T, F>>> {
-        let destructor = publisher.destructor;
-        self.ptr.publish(tx, publisher).map_err(|err| {
-            err.map(move |ptr| Publisher {
-                ptr: ptr.into(),
-                destructor,
-            })
-        })
+        let ptr = publisher.ptr;
+        if mem::size_of::<F>() == 0 {
+            // The publisher fits in the write log entry of the pointer, where it is dropped if the
+            // transaction fails. A zero sized destructor can be duplicated by a bitwise copy.
+            let destructor = unsafe { ptr::read(&publisher.destructor) };
+            self.ptr
+                .publish(tx, publisher)
+                .map_err(|err| err.map(move |_| Publisher { ptr, destructor }))
+        } else {
+            match self.ptr.set(tx, ptr.into()) {
+                Ok(()) => {
+                    let destructor = publisher.into_destructor();
+                    let ptr = Ptr::from(ptr);
+                    tx._on_abort(move || destructor(ptr.into()));
+                    Ok(())
+                }
+                Err(err) => Err(err.map(move |_| publisher)),
+            }
+        }
     }
 
     /// Queues up drop_in_place/deallocation of the raw pointer to happen at some later time,
@@ -198,7 +222,7 @@ impl<T: Send + Sync + 'static> TPtr<T> {
     /// Queues up a custom desctructor to happen at some later time, if and only if the transaction
     /// succeeds.
     #[inline]
-    pub unsafe fn privatize<'tcell, F: FnOnce(*mut T) + Copy + Send + 'static>(
+    pub unsafe fn privatize<'tcell, F: FnOnce(*mut T) + Send + 'static>(
         tx: &mut impl Write<'tcell>,
         value: *const T,
         privatizer: F,
@@ -223,30 +247,34 @@ impl<T: Send + Sync + 'static> TPtr<T> {
 }
 
 #[derive(Debug)]
-pub struct Publisher<T, F: FnOnce(*mut T) + Copy + 'static> {
+pub struct Publisher<T, F: FnOnce(*mut T) + 'static> {
     ptr:        *mut T,
-    destructor: F,
+    destructor: ManuallyDrop<F>,
 }
 
-impl<T, F: FnOnce(*mut T) + Copy + 'static> Drop for Publisher<T, F> {
+impl<T, F: FnOnce(*mut T) + 'static> Drop for Publisher<T, F> {
     #[inline]
     fn drop(&mut self) {
-        (self.destructor)(self.ptr)
+        unsafe { ptr::read(&*self.destructor)(self.ptr) }
     }
 }
 
-impl<T: 'static, F: FnOnce(*mut T) + Copy + 'static> Publisher<T, F> {
+impl<T: 'static, F: FnOnce(*mut T) + 'static> Publisher<T, F> {
     #[inline]
     pub fn new(ptr: *mut T, destructor: F) -> Self {
-        assert_eq!(
-            mem::size_of::<F>(),
-            0,
-            "Publisher requires the destructor to be zero sized"
-        );
-        Publisher { ptr, destructor }
+        Publisher {
+            ptr,
+            destructor: ManuallyDrop::new(destructor),
+        }
+    }
+
+    #[inline]
+    fn into_destructor(self) -> F {
+        let this = ManuallyDrop::new(self);
+        unsafe { ptr::read(&*this.destructor) }
     }
 }
 
-unsafe impl<T: 'static, F: FnOnce(*mut T) + Copy + 'static> _TValue<Ptr<T>> for Publisher<T, F> {
+unsafe impl<T: 'static, F: FnOnce(*mut T) + 'static> _TValue<Ptr<T>> for Publisher<T, F> {
     const REQUEST_TCELL_LIFETIME: bool = true;
 }
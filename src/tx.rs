@@ -1,17 +1,15 @@
 //! Functionality for working with transactions.
 
-use crate::tcell::{Ref, TCell};
+use crate::{
+    diagnostics::{ConflictDetails, ConflictKind},
+    tcell::{Ref, TCell},
+};
 use core::{
     cell::UnsafeCell,
     fmt::{self, Debug, Formatter},
     ops::{Deref, DerefMut},
 };
 
-#[derive(PartialEq, Eq)]
-enum ErrorKind {
-    Conflict,
-}
-
 /// An error type indicating that the transaction has failed.
 ///
 /// It is typical to route this error back to [`ThreadKey::rw`] or [`ThreadKey::read`] where the
@@ -26,13 +24,23 @@ enum ErrorKind {
 /// [`ThreadKey::rw`]: ../thread_key/struct.ThreadKey.html#method.rw
 #[derive(PartialEq, Eq)]
 pub struct Error {
-    kind: ErrorKind,
+    kind:    ConflictKind,
+    details: Option<ConflictDetails>,
 }
 
 impl Debug for Error {
     #[cold]
     fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
-        formatter.pad("Error { .. }")
+        match self.details {
+            Some(details) => formatter
+                .debug_struct("Error")
+                .field("details", &details)
+                .finish(),
+            None => formatter
+                .debug_struct("Error")
+                .field("kind", &self.kind)
+                .finish(),
+        }
     }
 }
 
@@ -44,9 +52,39 @@ impl<T> From<SetError<T>> for Error {
 }
 
 impl Error {
-    pub(crate) const CONFLICT: Self = Error {
-        kind: ErrorKind::Conflict,
-    };
+    /// A conflict that is not attributed to any `TCell`.
+    #[inline]
+    pub(crate) const fn conflict(kind: ConflictKind) -> Self {
+        Error {
+            kind,
+            details: None,
+        }
+    }
+
+    /// A conflict caused by the `TCell` described by `details`.
+    #[inline]
+    pub(crate) fn with_details(details: ConflictDetails) -> Self {
+        Error {
+            kind:    details.kind(),
+            details: Some(details),
+        }
+    }
+
+    /// Returns why the transaction conflicted.
+    #[inline]
+    pub fn kind(&self) -> ConflictKind {
+        self.kind
+    }
+
+    /// Returns the details of the conflict, if [`diagnostics`](crate::diagnostics) were enabled
+    /// when it occurred.
+    ///
+    /// The details describe the `TCell` that caused this error, regardless of any conflicts that
+    /// have happened since.
+    #[inline]
+    pub fn details(&self) -> Option<ConflictDetails> {
+        self.details
+    }
 }
 
 /// An error type indicating that the transaction has failed to [`set`] a value.
@@ -153,6 +191,68 @@ impl Status {
     pub const AWAIT_RETRY: Self = Status {
         kind: InternalStatus::Retry,
     };
+
+    /// Creates an [`Abort`] which ends the transaction without committing, and returns `error` from
+    /// [`ThreadKey::rw_abortable`].
+    ///
+    /// [`ThreadKey::rw_abortable`]: ../thread_key/struct.ThreadKey.html#method.rw_abortable
+    #[inline]
+    pub fn abort<E>(error: E) -> Abort<E> {
+        Abort::new(error)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum AbortKind<E> {
+    Status(Status),
+    User(E),
+}
+
+/// A type representing that the transaction does not wish to continue, or that it wishes to abort
+/// with a user defined error.
+///
+/// Aborting discards everything the transaction has written, and returns the error to the caller
+/// of [`ThreadKey::rw_abortable`] without retrying. Any other `Status` is handled the same as it
+/// would be by [`ThreadKey::rw`].
+///
+/// [`ThreadKey::rw`]: ../thread_key/struct.ThreadKey.html#method.rw
+/// [`ThreadKey::rw_abortable`]: ../thread_key/struct.ThreadKey.html#method.rw_abortable
+#[derive(Debug, PartialEq, Eq)]
+pub struct Abort<E> {
+    pub(crate) kind: AbortKind<E>,
+}
+
+impl<E> From<Status> for Abort<E> {
+    #[inline]
+    fn from(rhs: Status) -> Self {
+        Abort {
+            kind: AbortKind::Status(rhs),
+        }
+    }
+}
+
+impl<E> From<Error> for Abort<E> {
+    #[inline]
+    fn from(rhs: Error) -> Self {
+        Status::from(rhs).into()
+    }
+}
+
+impl<T, E> From<SetError<T>> for Abort<E> {
+    #[inline]
+    fn from(set_error: SetError<T>) -> Self {
+        set_error.error.into()
+    }
+}
+
+impl<E> Abort<E> {
+    /// Creates an `Abort` which ends the transaction without committing, and returns `error`.
+    #[inline]
+    pub fn new(error: E) -> Self {
+        Abort {
+            kind: AbortKind::User(error),
+        }
+    }
 }
 
 /// Transactional memory orderings.
@@ -259,7 +359,10 @@ pub trait Write<'tcell> {
     ) -> Result<(), SetError<T>>;
 
     #[doc(hidden)]
-    fn _privatize<F: FnOnce() + Copy + Send + 'static>(&mut self, privatizer: F);
+    fn _privatize<F: FnOnce() + Send + 'static>(&mut self, privatizer: F);
+
+    #[doc(hidden)]
+    fn _on_abort<F: FnOnce() + Send + 'static>(&mut self, f: F);
 }
 
 /// Trait for types that represent transactions with the ability to read and write.
@@ -269,6 +372,9 @@ impl<'tcell, T: Read<'tcell> + Write<'tcell>> Rw<'tcell> for T {}
 #[doc(hidden)]
 pub unsafe trait _TValue<T: 'static>: 'static {
     const REQUEST_TCELL_LIFETIME: bool;
+
+    /// The previous value of the `TCell` has been moved out, and must not be dropped by the write.
+    const PRIVATIZES_PREVIOUS: bool = false;
 }
 unsafe impl<T: 'static> _TValue<T> for T {
     const REQUEST_TCELL_LIFETIME: bool = false;
@@ -0,0 +1,82 @@
+mod abort {
+    use std::sync::atomic::{AtomicIsize, Ordering::Relaxed};
+    use swym::{
+        tcell::TCell,
+        thread_key,
+        tx::{Abort, Ordering, Status},
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct UserErr(usize);
+
+    #[test]
+    fn discards_writes() {
+        static ALLOC_COUNT: AtomicIsize = AtomicIsize::new(0);
+
+        struct Counted(usize);
+        impl Counted {
+            fn new(x: usize) -> Self {
+                ALLOC_COUNT.fetch_add(1, Relaxed);
+                Counted(x)
+            }
+        }
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                ALLOC_COUNT.fetch_sub(1, Relaxed);
+            }
+        }
+
+        let x = TCell::new(Counted::new(0));
+        let thread_key = thread_key::get();
+        for i in 0..100 {
+            let result: Result<(), _> = thread_key.rw_abortable(|tx| {
+                x.set(tx, Counted::new(i + 1))?;
+                x.set(tx, Counted::new(i + 2))?;
+                Err(Abort::new(UserErr(i)))
+            });
+            assert_eq!(result, Err(UserErr(i)));
+            assert_eq!(
+                thread_key.read(|tx| Ok(x.borrow(tx, Ordering::default())?.0)),
+                0
+            );
+        }
+        assert_eq!(ALLOC_COUNT.load(Relaxed), 1);
+        drop(x);
+        assert_eq!(ALLOC_COUNT.load(Relaxed), 0);
+    }
+
+    #[test]
+    fn commit() {
+        let x = TCell::new(0);
+        let thread_key = thread_key::get();
+        let result = thread_key.rw_abortable::<_, _, UserErr>(|tx| {
+            x.set(tx, 42)?;
+            Ok(x.get(tx, Ordering::default())?)
+        });
+        assert_eq!(result, Ok(42));
+        assert_eq!(x.into_inner(), 42);
+    }
+
+    #[test]
+    fn nested() {
+        let x = TCell::new(0);
+        let y = TCell::new(0);
+        let thread_key = thread_key::get();
+        let result = thread_key.rw(|tx| {
+            x.set(tx, 1)?;
+            let inner: Result<(), _> = tx.nested_abortable(|tx| {
+                x.set(tx, 2)?;
+                y.set(tx, 2)?;
+                Err(Status::abort(UserErr(2)))
+            })?;
+            assert_eq!(inner, Err(UserErr(2)));
+            Ok((
+                x.get(tx, Ordering::default())?,
+                y.get(tx, Ordering::default())?,
+            ))
+        });
+        assert_eq!(result, (1, 0));
+        assert_eq!(x.into_inner(), 1);
+        assert_eq!(y.into_inner(), 0);
+    }
+}
@@ -0,0 +1,57 @@
+//! Fixtures shared by the integration tests.
+
+#![allow(dead_code)]
+
+use crossbeam_utils::thread;
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed},
+    Arc,
+};
+
+/// A value that counts its drops in `drops`.
+#[derive(Debug)]
+pub struct DropCount {
+    pub value: usize,
+    drops:     Arc<AtomicUsize>,
+}
+
+impl DropCount {
+    pub fn new(value: usize, drops: &Arc<AtomicUsize>) -> Self {
+        DropCount {
+            value,
+            drops: drops.clone(),
+        }
+    }
+}
+
+impl Drop for DropCount {
+    fn drop(&mut self) {
+        drop(self.drops.fetch_add(1, Relaxed))
+    }
+}
+
+/// Spins until `flag` is set.
+pub fn wait(flag: &AtomicBool) {
+    while !flag.load(Relaxed) {
+        std::thread::yield_now()
+    }
+}
+
+/// Runs `first` and `second` on two threads. `second` starts once `first` sets the first flag it is
+/// passed, and the second flag is set after `second` returns.
+pub fn interleave(
+    first: impl FnOnce(&AtomicBool, &AtomicBool) + Send,
+    second: impl FnOnce() + Send,
+) {
+    let started = AtomicBool::new(false);
+    let finished = AtomicBool::new(false);
+    thread::scope(|s| {
+        s.spawn(|_| first(&started, &finished));
+        s.spawn(|_| {
+            wait(&started);
+            second();
+            finished.store(true, Relaxed)
+        });
+    })
+    .unwrap();
+}
@@ -0,0 +1,159 @@
+mod contention {
+    use crossbeam_utils::thread;
+    use std::{
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed},
+            Arc,
+        },
+        time::Duration,
+    };
+    use swym::{
+        contention::{Backoff, Conflict, ContentionManager},
+        tcell::TCell,
+        thread_key,
+        tx::Ordering,
+    };
+
+    #[derive(Default)]
+    struct Counts {
+        conflicts:   AtomicUsize,
+        commits:     AtomicUsize,
+        starvations: AtomicUsize,
+    }
+
+    struct Counting {
+        counts:       Arc<Counts>,
+        max_attempts: u32,
+    }
+
+    impl ContentionManager for Counting {
+        fn on_conflict(&mut self, conflict: &Conflict) -> Backoff {
+            drop(self.counts.conflicts.fetch_add(1, Relaxed));
+            assert!(conflict.attempts() >= 1);
+            assert!(conflict.attempts() <= self.max_attempts);
+            if conflict.attempts() >= self.max_attempts {
+                Backoff::Irrevocable
+            } else {
+                Backoff::Yield
+            }
+        }
+
+        fn on_commit(&mut self) {
+            drop(self.counts.commits.fetch_add(1, Relaxed));
+        }
+
+        fn on_starvation(&mut self) {
+            drop(self.counts.starvations.fetch_add(1, Relaxed));
+        }
+    }
+
+    #[test]
+    fn contended() {
+        const ITER_COUNT: usize = 1_000;
+        const THREAD_COUNT: usize = 4;
+
+        let counts = Arc::new(Counts::default());
+        let x = TCell::new(0);
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                let counts = counts.clone();
+                let x = &x;
+                s.spawn(move |_| {
+                    let thread_key = thread_key::get();
+                    assert!(thread_key
+                        .set_contention_manager(Counting {
+                            counts,
+                            max_attempts: 3,
+                        })
+                        .is_none());
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| {
+                            let next = x.get(tx, Ordering::default())? + 1;
+                            Ok(x.set(tx, next)?)
+                        });
+                    }
+                    assert!(thread_key.take_contention_manager().is_some());
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(counts.commits.load(Relaxed), ITER_COUNT * THREAD_COUNT);
+        assert_eq!(x.into_inner(), ITER_COUNT * THREAD_COUNT);
+    }
+
+    #[test]
+    fn starvation() {
+        let counts = Arc::new(Counts::default());
+        let irrevocable = AtomicBool::new(false);
+        let x = TCell::new(0);
+        let y = TCell::new(0);
+        thread::scope(|s| {
+            s.spawn(|_| {
+                thread_key::get().rw_irrevocable(|tx| {
+                    x.set(tx, 1).unwrap();
+                    irrevocable.store(true, Relaxed);
+                    std::thread::sleep(Duration::from_millis(200));
+                })
+            });
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                drop(thread_key.set_contention_manager(Counting {
+                    counts:       counts.clone(),
+                    max_attempts: 1,
+                }));
+                while !irrevocable.load(Relaxed) {
+                    std::thread::yield_now()
+                }
+                thread_key.rw(|tx| Ok(y.set(tx, 1)?));
+                drop(thread_key.take_contention_manager());
+            });
+        })
+        .unwrap();
+        assert_eq!(counts.starvations.load(Relaxed), 1);
+        assert_eq!(counts.commits.load(Relaxed), 1);
+    }
+
+    struct SleepThenIrrevocable;
+
+    impl ContentionManager for SleepThenIrrevocable {
+        fn on_conflict(&mut self, conflict: &Conflict) -> Backoff {
+            if conflict.attempts() % 2 == 1 {
+                Backoff::Sleep(Duration::from_micros(100))
+            } else {
+                Backoff::Irrevocable
+            }
+        }
+    }
+
+    #[test]
+    fn nested() {
+        const ITER_COUNT: usize = 1_000;
+        const THREAD_COUNT: usize = 4;
+
+        let x = TCell::new(0);
+        let y = TCell::new(0);
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                let (x, y) = (&x, &y);
+                s.spawn(move |_| {
+                    let thread_key = thread_key::get();
+                    drop(thread_key.set_contention_manager(SleepThenIrrevocable));
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| {
+                            let next = x.get(tx, Ordering::default())? + 1;
+                            x.set(tx, next)?;
+                            tx.nested(|tx| {
+                                let next = y.get(tx, Ordering::default())? + 1;
+                                Ok(y.set(tx, next)?)
+                            })
+                        });
+                    }
+                    drop(thread_key.take_contention_manager());
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(x.into_inner(), ITER_COUNT * THREAD_COUNT);
+        assert_eq!(y.into_inner(), ITER_COUNT * THREAD_COUNT);
+    }
+}
@@ -0,0 +1,71 @@
+mod common;
+
+mod diagnostics {
+    use crate::common::{interleave, wait};
+    use std::sync::atomic::Ordering::Relaxed;
+    use swym::{
+        diagnostics::{self, ConflictKind},
+        tcell::TCell,
+        thread_key,
+        tx::Ordering,
+    };
+
+    #[test]
+    fn read_validation() {
+        static X: TCell<usize> = TCell::new(0);
+        diagnostics::enable();
+        diagnostics::set_name(&X, "x");
+
+        interleave(
+            |pinned, written| {
+                let mut first = true;
+                thread_key::get().read(|tx| {
+                    if first {
+                        first = false;
+                        pinned.store(true, Relaxed);
+                        wait(written);
+                        let error = X.get(tx, Ordering::default()).unwrap_err();
+                        assert_eq!(error.kind(), ConflictKind::ReadValidation);
+                        let details = error.details().unwrap();
+                        assert_eq!(details.kind(), ConflictKind::ReadValidation);
+                        assert_eq!(details.name(), Some("x"));
+                        assert_eq!(diagnostics::last_conflict(), Some(details));
+                        assert!(format!("{:?}", error).contains("\"x\""));
+                        return Err(error);
+                    }
+                    Ok(X.get(tx, Ordering::default())?)
+                });
+            },
+            || thread_key::get().rw(|tx| Ok(X.set(tx, 1)?)),
+        );
+    }
+
+    #[test]
+    fn commit_validation() {
+        diagnostics::enable();
+        let x = TCell::new(0);
+        let y = TCell::new(0);
+        diagnostics::set_name(&x, "x");
+
+        interleave(
+            |pinned, written| {
+                let mut first = true;
+                thread_key::get().rw(|tx| {
+                    let x_value = x.get(tx, Ordering::ReadWrite)?;
+                    if first {
+                        first = false;
+                        pinned.store(true, Relaxed);
+                        wait(written);
+                    }
+                    Ok(y.set(tx, x_value)?)
+                });
+                let details = diagnostics::last_conflict().unwrap();
+                assert_eq!(details.kind(), ConflictKind::CommitValidation);
+                assert_eq!(details.name(), Some("x"));
+            },
+            || thread_key::get().rw(|tx| Ok(x.set(tx, 1)?)),
+        );
+        diagnostics::clear_name(&x);
+        assert_eq!(y.into_inner(), 1);
+    }
+}
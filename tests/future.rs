@@ -0,0 +1,128 @@
+mod future {
+    use crossbeam_utils::thread;
+    use std::{
+        future::Future,
+        mem,
+        pin::Pin,
+        sync::{
+            atomic::{AtomicBool, Ordering::Relaxed},
+            Arc,
+        },
+        task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+        thread::Thread,
+    };
+    use swym::{future, tcell::TCell, thread_key, tx::Status};
+
+    struct ThreadWaker {
+        thread: Thread,
+        woken:  AtomicBool,
+    }
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop_waker);
+
+    unsafe fn clone(data: *const ()) -> RawWaker {
+        let thread_waker = Arc::from_raw(data as *const ThreadWaker);
+        let raw = RawWaker::new(Arc::into_raw(thread_waker.clone()) as *const (), &VTABLE);
+        mem::forget(thread_waker);
+        raw
+    }
+
+    unsafe fn wake(data: *const ()) {
+        wake_by_ref(data);
+        drop_waker(data)
+    }
+
+    unsafe fn wake_by_ref(data: *const ()) {
+        let thread_waker = &*(data as *const ThreadWaker);
+        thread_waker.woken.store(true, Relaxed);
+        thread_waker.thread.unpark()
+    }
+
+    unsafe fn drop_waker(data: *const ()) {
+        drop(Arc::from_raw(data as *const ThreadWaker))
+    }
+
+    fn thread_waker() -> (Arc<ThreadWaker>, Waker) {
+        let thread_waker = Arc::new(ThreadWaker {
+            thread: std::thread::current(),
+            woken:  AtomicBool::new(false),
+        });
+        let data = Arc::into_raw(thread_waker.clone()) as *const ();
+        let waker = unsafe { Waker::from_raw(RawWaker::new(data, &VTABLE)) };
+        (thread_waker, waker)
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let (thread_waker, waker) = thread_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(o) = future.as_mut().poll(&mut cx) {
+                return o;
+            }
+            while !thread_waker.woken.swap(false, Relaxed) {
+                std::thread::park()
+            }
+        }
+    }
+
+    #[test]
+    fn wake_on_commit() {
+        const ITER_COUNT: usize = 100;
+
+        let x = TCell::new(0);
+        let y = TCell::new(0);
+        thread::scope(|s| {
+            s.spawn(|_| {
+                for i in 1..=ITER_COUNT {
+                    let value = block_on(future::rw(|tx| {
+                        let value = x.get(tx, Default::default())?;
+                        if value < i {
+                            Err(Status::AWAIT_RETRY)
+                        } else {
+                            y.set(tx, value)?;
+                            Ok(value)
+                        }
+                    }));
+                    assert_eq!(value, i);
+                }
+            });
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for i in 1..=ITER_COUNT {
+                    thread_key.rw(|tx| {
+                        if y.get(tx, Default::default())? + 1 < i {
+                            Err(Status::AWAIT_RETRY)
+                        } else {
+                            Ok(x.set(tx, i)?)
+                        }
+                    });
+                }
+            });
+        })
+        .unwrap();
+        assert_eq!(y.into_inner(), ITER_COUNT);
+    }
+
+    #[test]
+    fn drop_pending() {
+        let x = TCell::new(false);
+        let (thread_waker, waker) = thread_waker();
+        let mut cx = Context::from_waker(&waker);
+        {
+            let mut pending = future::rw(|tx| {
+                if x.get(tx, Default::default())? {
+                    Ok(())
+                } else {
+                    Err(Status::AWAIT_RETRY)
+                }
+            });
+            assert_eq!(Pin::new(&mut pending).poll(&mut cx), Poll::Pending);
+        }
+        thread_key::get().rw(|tx| Ok(x.set(tx, true)?));
+        assert!(
+            !thread_waker.woken.load(Relaxed),
+            "dropped future was woken"
+        );
+    }
+}
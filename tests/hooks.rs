@@ -0,0 +1,160 @@
+mod hooks {
+    use crossbeam_utils::thread;
+    use std::{
+        panic::{self, AssertUnwindSafe},
+        sync::{
+            atomic::{AtomicIsize, AtomicUsize, Ordering::Relaxed},
+            Arc,
+        },
+    };
+    use swym::{
+        tcell::TCell,
+        thread_key,
+        tx::{Ordering, Status},
+    };
+
+    #[test]
+    fn commit_and_abort() {
+        let commits = AtomicUsize::new(0);
+        let aborts = AtomicUsize::new(0);
+        let x = TCell::new(0);
+
+        let thread_key = thread_key::get();
+        thread_key.rw(|tx| {
+            tx.on_commit(|| drop(commits.fetch_add(1, Relaxed)));
+            tx.on_abort(|| drop(aborts.fetch_add(1, Relaxed)));
+            Ok(x.set(tx, 1)?)
+        });
+        assert_eq!(commits.load(Relaxed), 1);
+        assert_eq!(aborts.load(Relaxed), 0);
+
+        let result: Result<(), _> = thread_key.rw_abortable(|tx| {
+            tx.on_commit(|| drop(commits.fetch_add(1, Relaxed)));
+            tx.on_abort(|| drop(aborts.fetch_add(1, Relaxed)));
+            x.set(tx, 2)?;
+            Err(Status::abort(()))
+        });
+        assert_eq!(result, Err(()));
+        assert_eq!(commits.load(Relaxed), 1);
+        assert_eq!(aborts.load(Relaxed), 1);
+
+        let panicked = panic::catch_unwind(AssertUnwindSafe(|| {
+            thread_key.rw(|tx| -> Result<(), Status> {
+                tx.on_commit(|| drop(commits.fetch_add(1, Relaxed)));
+                tx.on_abort(|| drop(aborts.fetch_add(1, Relaxed)));
+                panic!("test panic")
+            })
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(commits.load(Relaxed), 1);
+        assert_eq!(aborts.load(Relaxed), 2);
+        assert_eq!(x.into_inner(), 1);
+    }
+
+    #[test]
+    fn nested() {
+        let log = parking_lot::Mutex::new(Vec::new());
+        let thread_key = thread_key::get();
+        thread_key.rw(|tx| {
+            log.lock().clear();
+            tx.on_commit(|| log.lock().push("outer commit"));
+            let inner: Result<(), _> = tx.nested_abortable(|tx| {
+                tx.on_commit(|| log.lock().push("inner commit"));
+                tx.on_abort(|| log.lock().push("inner abort"));
+                Err(Status::abort(()))
+            })?;
+            assert_eq!(inner, Err(()));
+            tx.nested(|tx| {
+                tx.on_commit(|| log.lock().push("second inner commit"));
+                Ok(())
+            })
+        });
+        assert_eq!(
+            *log.lock(),
+            ["inner abort", "outer commit", "second inner commit"]
+        );
+    }
+
+    #[test]
+    fn transaction_in_commit_hook() {
+        let x = TCell::new(0);
+        let thread_key = thread_key::get();
+        thread_key.rw(|tx| {
+            x.set(tx, 1)?;
+            tx.on_commit(|| {
+                let thread_key = thread_key::get();
+                thread_key.rw(|tx| {
+                    let next = x.get(tx, Ordering::default())? + 1;
+                    Ok(x.set(tx, next)?)
+                })
+            });
+            Ok(())
+        });
+        assert_eq!(x.into_inner(), 2);
+    }
+
+    #[test]
+    fn transaction_in_abort_hook() {
+        let x = TCell::new(0);
+        let thread_key = thread_key::get();
+        let result: Result<(), _> = thread_key.rw_abortable(|tx| {
+            x.set(tx, 1)?;
+            tx.on_abort(|| {
+                let thread_key = thread_key::get();
+                thread_key.rw(|tx| Ok(x.set(tx, 2)?))
+            });
+            Err(Status::abort(()))
+        });
+        assert_eq!(result, Err(()));
+        assert_eq!(x.into_inner(), 2);
+    }
+
+    #[test]
+    fn contended() {
+        const ITER_COUNT: usize = 10_000;
+        const THREAD_COUNT: usize = 4;
+        static ALLOC_COUNT: AtomicIsize = AtomicIsize::new(0);
+
+        struct Counted(Arc<AtomicUsize>);
+        impl Counted {
+            fn new(count: &Arc<AtomicUsize>) -> Self {
+                ALLOC_COUNT.fetch_add(1, Relaxed);
+                Counted(count.clone())
+            }
+        }
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                ALLOC_COUNT.fetch_sub(1, Relaxed);
+            }
+        }
+
+        let commits = Arc::new(AtomicUsize::new(0));
+        let x = TCell::new(0);
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| {
+                            let on_commit = Counted::new(&commits);
+                            let on_abort = Counted::new(&commits);
+                            tx.on_commit(move || drop(on_commit.0.fetch_add(1, Relaxed)));
+                            let x = &x;
+                            tx.on_abort(move || {
+                                drop(on_abort);
+                                // aborted attempts are followed by another attempt
+                                thread_key::get().read(|tx| Ok(x.get(tx, Ordering::default())?));
+                            });
+                            let next = x.get(tx, Ordering::default())? + 1;
+                            Ok(x.set(tx, next)?)
+                        });
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(commits.load(Relaxed), ITER_COUNT * THREAD_COUNT);
+        assert_eq!(x.into_inner(), ITER_COUNT * THREAD_COUNT);
+        assert_eq!(ALLOC_COUNT.load(Relaxed), 0);
+    }
+}
@@ -0,0 +1,70 @@
+mod irrevocable {
+    use crossbeam_utils::thread;
+    use std::{
+        panic::{self, AssertUnwindSafe},
+        sync::atomic::{AtomicUsize, Ordering::Relaxed},
+    };
+    use swym::{tcell::TCell, thread_key, tx::Ordering};
+
+    #[test]
+    fn contended() {
+        const ITER_COUNT: usize = 1_000;
+        const THREAD_COUNT: usize = 4;
+
+        let calls = AtomicUsize::new(0);
+        let x = TCell::new(0);
+        let y = TCell::new(0);
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| {
+                            let next = x.get(tx, Ordering::default())? + 1;
+                            x.set(tx, next)?;
+                            Ok(y.set(tx, next)?)
+                        });
+                    }
+                });
+            }
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for _ in 0..ITER_COUNT {
+                    thread_key.rw_irrevocable(|tx| {
+                        drop(calls.fetch_add(1, Relaxed));
+                        let next = x.get(tx, Ordering::default()).unwrap() + 1;
+                        assert_eq!(y.get(tx, Ordering::default()).unwrap(), next - 1);
+                        x.set(tx, next).unwrap();
+                        y.set(tx, next).unwrap()
+                    });
+                }
+            });
+        })
+        .unwrap();
+        assert_eq!(calls.load(Relaxed), ITER_COUNT);
+        assert_eq!(x.into_inner(), ITER_COUNT * (THREAD_COUNT + 1));
+        assert_eq!(y.into_inner(), ITER_COUNT * (THREAD_COUNT + 1));
+    }
+
+    #[test]
+    fn panic_discards_writes() {
+        let x = TCell::new(String::from("unchanged"));
+        let thread_key = thread_key::get();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            thread_key.rw_irrevocable(|tx| {
+                x.set(tx, String::from("changed")).unwrap();
+                panic!("test panic")
+            })
+        }));
+        assert!(result.is_err());
+        thread_key.rw(|tx| Ok(x.set(tx, String::from("rw"))?));
+        assert_eq!(x.into_inner(), "rw");
+    }
+
+    #[test]
+    #[should_panic]
+    fn nested() {
+        let thread_key = thread_key::get();
+        thread_key.rw(|_| Ok(thread_key.rw_irrevocable(|_| ())));
+    }
+}
@@ -0,0 +1,225 @@
+mod nested {
+    use crossbeam_utils::thread;
+    use swym::{
+        tcell::TCell,
+        thread_key,
+        tx::{Ordering, Status},
+        RwTx,
+    };
+
+    #[test]
+    fn writes_visible() {
+        let x = TCell::new("outer".to_owned());
+        let y = TCell::new(0);
+        let thread_key = thread_key::get();
+        thread_key.rw(|tx| {
+            x.set(tx, "set by outer".to_owned())?;
+            tx.nested(|tx| {
+                assert_eq!(*x.borrow(tx, Ordering::default())?, "set by outer");
+                x.set(tx, "set by inner".to_owned())?;
+                y.set(tx, 1)?;
+                Ok(())
+            })?;
+            assert_eq!(*x.borrow(tx, Ordering::default())?, "set by inner");
+            assert_eq!(y.get(tx, Ordering::default())?, 1);
+            y.set(tx, 2)?;
+            Ok(())
+        });
+        assert_eq!(x.into_inner(), "set by inner");
+        assert_eq!(y.into_inner(), 2);
+    }
+
+    #[test]
+    fn deep() {
+        const DEPTH: usize = 16;
+
+        fn recurse<'tcell>(
+            tx: &mut RwTx<'tcell>,
+            depth: usize,
+            x: &'tcell TCell<Vec<usize>>,
+        ) -> Result<Vec<usize>, Status> {
+            tx.nested(|tx| {
+                let mut v = x.borrow(tx, Ordering::default())?.clone();
+                v.push(depth);
+                x.set(tx, v)?;
+                if depth < DEPTH {
+                    recurse(tx, depth + 1, x)?;
+                }
+                Ok(x.borrow(tx, Ordering::default())?.clone())
+            })
+        }
+
+        let x = TCell::new(Vec::new());
+        let expected = (0..=DEPTH).collect::<Vec<_>>();
+        assert_eq!(thread_key::get().rw(|tx| recurse(tx, 0, &x)), expected);
+        assert_eq!(x.into_inner(), expected);
+    }
+
+    #[test]
+    fn contended() {
+        const ITER_COUNT: usize = 10_000;
+        const THREAD_COUNT: usize = 4;
+
+        let outer = TCell::new(0);
+        let inner = TCell::new(String::new());
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| {
+                            let next = outer.get(tx, Ordering::default())? + 1;
+                            outer.set(tx, next)?;
+                            tx.nested(|tx| {
+                                let mut s = inner.borrow(tx, Ordering::default())?.clone();
+                                s.push('a');
+                                inner.set(tx, s)?;
+                                Ok(())
+                            })
+                        });
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(outer.into_inner(), ITER_COUNT * THREAD_COUNT);
+        assert_eq!(inner.into_inner().len(), ITER_COUNT * THREAD_COUNT);
+    }
+
+    #[test]
+    fn await_retry() {
+        let x = TCell::new(0);
+        let mut attempts = 0;
+        let result = thread_key::get().rw(|tx| {
+            attempts += 1;
+            x.set(tx, 1)?;
+            let r = tx.nested(|tx| -> Result<(), _> {
+                x.set(tx, 2)?;
+                Err(Status::AWAIT_RETRY)
+            });
+            assert!(r.is_err());
+            // ignoring the retry behaves as if the nested transaction never ran
+            Ok(x.get(tx, Ordering::default())?)
+        });
+        assert_eq!(result, 1);
+        assert_eq!(attempts, 1);
+        assert_eq!(x.into_inner(), 1);
+    }
+
+    fn transfer(from: &TCell<usize>, to: &TCell<usize>, amount: usize) -> bool {
+        thread_key::get().rw(|tx| {
+            let balance = from.get(tx, Ordering::default())?;
+            if balance < amount {
+                return Ok(false);
+            }
+            from.set(tx, balance - amount)?;
+            to.set(tx, to.get(tx, Ordering::default())? + amount)?;
+            Ok(true)
+        })
+    }
+
+    #[test]
+    fn thread_key_composes() {
+        let a = TCell::new(10);
+        let b = TCell::new(0);
+        let c = TCell::new(0);
+        let thread_key = thread_key::get();
+        thread_key.rw(|_| {
+            assert!(transfer(&a, &b, 7));
+            assert!(transfer(&b, &c, 5));
+            assert!(!transfer(&a, &c, 5));
+            Ok(())
+        });
+        let balances = thread_key.read(|tx| {
+            Ok((
+                a.get(tx, Ordering::default())?,
+                b.get(tx, Ordering::default())?,
+                c.get(tx, Ordering::default())?,
+            ))
+        });
+        assert_eq!(balances, (3, 2, 5));
+    }
+
+    #[test]
+    fn thread_key_abort() {
+        let a = TCell::new(10);
+        let b = TCell::new(0);
+        let thread_key = thread_key::get();
+        let result = thread_key.rw_abortable(|_| -> Result<(), _> {
+            assert!(transfer(&a, &b, 7));
+            Err(Status::abort("rolled back"))
+        });
+        assert_eq!(result, Err("rolled back"));
+        thread_key.rw(|tx| {
+            assert!(transfer(&a, &b, 1));
+            let nested = thread_key.rw_abortable(|tx| -> Result<(), _> {
+                b.set(tx, 100)?;
+                Err(Status::abort(()))
+            });
+            assert_eq!(nested, Err(()));
+            assert_eq!(b.get(tx, Ordering::default())?, 1);
+            Ok(())
+        });
+        assert_eq!(a.into_inner(), 9);
+        assert_eq!(b.into_inner(), 1);
+    }
+
+    #[test]
+    fn thread_key_contended() {
+        const ITER_COUNT: usize = 10_000;
+        const THREAD_COUNT: usize = 4;
+        const TOTAL: usize = 100;
+
+        let a = TCell::new(TOTAL);
+        let b = TCell::new(0);
+        thread::scope(|s| {
+            for i in 0..THREAD_COUNT {
+                let (a, b) = (&a, &b);
+                s.spawn(move |_| {
+                    let thread_key = thread_key::get();
+                    let (from, to) = if i % 2 == 0 { (a, b) } else { (b, a) };
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| {
+                            transfer(from, to, 1);
+                            transfer(from, to, 2);
+                            let sum = thread_key.read(|tx| {
+                                Ok(a.get(tx, Ordering::default())?
+                                    + b.get(tx, Ordering::default())?)
+                            });
+                            assert_eq!(sum, TOTAL, "nested transactions saw inconsistent state");
+                            Ok(a.get(tx, Ordering::default())? + b.get(tx, Ordering::default())?)
+                        });
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(a.into_inner() + b.into_inner(), TOTAL);
+    }
+
+    #[test]
+    fn thread_key_await_retry() {
+        let ready = TCell::new(false);
+        let x = TCell::new(0);
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                thread_key.rw(|tx| {
+                    x.set(tx, 1)?;
+                    let ready = thread_key.try_rw(|tx| {
+                        if ready.get(tx, Ordering::default())? {
+                            Ok(())
+                        } else {
+                            Err(Status::AWAIT_RETRY)
+                        }
+                    });
+                    // the root transaction waits on the read set of the nested transaction
+                    Ok(ready.is_ok())
+                });
+            });
+            thread_key::get().rw(|tx| Ok(ready.set(tx, true)?));
+        })
+        .unwrap();
+        assert_eq!(x.into_inner(), 1);
+    }
+}
@@ -0,0 +1,187 @@
+mod common;
+
+mod observer {
+    use crate::common::{interleave, wait};
+    use std::{
+        cell::RefCell,
+        panic::{self, AssertUnwindSafe},
+        rc::Rc,
+        sync::atomic::Ordering::Relaxed,
+        time::Instant,
+    };
+    use swym::{
+        observer::{TxEvent, TxKind, TxObserver},
+        tcell::TCell,
+        thread_key,
+        tx::{Ordering, Status},
+    };
+
+    #[derive(Clone, Default)]
+    struct Recorder {
+        events: Rc<RefCell<Vec<(&'static str, TxEvent)>>>,
+    }
+
+    impl Recorder {
+        fn record(&self, name: &'static str, event: &TxEvent) {
+            self.events.borrow_mut().push((name, *event))
+        }
+
+        fn names(&self) -> Vec<&'static str> {
+            self.events.borrow().iter().map(|&(name, _)| name).collect()
+        }
+
+        fn last(&self) -> TxEvent {
+            self.events.borrow().last().unwrap().1
+        }
+    }
+
+    impl TxObserver for Recorder {
+        fn on_begin(&self, event: &TxEvent) {
+            self.record("begin", event)
+        }
+
+        fn on_conflict(&self, event: &TxEvent) {
+            self.record("conflict", event)
+        }
+
+        fn on_commit(&self, event: &TxEvent) {
+            self.record("commit", event)
+        }
+
+        fn on_abort(&self, event: &TxEvent) {
+            self.record("abort", event)
+        }
+
+        fn on_park(&self, event: &TxEvent) {
+            self.record("park", event)
+        }
+
+        fn on_unpark(&self, event: &TxEvent) {
+            self.record("unpark", event)
+        }
+    }
+
+    #[test]
+    fn commit() {
+        let x = TCell::new(0);
+        let y = TCell::new(0);
+        let recorder = Recorder::default();
+        let thread_key = thread_key::get();
+        assert!(thread_key.set_observer(recorder.clone()).is_none());
+
+        thread_key.rw(|tx| {
+            let x_value = x.get(tx, Ordering::ReadWrite)?;
+            x.set(tx, x_value + 1)?;
+            Ok(y.set(tx, x_value + 1)?)
+        });
+        assert_eq!(recorder.names(), ["begin", "commit"]);
+        let commit = recorder.last();
+        assert_eq!(commit.kind(), TxKind::Rw);
+        assert_eq!(commit.attempt(), 1);
+        assert_eq!(commit.read_set_size(), 1);
+        assert_eq!(commit.write_set_size(), 2);
+        let first_epoch = commit.commit_epoch().unwrap();
+
+        thread_key.read(|tx| Ok(x.get(tx, Ordering::default())?));
+        assert_eq!(recorder.last().kind(), TxKind::Read);
+
+        thread_key.rw(|tx| Ok(x.set(tx, 2)?));
+        assert!(recorder.last().commit_epoch().unwrap() > first_epoch);
+
+        assert!(thread_key.take_observer().is_some());
+        thread_key.rw(|tx| Ok(x.set(tx, 3)?));
+        assert_eq!(recorder.names().len(), 6);
+    }
+
+    #[test]
+    fn conflict() {
+        let x = TCell::new(0);
+        let y = TCell::new(0);
+        interleave(
+            |pinned, written| {
+                let recorder = Recorder::default();
+                let thread_key = thread_key::get();
+                drop(thread_key.set_observer(recorder.clone()));
+                let mut first = true;
+                thread_key.rw(|tx| {
+                    let x_value = x.get(tx, Ordering::ReadWrite)?;
+                    if first {
+                        first = false;
+                        pinned.store(true, Relaxed);
+                        wait(written);
+                    }
+                    Ok(y.set(tx, x_value)?)
+                });
+                drop(thread_key.take_observer());
+                assert_eq!(recorder.names(), ["begin", "conflict", "begin", "commit"]);
+                assert_eq!(recorder.last().attempt(), 2);
+            },
+            || thread_key::get().rw(|tx| Ok(x.set(tx, 1)?)),
+        );
+        assert_eq!(y.into_inner(), 1);
+    }
+
+    #[test]
+    fn park() {
+        let flag = TCell::new(false);
+        interleave(
+            |parked, _| {
+                let recorder = Recorder::default();
+                let thread_key = thread_key::get();
+                drop(thread_key.set_observer(recorder.clone()));
+                thread_key.rw(|tx| {
+                    if flag.get(tx, Ordering::default())? {
+                        Ok(())
+                    } else {
+                        parked.store(true, Relaxed);
+                        Err(Status::AWAIT_RETRY)
+                    }
+                });
+                drop(thread_key.take_observer());
+                assert_eq!(
+                    recorder.names(),
+                    ["begin", "park", "unpark", "begin", "commit"]
+                );
+                assert_eq!(recorder.events.borrow()[1].1.read_set_size(), 1);
+            },
+            || thread_key::get().rw(|tx| Ok(flag.set(tx, true)?)),
+        );
+    }
+
+    #[test]
+    fn abort() {
+        let x = TCell::new(0);
+        let recorder = Recorder::default();
+        let thread_key = thread_key::get();
+        drop(thread_key.set_observer(recorder.clone()));
+
+        let result = thread_key.rw_abortable(|tx| {
+            x.set(tx, 1)?;
+            Err::<(), _>(Status::abort("nope"))
+        });
+        assert_eq!(result, Err("nope"));
+        assert_eq!(recorder.names(), ["begin", "abort"]);
+        assert_eq!(recorder.last().kind(), TxKind::Rw);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            thread_key.read(|tx| -> Result<(), _> {
+                x.get(tx, Ordering::default())?;
+                panic!("oops")
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(recorder.names()[2..], ["begin", "abort"]);
+        assert_eq!(recorder.last().kind(), TxKind::Read);
+
+        let result = thread_key.rw_timeout(Instant::now(), |tx| {
+            x.get(tx, Ordering::default())?;
+            Err::<(), _>(Status::AWAIT_RETRY)
+        });
+        assert!(result.is_err());
+        assert_eq!(recorder.names()[4..], ["begin", "park", "unpark", "abort"]);
+
+        thread_key.rw(|tx| Ok(x.set(tx, 1)?));
+        drop(thread_key.take_observer());
+        assert_eq!(recorder.names()[8..], ["begin", "commit"]);
+    }
+}
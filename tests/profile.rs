@@ -0,0 +1,99 @@
+mod profile {
+    use crossbeam_utils::thread;
+    use std::{
+        sync::atomic::{AtomicBool, Ordering::Relaxed},
+        time::Duration,
+    };
+    use swym::{diagnostics, profile, tcell::TCell, thread_key, tx::Status};
+
+    const SHARD_COUNT: usize = 4;
+
+    #[test]
+    fn hot_spots() {
+        let shards: Vec<_> = (0..SHARD_COUNT).map(|_| TCell::new(0)).collect();
+        let flag = TCell::new(false);
+        for shard in &shards {
+            diagnostics::set_name(shard, "shards");
+        }
+        profile::enable();
+
+        // every shard conflicts once
+        for shard in &shards {
+            let pinned = AtomicBool::new(false);
+            let written = AtomicBool::new(false);
+            thread::scope(|s| {
+                s.spawn(|_| {
+                    let mut first = true;
+                    thread_key::get().read(|tx| {
+                        if first {
+                            first = false;
+                            pinned.store(true, Relaxed);
+                            while !written.load(Relaxed) {
+                                std::thread::yield_now()
+                            }
+                        }
+                        Ok(shard.get(tx, Default::default())?)
+                    })
+                });
+                s.spawn(|_| {
+                    while !pinned.load(Relaxed) {
+                        std::thread::yield_now()
+                    }
+                    thread_key::get().rw(|tx| Ok(shard.set(tx, 1)?));
+                    written.store(true, Relaxed);
+                });
+            })
+            .unwrap();
+        }
+
+        let flag_address = &flag as *const _ as usize;
+        let flag_hot_spot = || {
+            profile::hot_spots(usize::max_value())
+                .into_iter()
+                .find(|hot_spot| {
+                    hot_spot.address().map_or(false, |address| {
+                        address >= flag_address
+                            && address < flag_address + std::mem::size_of_val(&flag)
+                    })
+                })
+        };
+
+        // `flag` is only set once a thread has parked waiting on it
+        thread::scope(|s| {
+            s.spawn(|_| {
+                thread_key::get().rw(|tx| {
+                    if flag.get(tx, Default::default())? {
+                        Ok(())
+                    } else {
+                        Err(Status::AWAIT_RETRY)
+                    }
+                })
+            });
+            while flag_hot_spot().is_none() {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            thread_key::get().rw(|tx| Ok(flag.set(tx, true)?));
+        })
+        .unwrap();
+        profile::disable();
+
+        let sharded = profile::hot_spots(usize::max_value())
+            .into_iter()
+            .find(|hot_spot| hot_spot.name() == Some("shards"))
+            .unwrap();
+        assert_eq!(sharded.cells(), SHARD_COUNT);
+        assert_eq!(sharded.address(), None);
+        assert!(sharded.conflicts() >= SHARD_COUNT as u64);
+
+        let waited = flag_hot_spot().unwrap();
+        assert_eq!(waited.cells(), 1);
+        assert!(waited.parked() >= 1);
+        profile::print_hot_spots(10);
+
+        for shard in &shards {
+            diagnostics::clear_name(shard);
+        }
+        profile::reset();
+        assert!(profile::hot_spots(10).is_empty());
+    }
+}
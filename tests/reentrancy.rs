@@ -1,6 +1,5 @@
 mod reentrancy {
     use crossbeam_utils::thread;
-    use std::cell::Cell;
     use swym::{tcell::TCell, thread_key};
 
     #[test]
@@ -8,20 +7,17 @@ mod reentrancy {
         const THREAD_COUNT: usize = 4;
         const ITER_COUNT: usize = 10_000;
 
-        thread_local! {
-            static THREAD_END: Cell<bool> = Cell::new(false);
-        }
-
         struct TxOnDrop(String, [usize; 64]);
         impl Drop for TxOnDrop {
             fn drop(&mut self) {
-                let x = TCell::new(([0usize; 128], "hello there".to_owned()));
+                // Destructors run by the closure of a transaction are nested inside of it, and
+                // those run while committing fail to start a transaction.
+                static X: TCell<([usize; 128], String)> = TCell::new(([0; 128], String::new()));
                 for _ in 0..128 {
-                    let tx_result = thread_key::get().try_rw(|tx| {
-                        x.set(tx, ([0; 128], "hello there".to_owned()))?;
+                    drop(thread_key::get().try_rw(|tx| {
+                        X.set(tx, ([0; 128], "hello there".to_owned()))?;
                         Ok(())
-                    });
-                    assert!(tx_result.is_err() || THREAD_END.try_with(|b| b.get()).unwrap_or(true));
+                    }));
                 }
             }
         }
@@ -39,7 +35,6 @@ mod reentrancy {
                                 })
                                 .unwrap()
                         }
-                        THREAD_END.with(|b| b.set(true));
                     });
                 }
             })
@@ -0,0 +1,99 @@
+mod snapshot {
+    use crossbeam_utils::thread;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed};
+    use swym::{tcell::TCell, thread_key, tx::Ordering};
+
+    const CELL_COUNT: usize = 64;
+
+    #[test]
+    fn long_scan() {
+        const SNAPSHOT_COUNT: usize = 10;
+        const WRITER_COUNT: usize = 2;
+
+        let cells: Vec<_> = (0..CELL_COUNT).map(|_| TCell::new(0)).collect();
+        let done = AtomicBool::new(false);
+        let attempts = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for _ in 0..WRITER_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    while !done.load(Relaxed) {
+                        thread_key.rw(|tx| {
+                            for cell in &cells {
+                                let next = cell.get(tx, Ordering::default())? + 1;
+                                cell.set(tx, next)?;
+                            }
+                            Ok(())
+                        });
+                    }
+                });
+            }
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for _ in 0..SNAPSHOT_COUNT {
+                    thread_key.read_snapshot(|tx| {
+                        drop(attempts.fetch_add(1, Relaxed));
+                        let first = cells[0].get(tx, Ordering::default())?;
+                        for cell in &cells {
+                            // Give writers a chance to overwrite the rest of the cells.
+                            std::thread::yield_now();
+                            assert_eq!(cell.get(tx, Ordering::default())?, first);
+                        }
+                        Ok(())
+                    });
+                }
+                done.store(true, Relaxed);
+            });
+        })
+        .unwrap();
+        assert!(
+            attempts.load(Relaxed) < SNAPSHOT_COUNT * 10,
+            "snapshot transactions restarted too often"
+        );
+    }
+
+    #[test]
+    fn drop_values() {
+        let cells: Vec<_> = (0..CELL_COUNT)
+            .map(|_| TCell::new(String::from("0")))
+            .collect();
+        let done = AtomicBool::new(false);
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                let mut i = 0;
+                while !done.load(Relaxed) {
+                    i += 1;
+                    thread_key.rw(|tx| {
+                        for cell in &cells {
+                            cell.set(tx, i.to_string())?;
+                        }
+                        Ok(())
+                    });
+                }
+            });
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for _ in 0..10 {
+                    thread_key.read_snapshot(|tx| {
+                        let first = cells[0].borrow(tx, Ordering::default())?.clone();
+                        for cell in &cells {
+                            std::thread::yield_now();
+                            assert_eq!(*cell.borrow(tx, Ordering::default())?, first);
+                        }
+                        Ok(())
+                    });
+                }
+                done.store(true, Relaxed);
+            });
+        })
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn nested() {
+        let thread_key = thread_key::get();
+        thread_key.read(|_| Ok(thread_key.read_snapshot(|_| Ok(()))));
+    }
+}
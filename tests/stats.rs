@@ -0,0 +1,115 @@
+mod common;
+
+mod stats {
+    use crate::common::{interleave, wait};
+    use std::sync::atomic::Ordering::Relaxed;
+    use swym::{stats, tcell::TCell, thread_key};
+
+    #[test]
+    fn export() {
+        let stats = stats::Stats::default();
+
+        let json = stats.to_json();
+        assert!(json.starts_with('{') && json.ends_with('}'));
+        assert!(json.contains("\"bloom_check\":{\"count\":0}"));
+        assert!(json.contains("\"read_size\":{\"count\":0,\"min\":null,"));
+        assert!(json.contains("\"transactions\":0,\"conflict_avg\":null"));
+
+        let prometheus = stats.to_prometheus();
+        assert!(prometheus.contains("# TYPE swym_bloom_check_total counter\n"));
+        assert!(prometheus.contains("\nswym_bloom_check_total 0\n"));
+        assert!(prometheus.contains("# TYPE swym_read_size summary\nswym_read_size_sum 0\n"));
+        assert!(prometheus.contains("swym_conflict_avg NaN\n"));
+    }
+
+    #[test]
+    fn export_recorded() {
+        let x = TCell::new(0);
+        let kinds = stats::Kind::READ_AFTER_WRITE | stats::Kind::READ_CLOSURE_TIME;
+        stats::enable(kinds);
+
+        let thread_key = thread_key::get();
+        thread_key.rw(|tx| {
+            x.set(tx, 1)?;
+            Ok(x.get(tx, Default::default())?)
+        });
+        thread_key.read(|tx| Ok(x.get(tx, Default::default())?));
+        let stats = thread_key.stats();
+        stats::disable(kinds);
+
+        let json = stats.to_json();
+        assert!(json.contains("\"read_after_write\":{\"count\":1}"));
+        assert!(json.contains("\"read_closure_time\":{\"count\":1,\"min\":"));
+
+        let prometheus = stats.to_prometheus();
+        assert!(prometheus.contains("\nswym_read_after_write_total 1\n"));
+        assert!(prometheus.contains("# TYPE swym_read_closure_time_seconds summary\n"));
+        assert!(prometheus.contains("\nswym_read_closure_time_seconds_count 1\n"));
+        // one attempt at a trivial closure takes well under a second
+        let sum = prometheus
+            .lines()
+            .find(|line| line.starts_with("swym_read_closure_time_seconds_sum "))
+            .unwrap();
+        let seconds: f64 = sum.rsplit(' ').next().unwrap().parse().unwrap();
+        assert!(seconds < 1.0);
+        assert!(prometheus.contains("# TYPE swym_read_closure_time_max_seconds gauge\n"));
+    }
+
+    #[test]
+    fn take_from_live_thread() {
+        let x = TCell::new(0);
+        let kinds = stats::Kind::WRITE_TRANSACTION_EAGER_CONFLICTS
+            | stats::Kind::WRITE_WORD_SIZE
+            | stats::Kind::RW_CLOSURE_TIME;
+        stats::enable(kinds);
+
+        interleave(
+            |committed, taken| {
+                let thread_key = thread_key::get();
+                thread_key.rw(|tx| Ok(x.set(tx, 1)?));
+                thread_key.rw_named("x::set", |tx| Ok(x.set(tx, 2)?));
+                let thread_stats = thread_key.stats();
+                assert_eq!(thread_stats.rw_closure_time.count(), 2);
+                assert_eq!(thread_stats.read_closure_time.count(), 0);
+                let named = thread_stats.named("x::set").unwrap();
+                assert_eq!(named.transactions(), 1);
+                assert_eq!(named.rw_closure_time.count(), 1);
+                assert!(named.named("x::set").is_none());
+                committed.store(true, Relaxed);
+                // stay alive, without flushing, until the stats have been taken
+                wait(taken);
+            },
+            || {
+                let snapshot = stats::snapshot();
+                assert!(snapshot.write_transaction_eager_conflicts.count() >= 1);
+                assert!(snapshot.rw_closure_time.count() >= 1);
+                assert_eq!(snapshot.named("x::set").unwrap().transactions(), 1);
+                assert!(snapshot.to_json().contains(",\"named\":{\"x::set\":{"));
+                let prometheus = snapshot.to_prometheus();
+                assert!(prometheus.contains("\nswym_transactions_total{name=\"x::set\"} 1\n"));
+                assert!(prometheus
+                    .contains("\nswym_rw_closure_time_seconds_count{name=\"x::set\"} 1\n"));
+                assert_eq!(
+                    prometheus
+                        .matches("# TYPE swym_rw_closure_time_seconds summary\n")
+                        .count(),
+                    1
+                );
+                // each write is at least 3 words
+                let write_word_size = &snapshot.write_word_size;
+                assert!(write_word_size.percentile(50.0).unwrap() >= 3);
+                assert!(
+                    write_word_size.percentile(99.9).unwrap() <= write_word_size.max().unwrap()
+                );
+                assert!(stats::take().write_transaction_eager_conflicts.count() >= 1);
+                stats::reset();
+                assert_eq!(
+                    stats::snapshot().write_transaction_eager_conflicts.count(),
+                    0
+                );
+            },
+        );
+
+        stats::disable(kinds);
+    }
+}
@@ -0,0 +1,165 @@
+mod tarc {
+    use crossbeam_utils::thread;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Arc,
+    };
+    use swym::{
+        tarc::{TArc, TWeak},
+        thread_key,
+        tx::{Ordering, Status},
+    };
+
+    #[derive(Debug)]
+    struct Node {
+        next:  TArc<Node>,
+        drops: Arc<AtomicUsize>,
+    }
+
+    impl Node {
+        fn new(drops: &Arc<AtomicUsize>) -> Self {
+            Node {
+                next:  TArc::empty(),
+                drops: drops.clone(),
+            }
+        }
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            drop(self.drops.fetch_add(1, Relaxed))
+        }
+    }
+
+    #[test]
+    fn share() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let a = TArc::new(Node::new(&drops));
+                let b = TArc::empty();
+                let c = TArc::empty();
+                let thread_key = thread_key::get();
+
+                thread_key.rw(|tx| {
+                    b.clone_from(tx, &a)?;
+                    Ok(c.clone_from(tx, &b)?)
+                });
+                thread_key.read(|tx| {
+                    assert_eq!(a.strong_count(tx)?, 3);
+                    assert!(a.ptr_eq(tx, &c)?);
+                    Ok(())
+                });
+
+                thread_key.rw(|tx| {
+                    a.clear(tx)?;
+                    Ok(b.clear(tx)?)
+                });
+                assert!(thread_key.read(|tx| Ok(c.get(tx, Ordering::default())?.is_some())));
+                assert_eq!(thread_key.read(|tx| Ok(c.strong_count(tx)?)), 1);
+
+                thread_key.rw(|tx| Ok(c.clear(tx)?));
+                assert!(thread_key.read(|tx| Ok(c.get(tx, Ordering::default())?.is_none())));
+            });
+        })
+        .unwrap();
+        assert_eq!(drops.load(Relaxed), 1);
+    }
+
+    #[test]
+    fn weak() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let a = TArc::new(Node::new(&drops));
+                let b = TArc::empty();
+                let weak = TWeak::empty();
+                let thread_key = thread_key::get();
+
+                thread_key.rw(|tx| Ok(weak.downgrade_from(tx, &a)?));
+                assert_eq!(thread_key.read(|tx| Ok(a.weak_count(tx)?)), 1);
+                assert!(thread_key.rw(|tx| Ok(weak.upgrade_into(tx, &b)?)));
+                assert_eq!(thread_key.read(|tx| Ok(a.strong_count(tx)?)), 2);
+
+                thread_key.rw(|tx| {
+                    a.clear(tx)?;
+                    Ok(b.clear(tx)?)
+                });
+                assert!(thread_key.read(|tx| Ok(weak.get(tx, Ordering::default())?.is_none())));
+                assert!(!thread_key.rw(|tx| Ok(weak.upgrade_into(tx, &b)?)));
+            });
+        })
+        .unwrap();
+        assert_eq!(drops.load(Relaxed), 1);
+    }
+
+    #[test]
+    fn abort() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let a = TArc::new(Node::new(&drops));
+        let b = TArc::empty();
+        let thread_key = thread_key::get();
+
+        let result = thread_key.rw_abortable(|tx| {
+            b.clone_from(tx, &a)?;
+            a.clear(tx)?;
+            b.clear(tx)?;
+            Err::<(), _>(Status::abort(()))
+        });
+        assert_eq!(result, Err(()));
+        assert_eq!(thread_key.read(|tx| Ok(a.strong_count(tx)?)), 1);
+        assert_eq!(thread_key.read(|tx| Ok(b.strong_count(tx)?)), 0);
+        assert_eq!(drops.load(Relaxed), 0);
+    }
+
+    #[test]
+    fn release_without_commit() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let a = TArc::new(Node::new(&drops));
+        let mut b = Some(TArc::empty());
+        let mut c = Some(TArc::empty());
+        let thread_key = thread_key::get();
+
+        thread_key.rw(|tx| {
+            b.as_ref().unwrap().clone_from(tx, &a)?;
+            Ok(c.as_ref().unwrap().clone_from(tx, &a)?)
+        });
+        thread_key.read(|_| {
+            drop(b.take());
+            Ok(())
+        });
+        assert_eq!(thread_key.read(|tx| Ok(a.strong_count(tx)?)), 2);
+
+        let result = thread_key.rw_abortable(|_| {
+            drop(c.take());
+            Err::<(), _>(Status::abort(()))
+        });
+        assert_eq!(result, Err(()));
+        assert_eq!(thread_key.read(|tx| Ok(a.strong_count(tx)?)), 1);
+        assert_eq!(drops.load(Relaxed), 0);
+    }
+
+    #[test]
+    fn drop_chain() {
+        const LEN: usize = 10_000;
+        let drops = Arc::new(AtomicUsize::new(0));
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let mut head = TArc::empty();
+                for _ in 0..LEN {
+                    head = TArc::new(Node {
+                        next:  head,
+                        drops: drops.clone(),
+                    });
+                }
+                let thread_key = thread_key::get();
+                assert_eq!(thread_key.read(|tx| Ok(head.strong_count(tx)?)), 1);
+                // dropping each node drops the `TArc` of the next one during garbage collection
+                drop(head);
+                thread_key.rw(|_| Ok(()));
+            });
+        })
+        .unwrap();
+        assert_eq!(drops.load(Relaxed), LEN);
+    }
+}
@@ -0,0 +1,76 @@
+mod common;
+
+mod tbox {
+    use crate::common::DropCount;
+    use crossbeam_utils::thread;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Arc,
+    };
+    use swym::{
+        tbox::TBox,
+        thread_key,
+        tx::{Ordering, Status},
+    };
+
+    #[test]
+    fn set_get() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let x = TBox::from(Box::new(DropCount::new(1, &drops)));
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                let value = thread_key.rw(|tx| {
+                    let value = x.get(tx, Ordering::ReadWrite)?.unwrap().value;
+                    x.set(tx, Box::new(DropCount::new(value + 1, &drops)))
+                        .map_err(|error| error.error)?;
+                    // the pending box is visible inside of the transaction, and dropped when
+                    // overwritten
+                    let next = x.get(tx, Ordering::default())?.unwrap().value;
+                    x.set(tx, Box::new(DropCount::new(next + 1, &drops)))
+                        .map_err(|error| error.error)?;
+                    Ok(next)
+                });
+                assert_eq!(value, 2);
+
+                let value =
+                    thread_key.read(|tx| Ok(x.get(tx, Ordering::default())?.unwrap().value));
+                assert_eq!(value, 3);
+
+                thread_key.rw(|tx| Ok(x.clear(tx)?));
+                assert!(thread_key.read(|tx| Ok(x.get(tx, Ordering::default())?.is_none())));
+            });
+        })
+        .unwrap();
+        // the privatized boxes are collected by the time the thread exits
+        assert_eq!(drops.load(Relaxed), 3);
+        assert!(x.into_inner().is_none());
+    }
+
+    #[test]
+    fn abort() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let x = TBox::empty();
+        let thread_key = thread_key::get();
+
+        let result = thread_key.rw_abortable(|tx| {
+            x.set(tx, Box::new(DropCount::new(1, &drops)))
+                .map_err(|error| error.error)?;
+            Err::<(), _>(Status::abort("nope"))
+        });
+        assert!(result.is_err());
+        assert_eq!(drops.load(Relaxed), 1);
+        assert!(x.into_inner().is_none());
+    }
+
+    #[test]
+    fn into_inner() {
+        let mut x = TBox::new(vec![1, 2]);
+        x.get_mut().unwrap().push(3);
+        assert_eq!(*x.into_inner().unwrap(), [1, 2, 3]);
+
+        let mut y = TBox::<i32>::default();
+        assert!(y.get_mut().is_none());
+        assert!(y.into_inner().is_none());
+    }
+}
@@ -0,0 +1,105 @@
+mod common;
+
+mod tcell {
+    use crate::common::DropCount;
+    use crossbeam_utils::thread;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Arc, Mutex,
+    };
+    use swym::{
+        tcell::{Ref, TCell},
+        thread_key,
+        tx::{Ordering, Status},
+    };
+
+    #[test]
+    fn update() {
+        let x = TCell::new(1);
+        let y = TCell::new(2);
+        let thread_key = thread_key::get();
+
+        thread_key.rw(|tx| {
+            x.update(tx, |x| x + 10)?;
+            // later operations see the pending value
+            assert_eq!(x.fetch_update(tx, |x| x * 2)?, 11);
+            Ok(x.swap(tx, &y)?)
+        });
+        assert_eq!(thread_key.read(|tx| Ok(x.get(tx, Ordering::default())?)), 2);
+        assert_eq!(
+            thread_key.read(|tx| Ok(y.get(tx, Ordering::default())?)),
+            22
+        );
+
+        thread_key.rw(|tx| Ok(x.swap(tx, &x)?));
+        assert_eq!(thread_key.rw(|tx| Ok(y.take(tx)?)), 22);
+        assert_eq!(y.into_inner(), 0);
+    }
+
+    #[test]
+    fn replace_and_privatize() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let privatized = Arc::new(Mutex::new(Vec::new()));
+        let x = TCell::new(DropCount::new(0, &drops));
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                let replace = |tx: &mut _, value| {
+                    let privatized = privatized.clone();
+                    x.replace_and_privatize(
+                        tx,
+                        DropCount::new(value, &drops),
+                        move |prev: DropCount| privatized.lock().unwrap().push(prev.value),
+                    )
+                };
+
+                let result = thread_key.rw_abortable(|tx| {
+                    replace(tx, 1).map_err(|error| error.error)?;
+                    Err::<(), _>(Status::abort(()))
+                });
+                assert_eq!(result, Err(()));
+                // only the new value was dropped
+                assert_eq!(drops.load(Relaxed), 1);
+
+                thread_key.rw(|tx| {
+                    replace(tx, 2).map_err(|error| error.error)?;
+                    // the pending value is privatized as well
+                    replace(tx, 3).map_err(|error| error.error)?;
+                    Ok(())
+                });
+            });
+        })
+        .unwrap();
+        // the privatizers have run by the time the thread exits
+        assert_eq!(drops.load(Relaxed), 3);
+        let mut privatized = privatized.lock().unwrap().clone();
+        privatized.sort();
+        assert_eq!(privatized, [0, 2]);
+        assert_eq!(Arc::strong_count(&drops), 2);
+        assert_eq!(x.into_inner().value, 3);
+    }
+
+    #[test]
+    fn ref_map() {
+        let x = TCell::new((String::from("a"), Some(vec![1, 2])));
+        let thread_key = thread_key::get();
+        thread_key.rw(|tx| {
+            x.set(tx, (String::from("b"), None))?;
+            // maps the pending value from the write log
+            let name = Ref::map(x.borrow(tx, Ordering::default())?, |x| &x.0);
+            assert_eq!(*name, "b");
+            let tail = Ref::filter_map(x.borrow(tx, Ordering::default())?, |x| x.1.as_ref());
+            assert!(tail.is_err());
+            Ok(())
+        });
+
+        thread_key.rw(|tx| Ok(x.set(tx, (String::from("c"), Some(vec![3, 4])))?));
+        let last = thread_key.read(|tx| {
+            let x = x.borrow(tx, Ordering::default())?;
+            Ok(Ref::filter_map(x, |x| x.1.as_ref().and_then(|v| v.last()))
+                .map(|last| *last)
+                .ok())
+        });
+        assert_eq!(last, Some(4));
+    }
+}
@@ -0,0 +1,79 @@
+mod tptr {
+    use crossbeam_utils::thread;
+    use std::sync::{Arc, Mutex};
+    use swym::{
+        thread_key,
+        tptr::{Publisher, TPtr},
+        tx::{Ordering, Status},
+    };
+
+    type Pool = Arc<Mutex<Vec<Box<usize>>>>;
+
+    unsafe fn return_to(pool: Pool) -> impl FnOnce(*mut usize) + Send + 'static {
+        move |ptr| pool.lock().unwrap().push(Box::from_raw(ptr))
+    }
+
+    #[test]
+    fn privatize_into_pool() {
+        let pool = Pool::default();
+        let x = TPtr::new(Box::into_raw(Box::new(1)));
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+
+                let result = thread_key.rw_abortable(|tx| {
+                    let prev = x.as_ptr(tx, Ordering::ReadWrite)?;
+                    unsafe { TPtr::privatize(tx, prev, return_to(pool.clone())) };
+                    Err::<(), _>(Status::abort(()))
+                });
+                assert_eq!(result, Err(()));
+                // the privatizer of the aborted transaction was dropped without being called
+                assert_eq!(Arc::strong_count(&pool), 1);
+
+                thread_key.rw(|tx| {
+                    let prev = x.as_ptr(tx, Ordering::ReadWrite)?;
+                    unsafe { TPtr::privatize(tx, prev, return_to(pool.clone())) };
+                    x.publish_box(tx, Box::new(2))
+                        .map_err(|error| error.error)?;
+                    Ok(())
+                });
+            });
+        })
+        .unwrap();
+        assert_eq!(Arc::strong_count(&pool), 1);
+        assert_eq!(*pool.lock().unwrap(), [Box::new(1)]);
+        drop(unsafe { Box::from_raw(x.into_inner() as *mut usize) });
+    }
+
+    #[test]
+    fn publish_capturing_destructor() {
+        let pool = Pool::default();
+        let x = TPtr::null();
+        let thread_key = thread_key::get();
+
+        let result = thread_key.rw_abortable(|tx| {
+            let publisher = Publisher::new(Box::into_raw(Box::new(1)), unsafe {
+                return_to(pool.clone())
+            });
+            x.publish(tx, publisher).map_err(|error| error.error)?;
+            Err::<(), _>(Status::abort(()))
+        });
+        assert_eq!(result, Err(()));
+        assert_eq!(Arc::strong_count(&pool), 1);
+        assert_eq!(*pool.lock().unwrap(), [Box::new(1)]);
+
+        thread_key.rw(|tx| {
+            let publisher = Publisher::new(Box::into_raw(Box::new(2)), unsafe {
+                return_to(pool.clone())
+            });
+            x.publish(tx, publisher).map_err(|error| error.error)?;
+            Ok(())
+        });
+        // the destructor was dropped without being called
+        assert_eq!(Arc::strong_count(&pool), 1);
+        assert_eq!(pool.lock().unwrap().len(), 1);
+        let ptr = thread_key.read(|tx| Ok(x.as_ptr(tx, Ordering::default())?));
+        assert_eq!(unsafe { *ptr }, 2);
+        drop(unsafe { Box::from_raw(ptr as *mut usize) });
+    }
+}
@@ -0,0 +1,84 @@
+mod common;
+
+mod tx_info {
+    use crate::common::interleave;
+    use std::sync::atomic::Ordering::Relaxed;
+    use swym::{
+        tcell::TCell,
+        thread_key,
+        tx::{Ordering, Status},
+    };
+
+    #[test]
+    fn rw() {
+        let x = TCell::new(0);
+        let y = TCell::new(0);
+        let thread_key = thread_key::get();
+
+        let ((), info) = thread_key.rw_with_info(|tx| {
+            let x_value = x.get(tx, Ordering::ReadWrite)?;
+            Ok(y.set(tx, x_value + 1)?)
+        });
+        assert_eq!(info.conflicts(), 0);
+        assert!(!info.parked());
+        assert_eq!(info.read_log_len(), 1);
+        // each write is at least 3 words
+        assert!(info.write_log_word_len() >= 3);
+
+        let ((), next_info) = thread_key.rw_with_info(|tx| Ok(y.set(tx, 2)?));
+        assert!(next_info.commit_epoch() > info.commit_epoch());
+
+        let (y_value, read_info) =
+            thread_key.read_with_info(|tx| Ok(y.get(tx, Ordering::default())?));
+        assert_eq!(y_value, 2);
+        assert!(read_info.commit_epoch() >= next_info.commit_epoch());
+        assert!(!read_info.htm_committed());
+        assert_eq!(read_info.read_log_len(), 0);
+    }
+
+    #[test]
+    fn parked() {
+        let flag = TCell::new(false);
+        interleave(
+            |waiting, _| {
+                let ((), info) = thread_key::get().rw_with_info(|tx| {
+                    if flag.get(tx, Ordering::default())? {
+                        Ok(())
+                    } else {
+                        waiting.store(true, Relaxed);
+                        Err(Status::AWAIT_RETRY)
+                    }
+                });
+                assert!(info.parked());
+                assert_eq!(info.read_log_len(), 1);
+            },
+            || thread_key::get().rw(|tx| Ok(flag.set(tx, true)?)),
+        );
+    }
+
+    #[test]
+    fn try_nested() {
+        let thread_key = thread_key::get();
+        thread_key.read(|_| {
+            assert!(thread_key.try_rw_with_info(|_| Ok(())).is_err());
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn nested() {
+        let x = TCell::new(0);
+        let thread_key = thread_key::get();
+        thread_key.rw(|tx| {
+            x.set(tx, 1)?;
+            let ((), info) = thread_key.rw_with_info(|tx| Ok(x.set(tx, 2)?));
+            assert_eq!(info.conflicts(), 0);
+            assert!(info.write_log_word_len() > 0);
+            let (value, info) = thread_key.read_with_info(|tx| Ok(x.get(tx, Default::default())?));
+            assert_eq!(value, 2);
+            assert_eq!(info.write_log_word_len(), 0);
+            Ok(())
+        });
+        assert_eq!(x.into_inner(), 2);
+    }
+}
@@ -1,6 +1,15 @@
 mod unpark {
     use crossbeam_utils::thread;
-    use swym::{tcell::TCell, thread_key, tx::Status};
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering::Relaxed},
+        time::{Duration, Instant},
+    };
+    use swym::{
+        tcell::TCell,
+        thread_key::{self, AwaitErr, CancelHandle},
+        tx::Status,
+        RwTx,
+    };
 
     #[cfg(debug_assertions)]
     #[test]
@@ -83,4 +92,230 @@ mod unpark {
         }
         swym::stats::print_stats()
     }
+
+    // A consumer takes from either `a` or `b`, blocking only when both are empty. The writes of a
+    // retried alternative must never be committed.
+    #[test]
+    fn or_else() {
+        const ITER: usize = 10_000;
+
+        std::thread::spawn(|| {
+            std::thread::sleep(std::time::Duration::from_secs(60));
+            std::process::abort();
+        });
+
+        fn take<'tcell>(
+            tx: &mut RwTx<'tcell>,
+            queue: &'tcell TCell<Option<usize>>,
+            discarded: &'tcell TCell<bool>,
+        ) -> Result<usize, Status> {
+            discarded.set(tx, true)?;
+            match queue.get(tx, Default::default())? {
+                Some(x) => {
+                    discarded.set(tx, false)?;
+                    queue.set(tx, None)?;
+                    Ok(x)
+                }
+                None => Err(Status::AWAIT_RETRY),
+            }
+        }
+
+        let a = TCell::new(None);
+        let b = TCell::new(None);
+        let discarded = TCell::new(false);
+
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let key = thread_key::get();
+                for x in 0..ITER {
+                    let queue = if x % 3 == 0 { &a } else { &b };
+                    key.rw(|tx| {
+                        if queue.get(tx, Default::default())?.is_some() {
+                            Err(Status::AWAIT_RETRY)
+                        } else {
+                            Ok(queue.set(tx, Some(x))?)
+                        }
+                    });
+                }
+            });
+            s.spawn(|_| {
+                let key = thread_key::get();
+                let mut sum = 0;
+                for _ in 0..ITER {
+                    sum += key.rw(|tx| {
+                        let x = tx.or_else(
+                            |tx| take(tx, &a, &discarded),
+                            |tx| take(tx, &b, &discarded),
+                        )?;
+                        assert!(!discarded.get(tx, Default::default())?);
+                        Ok(x)
+                    });
+                }
+                assert_eq!(sum, (0..ITER).sum());
+            });
+        })
+        .unwrap();
+        assert!(!discarded.into_inner());
+    }
+
+    #[test]
+    fn timeout() {
+        let ready = TCell::new(false);
+        let written = TCell::new(0);
+
+        let key = thread_key::get();
+        let deadline = Instant::now() + Duration::from_millis(50);
+        let result = key.rw_timeout(deadline, |tx| {
+            written.set(tx, 42)?;
+            if ready.get(tx, Default::default())? {
+                Ok(())
+            } else {
+                Err(Status::AWAIT_RETRY)
+            }
+        });
+        assert_eq!(result, Err(AwaitErr::TimedOut));
+        assert!(Instant::now() >= deadline);
+        assert_eq!(written.into_inner(), 0);
+    }
+
+    #[test]
+    fn timeout_not_reached() {
+        let ready = TCell::new(false);
+
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let key = thread_key::get();
+                let deadline = Instant::now() + Duration::from_secs(30);
+                let result = key.rw_timeout(deadline, |tx| {
+                    if ready.get(tx, Default::default())? {
+                        Ok(())
+                    } else {
+                        Err(Status::AWAIT_RETRY)
+                    }
+                });
+                assert_eq!(result, Ok(()));
+            });
+            std::thread::sleep(Duration::from_millis(10));
+            thread_key::get().rw(|tx| Ok(ready.set(tx, true)?));
+        })
+        .unwrap();
+    }
+
+    // A read only transaction waits for a producer, which only writes once the reader has read the
+    // previous value.
+    #[test]
+    fn read() {
+        const ITER: usize = 1_000;
+
+        std::thread::spawn(|| {
+            std::thread::sleep(std::time::Duration::from_secs(60));
+            std::process::abort();
+        });
+
+        let x = TCell::new(0);
+        let seen = TCell::new(0);
+
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let key = thread_key::get();
+                for i in 1..=ITER {
+                    key.rw(|tx| {
+                        if seen.get(tx, Default::default())? != i - 1 {
+                            Err(Status::AWAIT_RETRY)
+                        } else {
+                            Ok(x.set(tx, i)?)
+                        }
+                    });
+                }
+            });
+            s.spawn(|_| {
+                let key = thread_key::get();
+                for i in 1..=ITER {
+                    let next = key.read_awaitable(|tx| {
+                        let next = x.get(tx, Default::default())?;
+                        if next < i {
+                            Err(Status::AWAIT_RETRY)
+                        } else {
+                            Ok(next)
+                        }
+                    });
+                    assert_eq!(next, i);
+                    key.rw(|tx| Ok(seen.set(tx, next)?));
+                }
+            });
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn nested() {
+        let ready = TCell::new(false);
+
+        thread::scope(|s| {
+            s.spawn(|_| {
+                thread_key::get().rw(|tx| {
+                    tx.nested(|tx| {
+                        if ready.get(tx, Default::default())? {
+                            Ok(())
+                        } else {
+                            Err(Status::AWAIT_RETRY)
+                        }
+                    })
+                })
+            });
+            std::thread::sleep(Duration::from_millis(10));
+            thread_key::get().rw(|tx| Ok(ready.set(tx, true)?));
+        })
+        .unwrap();
+    }
+
+    // Cancelling a handle only wakes the transactions using that handle.
+    #[test]
+    fn cancel() {
+        const WAITERS: usize = 4;
+
+        std::thread::spawn(|| {
+            std::thread::sleep(std::time::Duration::from_secs(60));
+            std::process::abort();
+        });
+
+        let ready = TCell::new(false);
+        let cancel = CancelHandle::new();
+        let other = CancelHandle::new();
+        let cancelled = AtomicUsize::new(0);
+
+        let wait = |handle: &CancelHandle| {
+            thread_key::get().rw_cancellable(handle, None, |tx| {
+                if ready.get(tx, Default::default())? {
+                    Ok(())
+                } else {
+                    Err(Status::AWAIT_RETRY)
+                }
+            })
+        };
+
+        thread::scope(|s| {
+            for _ in 0..WAITERS {
+                s.spawn(|_| {
+                    assert_eq!(wait(&cancel), Err(AwaitErr::Cancelled));
+                    cancelled.fetch_add(1, Relaxed);
+                });
+            }
+            s.spawn(|_| assert_eq!(wait(&other), Ok(())));
+
+            while cancelled.load(Relaxed) < WAITERS {
+                cancel.cancel();
+                std::thread::yield_now();
+            }
+            assert!(cancel.is_cancelled());
+            assert!(!other.is_cancelled());
+            thread_key::get().rw(|tx| Ok(ready.set(tx, true)?));
+        })
+        .unwrap();
+
+        // transactions which do not wait are unaffected by cancellation
+        assert_eq!(wait(&cancel), Ok(()));
+        thread_key::get().rw(|tx| Ok(ready.set(tx, false)?));
+        assert_eq!(wait(&cancel), Err(AwaitErr::Cancelled));
+    }
 }
@@ -0,0 +1,95 @@
+mod upgrade {
+    use crossbeam_utils::thread;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed};
+    use swym::{
+        tcell::TCell,
+        thread_key,
+        tx::{Ordering, Status},
+    };
+
+    // Threads only write when the counter is even, so lost updates would leave it short.
+    #[test]
+    fn counter() {
+        const ITER_COUNT: usize = 1_000;
+        const THREAD_COUNT: usize = 4;
+
+        let x = TCell::new(0);
+        let odd = TCell::new(0);
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        thread_key.read_upgradable(|tx| {
+                            let next = x.get(tx, Ordering::default())? + 1;
+                            tx.upgrade(|tx| Ok(x.set(tx, next)?))?;
+                            if next % 2 == 1 {
+                                let odd_count = odd.get(tx, Ordering::default())?;
+                                tx.upgrade(|tx| Ok(odd.set(tx, odd_count + 1)?))?;
+                            }
+                            Ok(())
+                        });
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(x.into_inner(), ITER_COUNT * THREAD_COUNT);
+        assert_eq!(odd.into_inner(), ITER_COUNT * THREAD_COUNT / 2);
+    }
+
+    #[test]
+    fn read_own_writes() {
+        let attempts = AtomicUsize::new(0);
+        let x = TCell::new(String::from("before"));
+
+        let thread_key = thread_key::get();
+        thread_key.read_upgradable(|tx| {
+            drop(attempts.fetch_add(1, Relaxed));
+            assert_eq!(*x.borrow(tx, Ordering::default())?, "before");
+            tx.upgrade(|tx| Ok(x.set(tx, String::from("first"))?))?;
+            assert_eq!(*x.borrow(tx, Ordering::default())?, "first");
+            tx.upgrade(|tx| Ok(x.set(tx, String::from("second"))?))?;
+            assert_eq!(*x.borrow(tx, Ordering::default())?, "second");
+            Ok(())
+        });
+        // upgrading does not restart the transaction
+        assert_eq!(attempts.load(Relaxed), 1);
+        assert_eq!(x.into_inner(), "second");
+    }
+
+    // An upgrade that waits discards its writes, and the transaction waits on its reads.
+    #[test]
+    fn await_retry() {
+        let ready = TCell::new(false);
+        let x = TCell::new(0);
+        let waiting = AtomicBool::new(false);
+
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let attempts = AtomicUsize::new(0);
+                thread_key::get().read_upgradable(|tx| {
+                    drop(attempts.fetch_add(1, Relaxed));
+                    let ready = ready.get(tx, Ordering::default())?;
+                    tx.upgrade(|tx| {
+                        x.set(tx, 1)?;
+                        if ready {
+                            Ok(())
+                        } else {
+                            waiting.store(true, Relaxed);
+                            Err(Status::AWAIT_RETRY)
+                        }
+                    })?;
+                    Ok(())
+                });
+                assert!(attempts.load(Relaxed) >= 2);
+            });
+            while !waiting.load(Relaxed) {
+                std::thread::yield_now()
+            }
+            thread_key::get().rw(|tx| Ok(ready.set(tx, true)?));
+        })
+        .unwrap();
+        assert_eq!(x.into_inner(), 1);
+    }
+}